@@ -0,0 +1,54 @@
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use bevy_wasm_game::{headless::*, replication::*, soak::SoakMonitor};
+
+/// Runs the simulation without a window, see `HeadlessConfig` for the options.
+/// With `--serve` it is a dedicated replication server. A soak run that broke
+/// an invariant exits with status 1.
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = match HeadlessConfig::from_args(&args) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        }
+    };
+    let mut app = match headless_app(&config) {
+        Ok(app) => app,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    app.add_plugins(LogPlugin::default())
+        .add_systems(Last, print_summary.after(finish_headless_run));
+    ReplicationSystems::add_to_app(&mut app);
+    if let Err(error) = ReplicationConfig::from_args(&args)
+        .and_then(|network| network.map_or(Ok(()), |n| n.start(&mut app.world)))
+    {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+
+    if config.realtime {
+        app.run();
+    } else {
+        run_headless(&mut app);
+    }
+    if app
+        .world
+        .get_resource::<SoakMonitor>()
+        .is_some_and(|monitor| monitor.failed())
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Prints the final state once the run has ended
+fn print_summary(run: Res<HeadlessRun>, mut printed: Local<bool>) {
+    if let Some(summary) = run.summary.as_ref().filter(|_| !*printed) {
+        print!("{}", summary);
+        *printed = true;
+    }
+}
@@ -0,0 +1,226 @@
+use crate::collectibles::Collectible;
+use crate::components::{MovementIntent, Player};
+use crate::power_ups::PowerUp;
+use crate::replay::{play_input_replay, record_inputs};
+use crate::resources::WorldBounds;
+use crate::rng::SeededRng;
+use crate::systems::PhysicsStep;
+use bevy::prelude::*;
+use std::str::FromStr;
+
+/// How a bot picks its movement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BotBehavior {
+    /// Heads in a random direction for a while, sometimes boosting
+    RandomWalk,
+    /// Steers toward the nearest collectible or power-up, walks randomly
+    /// while there is none
+    ChasePickups,
+    /// Heads for the nearest wall and follows it around the arena
+    WallHug,
+}
+
+impl FromStr for BotBehavior {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "random-walk" => Ok(BotBehavior::RandomWalk),
+            "chase-pickups" => Ok(BotBehavior::ChasePickups),
+            "wall-hug" => Ok(BotBehavior::WallHug),
+            other => Err(format!(
+                "unknown bot '{}', expected random-walk, chase-pickups or wall-hug",
+                other
+            )),
+        }
+    }
+}
+
+/// Drives the entity's `MovementIntent` instead of a player. Each bot rolls
+/// its own generator, so bots never shift the gameplay random sequence.
+#[derive(Component, Debug, Clone)]
+pub struct Bot {
+    pub behavior: BotBehavior,
+    rng: SeededRng,
+    /// Ticks left before the next decision
+    hold: u32,
+    intent: MovementIntent,
+}
+
+impl Bot {
+    pub fn new(behavior: BotBehavior, seed: u64) -> Self {
+        Self {
+            behavior,
+            rng: SeededRng::new(seed),
+            hold: 0,
+            intent: MovementIntent::default(),
+        }
+    }
+
+    /// Intent for the next tick
+    pub fn decide(&mut self, senses: &BotSenses) -> MovementIntent {
+        match self.behavior {
+            BotBehavior::RandomWalk => self.random_walk(),
+            BotBehavior::ChasePickups => match senses.nearest_pickup() {
+                Some(target) => {
+                    // Pick the walk back up fresh once the pickups are gone
+                    self.hold = 0;
+                    let offset = target - senses.position;
+                    MovementIntent {
+                        direction: offset.normalize_or_zero(),
+                        boost: offset.length() > CHASE_BOOST_DISTANCE,
+                        mouse_boost: false,
+                    }
+                }
+                None => self.random_walk(),
+            },
+            BotBehavior::WallHug => {
+                let (normal, distance) = senses.nearest_wall();
+                let direction = if distance > WALL_HUG_DISTANCE {
+                    normal
+                } else {
+                    // Counterclockwise along the wall, leaning into it
+                    (normal.perp() + normal * 0.3).normalize()
+                };
+                MovementIntent {
+                    direction,
+                    boost: false,
+                    mouse_boost: false,
+                }
+            }
+        }
+    }
+
+    fn random_walk(&mut self) -> MovementIntent {
+        if self.hold == 0 {
+            let angle = self
+                .rng
+                .range_f32(-std::f32::consts::PI, std::f32::consts::PI);
+            self.intent = MovementIntent {
+                direction: Vec2::from_angle(angle),
+                boost: self.rng.chance(0.25),
+                mouse_boost: false,
+            };
+            self.hold = 20 + self.rng.below(60);
+        }
+        self.hold -= 1;
+        self.intent
+    }
+}
+
+/// Pickups further away than this are chased with a boost
+const CHASE_BOOST_DISTANCE: f32 = 300.0;
+/// Wall huggers closer to a wall than this follow it
+const WALL_HUG_DISTANCE: f32 = 60.0;
+
+/// What a bot sees when it decides
+#[derive(Debug, Clone, Copy)]
+pub struct BotSenses<'a> {
+    pub position: Vec2,
+    /// Collectibles and power-ups
+    pub pickups: &'a [Vec2],
+    pub bounds: &'a WorldBounds,
+}
+
+impl BotSenses<'_> {
+    pub fn nearest_pickup(&self) -> Option<Vec2> {
+        self.pickups.iter().copied().min_by(|a, b| {
+            a.distance_squared(self.position)
+                .total_cmp(&b.distance_squared(self.position))
+        })
+    }
+
+    /// Direction toward the closest wall and the distance to it
+    pub fn nearest_wall(&self) -> (Vec2, f32) {
+        let (min, max, position) = (self.bounds.min, self.bounds.max, self.position);
+        [
+            (Vec2::NEG_X, position.x - min.x),
+            (Vec2::X, max.x - position.x),
+            (Vec2::NEG_Y, position.y - min.y),
+            (Vec2::Y, max.y - position.y),
+        ]
+        .into_iter()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap()
+    }
+}
+
+/// Every player gets a bot with this behavior while the resource exists
+#[derive(Resource, Debug, Clone)]
+pub struct BotSettings {
+    pub behavior: BotBehavior,
+    pub seed: u64,
+    /// Bots handed out so far, each gets its own seed
+    pub spawned: u64,
+}
+
+impl BotSettings {
+    pub fn new(behavior: BotBehavior, seed: u64) -> Self {
+        Self {
+            behavior,
+            seed,
+            spawned: 0,
+        }
+    }
+}
+
+/// Gives players without a bot one, including respawned players
+pub fn attach_bots(
+    mut commands: Commands,
+    mut settings: ResMut<BotSettings>,
+    players: Query<Entity, (With<Player>, Without<Bot>)>,
+) {
+    let mut players: Vec<Entity> = players.iter().collect();
+    players.sort();
+    for entity in players {
+        let seed = settings.seed.wrapping_add(settings.spawned);
+        settings.spawned += 1;
+        commands
+            .entity(entity)
+            .insert(Bot::new(settings.behavior, seed));
+    }
+}
+
+/// Pickups bots can chase
+pub type PickupQuery<'w, 's> =
+    Query<'w, 's, &'static Transform, Or<(With<Collectible>, With<PowerUp>)>>;
+
+/// Bots write their decisions into the intents `handle_input` would set
+pub fn drive_bots(
+    bounds: Res<WorldBounds>,
+    pickups: PickupQuery,
+    mut bots: Query<(&mut Bot, &Transform, &mut MovementIntent)>,
+) {
+    let pickups: Vec<Vec2> = pickups
+        .iter()
+        .map(|transform| transform.translation.truncate())
+        .collect();
+    for (mut bot, transform, mut intent) in bots.iter_mut() {
+        *intent = bot.decide(&BotSenses {
+            position: transform.translation.truncate(),
+            pickups: &pickups,
+            bounds: &bounds,
+        });
+    }
+}
+
+/// Bundle bot control for easy registration. Bots are attached while a
+/// `BotSettings` resource exists.
+pub struct BotSystems;
+
+impl BotSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.add_systems(
+            FixedUpdate,
+            (
+                attach_bots.run_if(resource_exists::<BotSettings>()),
+                apply_deferred,
+                drive_bots,
+            )
+                .chain()
+                .after(play_input_replay)
+                .before(record_inputs)
+                .in_set(PhysicsStep::Begin),
+        )
+    }
+}
@@ -0,0 +1,254 @@
+use crate::resources::WorldBounds;
+use crate::systems::WallImpact;
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy::window::PrimaryWindow;
+
+/// View size used when there is no window, such as in headless runs
+const FALLBACK_VIEW_SIZE: Vec2 = Vec2::new(1280.0, 720.0);
+
+/// Entities the camera keeps in view
+#[derive(Component, Debug, Default)]
+pub struct CameraTarget;
+
+/// Follows every `CameraTarget`, zooming out to fit them all
+#[derive(Component, Debug, Clone)]
+pub struct CameraFollow {
+    /// How quickly the camera catches up, higher is snappier
+    pub smoothing: f32,
+    /// Half extents of the box around the camera focus the targets can move in freely
+    pub dead_zone: Vec2,
+    /// World space kept around the targets when zooming to fit
+    pub padding: f32,
+    /// Smallest and largest projection scale, 1.0 is one world unit per pixel
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    /// How far past the world bounds the view may show
+    pub bounds_margin: f32,
+    /// Point the camera looks at before screen shake is applied
+    pub focus: Vec2,
+    pub zoom: f32,
+}
+
+impl Default for CameraFollow {
+    fn default() -> Self {
+        Self {
+            smoothing: 5.0,
+            dead_zone: Vec2::new(80.0, 60.0),
+            padding: 150.0,
+            min_zoom: 1.0,
+            max_zoom: 2.5,
+            bounds_margin: 50.0,
+            focus: Vec2::ZERO,
+            zoom: 1.0,
+        }
+    }
+}
+
+/// Trauma based screen shake. Trauma decays linearly and the shake strength
+/// grows with its square, so small hits barely register and big hits stack up.
+#[derive(Component, Debug, Clone)]
+pub struct CameraShake {
+    /// Current trauma in 0..=1
+    pub trauma: f32,
+    /// Trauma lost per second
+    pub decay: f32,
+    /// Largest offset in world units at full trauma
+    pub max_offset: f32,
+    /// Largest roll in radians at full trauma
+    pub max_angle: f32,
+    /// Speed of the shake noise
+    pub frequency: f32,
+    time: f32,
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self {
+            trauma: 0.0,
+            decay: 1.2,
+            max_offset: 24.0,
+            max_angle: 0.05,
+            frequency: 30.0,
+            time: 0.0,
+        }
+    }
+}
+
+impl CameraShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    /// Advance the shake and return the offset and roll to apply this frame
+    pub fn advance(&mut self, delta: f32) -> (Vec2, f32) {
+        self.time += delta;
+        self.trauma = (self.trauma - self.decay * delta).max(0.0);
+
+        let strength = self.trauma * self.trauma;
+        let t = self.time * self.frequency;
+        let offset = Vec2::new(shake_noise(t, 0.0), shake_noise(t, 17.0)) * self.max_offset;
+        (
+            offset * strength,
+            shake_noise(t, 41.0) * self.max_angle * strength,
+        )
+    }
+}
+
+/// Smooth noise in -1..=1, a different curve for each `seed`
+fn shake_noise(t: f32, seed: f32) -> f32 {
+    ((t + seed).sin() + (t * 2.3 + seed * 1.7).sin() * 0.5) / 1.5
+}
+
+/// Adds trauma to every shaking camera
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ShakeEvent {
+    pub trauma: f32,
+}
+
+/// How wall hits translate into screen shake
+#[derive(Resource, Debug, Clone)]
+pub struct ImpactShakeSettings {
+    /// Hits slower than this do not shake
+    pub min_speed: f32,
+    /// Hits this fast or faster add `max_trauma`
+    pub full_speed: f32,
+    pub max_trauma: f32,
+}
+
+impl Default for ImpactShakeSettings {
+    fn default() -> Self {
+        Self {
+            min_speed: 600.0,
+            full_speed: 1800.0,
+            max_trauma: 0.6,
+        }
+    }
+}
+
+impl ImpactShakeSettings {
+    pub fn trauma_for(&self, speed: f32) -> f32 {
+        if speed < self.min_speed {
+            return 0.0;
+        }
+        let t = (speed - self.min_speed) / (self.full_speed - self.min_speed).max(f32::EPSILON);
+        t.min(1.0) * self.max_trauma
+    }
+}
+
+/// Move `focus` just enough that `target` is back inside the dead zone
+pub fn apply_dead_zone(focus: Vec2, target: Vec2, dead_zone: Vec2) -> Vec2 {
+    let offset = target - focus;
+    focus + offset - offset.clamp(-dead_zone, dead_zone)
+}
+
+/// Projection scale that fits a box of `extent` plus padding into the view
+pub fn fit_zoom(extent: Vec2, view_size: Vec2, follow: &CameraFollow) -> f32 {
+    let needed = (extent + Vec2::splat(follow.padding * 2.0)) / view_size;
+    needed.max_element().clamp(follow.min_zoom, follow.max_zoom)
+}
+
+/// Keep the view inside the bounds plus margin. On an axis where the view is
+/// larger than the bounds the camera centers on the bounds instead.
+pub fn clamp_focus(focus: Vec2, half_view: Vec2, bounds: &WorldBounds, margin: f32) -> Vec2 {
+    let min = bounds.min - Vec2::splat(margin) + half_view;
+    let max = bounds.max + Vec2::splat(margin) - half_view;
+    let center = (bounds.min + bounds.max) / 2.0;
+    let clamp_axis = |value: f32, min: f32, max: f32, center: f32| {
+        if min > max {
+            center
+        } else {
+            value.clamp(min, max)
+        }
+    };
+    Vec2::new(
+        clamp_axis(focus.x, min.x, max.x, center.x),
+        clamp_axis(focus.y, min.y, max.y, center.y),
+    )
+}
+
+/// Follow the camera targets with dead zone, smoothing, zoom to fit and bounds clamping
+pub fn camera_follow(
+    time: Res<Time>,
+    bounds: Res<WorldBounds>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    targets: Query<&Transform, (With<CameraTarget>, Without<CameraFollow>)>,
+    mut cameras: Query<(&mut CameraFollow, &mut OrthographicProjection)>,
+) {
+    let view_size = windows
+        .get_single()
+        .map(|window| Vec2::new(window.width(), window.height()))
+        .unwrap_or(FALLBACK_VIEW_SIZE);
+    let smoothing = |rate: f32| 1.0 - (-rate * time.delta_seconds()).exp();
+
+    let mut positions = targets.iter().map(|t| t.translation.truncate());
+    let Some(first) = positions.next() else {
+        return;
+    };
+    let (min, max) = positions.fold((first, first), |(min, max), p| (min.min(p), max.max(p)));
+    let center = (min + max) / 2.0;
+
+    for (mut follow, mut projection) in cameras.iter_mut() {
+        let target_zoom = fit_zoom(max - min, view_size, &follow);
+        follow.zoom += (target_zoom - follow.zoom) * smoothing(follow.smoothing);
+        projection.scale = follow.zoom;
+
+        let desired = apply_dead_zone(follow.focus, center, follow.dead_zone);
+        let focus = follow.focus + (desired - follow.focus) * smoothing(follow.smoothing);
+        let half_view = view_size * follow.zoom / 2.0;
+        follow.focus = clamp_focus(focus, half_view, &bounds, follow.bounds_margin);
+    }
+}
+
+/// Hard wall hits and explicit shake events add trauma
+pub fn collect_camera_trauma(
+    settings: Res<ImpactShakeSettings>,
+    mut impacts: EventReader<WallImpact>,
+    mut shake_events: EventReader<ShakeEvent>,
+    mut cameras: Query<&mut CameraShake>,
+) {
+    let trauma: f32 = impacts
+        .iter()
+        .map(|impact| settings.trauma_for(impact.speed))
+        .chain(shake_events.iter().map(|event| event.trauma))
+        .sum();
+    if trauma <= 0.0 {
+        return;
+    }
+
+    for mut shake in cameras.iter_mut() {
+        shake.add_trauma(trauma);
+    }
+}
+
+/// Writes the camera transform from the follow focus plus the current shake
+pub fn apply_camera_transform(
+    time: Res<Time>,
+    mut cameras: Query<(&CameraFollow, Option<&mut CameraShake>, &mut Transform)>,
+) {
+    for (follow, shake, mut transform) in cameras.iter_mut() {
+        let (offset, angle) = shake
+            .map(|mut shake| shake.advance(time.delta_seconds()))
+            .unwrap_or_default();
+        transform.translation.x = follow.focus.x + offset.x;
+        transform.translation.y = follow.focus.y + offset.y;
+        transform.rotation = Quat::from_rotation_z(angle);
+    }
+}
+
+/// Bundle the camera controller for easy registration
+pub struct CameraSystems;
+
+impl CameraSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.init_resource::<ImpactShakeSettings>()
+            .add_event::<ShakeEvent>()
+            .add_event::<WallImpact>()
+            .add_systems(
+                PostUpdate,
+                (camera_follow, collect_camera_trauma, apply_camera_transform)
+                    .chain()
+                    .before(TransformSystem::TransformPropagate),
+            )
+    }
+}
@@ -0,0 +1,309 @@
+use crate::components::{CollisionRadius, Obstacle, Player};
+use crate::entity_relations::RelationCommandsExt;
+use crate::game_state::{GameState, GameplayEntity};
+use crate::resources::WorldBounds;
+use crate::rng::GameRng;
+use crate::systems::{fixed_delta, obstacle_collision, PhysicsStep};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Item the player picks up by touching it
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Collectible {
+    /// Points before the combo multiplier
+    pub value: u32,
+    pub radius: f32,
+}
+
+impl Default for Collectible {
+    fn default() -> Self {
+        Self {
+            value: 10,
+            radius: 12.0,
+        }
+    }
+}
+
+/// Keeps the arena stocked with collectibles
+#[derive(Resource, Debug, Clone)]
+pub struct CollectibleSpawner {
+    /// Collectibles kept alive at once
+    pub target_count: usize,
+    /// Distance kept from the world bounds
+    pub margin: f32,
+    /// Collectibles never appear closer than this to a player
+    pub min_player_distance: f32,
+    /// Minimum distance between two collectibles
+    pub min_spacing: f32,
+    /// Random positions tried before giving up for this tick
+    pub max_attempts: u32,
+}
+
+impl Default for CollectibleSpawner {
+    fn default() -> Self {
+        Self {
+            target_count: 5,
+            margin: 30.0,
+            min_player_distance: 120.0,
+            min_spacing: 60.0,
+            max_attempts: 32,
+        }
+    }
+}
+
+/// Combo tuning: pickups within `combo_window` seconds of each other raise the multiplier
+#[derive(Resource, Debug, Clone)]
+pub struct ScoreSettings {
+    pub combo_window: f32,
+    /// Multiplier added per chained pickup
+    pub multiplier_step: f32,
+    pub max_multiplier: f32,
+}
+
+impl Default for ScoreSettings {
+    fn default() -> Self {
+        Self {
+            combo_window: 1.5,
+            multiplier_step: 0.5,
+            max_multiplier: 4.0,
+        }
+    }
+}
+
+/// Running score for the current session
+#[derive(Resource, Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    pub points: u32,
+    /// Pickups in the current chain, 0 when no combo is running
+    pub combo: u32,
+    pub best_combo: u32,
+    /// Seconds left before the current chain breaks
+    pub combo_time_left: f32,
+}
+
+impl Score {
+    /// Multiplier the next pickup would get if the chain holds
+    pub fn multiplier(&self, settings: &ScoreSettings) -> f32 {
+        let chained = self.combo.saturating_sub(1) as f32;
+        (1.0 + chained * settings.multiplier_step).min(settings.max_multiplier)
+    }
+
+    /// Register one pickup and return the points it was worth
+    pub fn register_pickup(&mut self, value: u32, settings: &ScoreSettings) -> u32 {
+        self.combo = if self.combo_time_left > 0.0 {
+            self.combo + 1
+        } else {
+            1
+        };
+        self.best_combo = self.best_combo.max(self.combo);
+        self.combo_time_left = settings.combo_window;
+
+        let points = (value as f32 * self.multiplier(settings)).round() as u32;
+        self.points += points;
+        points
+    }
+
+    /// Advance the combo timer, breaking the chain when it runs out
+    pub fn tick(&mut self, delta: f32) {
+        if self.combo_time_left > 0.0 {
+            self.combo_time_left -= delta;
+            if self.combo_time_left <= 0.0 {
+                self.combo_time_left = 0.0;
+                self.combo = 0;
+            }
+        }
+    }
+}
+
+/// Fired when a player picks up a collectible
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PickupEvent {
+    pub collector: Entity,
+    pub collectible: Entity,
+    pub position: Vec2,
+    /// Points awarded after the combo multiplier
+    pub points: u32,
+    /// Length of the combo chain including this pickup
+    pub combo: u32,
+}
+
+/// Picks a random position inside the bounds that respects the spawner spacing rules
+/// and stays clear of the `blocked` areas
+pub fn find_spawn_position(
+    rng: &mut GameRng,
+    bounds: &WorldBounds,
+    spawner: &CollectibleSpawner,
+    players: &[Vec2],
+    collectibles: &[Vec2],
+    blocked: &[Rect],
+) -> Option<Vec2> {
+    let min = bounds.min + Vec2::splat(spawner.margin);
+    let max = bounds.max - Vec2::splat(spawner.margin);
+    if min.x >= max.x || min.y >= max.y {
+        return None;
+    }
+
+    (0..spawner.max_attempts)
+        .map(|_| rng.0.point_in(min, max))
+        .find(|&candidate| {
+            players
+                .iter()
+                .all(|player| player.distance(candidate) >= spawner.min_player_distance)
+                && collectibles
+                    .iter()
+                    .all(|other| other.distance(candidate) >= spawner.min_spacing)
+                && !blocked.iter().any(|rect| rect.contains(candidate))
+        })
+}
+
+/// Spawn a collectible sprite
+pub fn spawn_collectible(
+    commands: &mut Commands,
+    position: Vec2,
+    collectible: Collectible,
+) -> Entity {
+    commands
+        .spawn(collectible_bundle(position, collectible))
+        .id()
+}
+
+/// Everything `spawn_collectible` gives a collectible
+pub fn collectible_bundle(position: Vec2, collectible: Collectible) -> impl Bundle {
+    (
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(1.0, 0.85, 0.2),
+                custom_size: Some(Vec2::splat(collectible.radius * 2.0)),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(0.0)),
+            ..default()
+        },
+        collectible,
+        GameplayEntity,
+    )
+}
+
+/// Tops the arena back up to the spawner's target count
+pub fn spawn_collectibles(
+    mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    bounds: Res<WorldBounds>,
+    spawner: Res<CollectibleSpawner>,
+    players: Query<&Transform, With<Player>>,
+    collectibles: Query<&Transform, With<Collectible>>,
+    obstacles: Query<(&Transform, &Obstacle)>,
+) {
+    // Keep the whole collectible clear of obstacles, not just its center
+    let clearance = Vec2::splat(Collectible::default().radius);
+    let blocked: Vec<Rect> = obstacles
+        .iter()
+        .map(|(transform, obstacle)| {
+            let rect = obstacle.rect(transform.translation.truncate());
+            Rect::from_corners(rect.min - clearance, rect.max + clearance)
+        })
+        .collect();
+    let player_positions: Vec<Vec2> = players.iter().map(|t| t.translation.truncate()).collect();
+    let mut collectible_positions: Vec<Vec2> = collectibles
+        .iter()
+        .map(|t| t.translation.truncate())
+        .collect();
+
+    while collectible_positions.len() < spawner.target_count {
+        let Some(position) = find_spawn_position(
+            &mut rng,
+            &bounds,
+            &spawner,
+            &player_positions,
+            &collectible_positions,
+            &blocked,
+        ) else {
+            break;
+        };
+
+        spawn_collectible(&mut commands, position, Collectible::default());
+        collectible_positions.push(position);
+    }
+}
+
+/// Counts down the combo window on the fixed timestep
+pub fn tick_combo(fixed_time: Res<FixedTime>, mut score: ResMut<Score>) {
+    score.tick(fixed_delta(&fixed_time));
+}
+
+/// Overlap test between players and collectibles, scoring each pickup once
+pub fn detect_pickups(
+    mut commands: Commands,
+    settings: Res<ScoreSettings>,
+    mut score: ResMut<Score>,
+    players: Query<(Entity, &Transform, Option<&CollisionRadius>), With<Player>>,
+    collectibles: Query<(Entity, &Transform, &Collectible)>,
+    mut pickup_events: EventWriter<PickupEvent>,
+) {
+    let mut collected = Vec::new();
+
+    for (collector, player_transform, radius) in players.iter() {
+        let player_position = player_transform.translation.truncate();
+        let player_radius = radius.copied().unwrap_or_default().0;
+
+        for (collectible_entity, transform, collectible) in collectibles.iter() {
+            if collected.contains(&collectible_entity) {
+                continue;
+            }
+
+            let position = transform.translation.truncate();
+            if player_position.distance(position) > player_radius + collectible.radius {
+                continue;
+            }
+
+            collected.push(collectible_entity);
+            commands.entity(collectible_entity).despawn_related();
+
+            let points = score.register_pickup(collectible.value, &settings);
+            pickup_events.send(PickupEvent {
+                collector,
+                collectible: collectible_entity,
+                position,
+                points,
+                combo: score.combo,
+            });
+        }
+    }
+}
+
+/// Logs pickups, an example of a system reacting to `PickupEvent`
+pub fn log_pickups(mut pickup_events: EventReader<PickupEvent>, score: Res<Score>) {
+    for event in pickup_events.iter() {
+        info!(
+            "{:?} picked up {} points (combo x{}), score {}",
+            event.collector, event.points, event.combo, score.points
+        );
+    }
+}
+
+pub fn reset_score(mut score: ResMut<Score>) {
+    *score = Score::default();
+}
+
+/// Bundle collectible spawning, pickup detection and scoring for easy registration
+pub struct CollectibleSystems;
+
+impl CollectibleSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.init_resource::<GameRng>()
+            .init_resource::<CollectibleSpawner>()
+            .init_resource::<ScoreSettings>()
+            .init_resource::<Score>()
+            .add_event::<PickupEvent>()
+            .add_systems(
+                FixedUpdate,
+                (tick_combo, detect_pickups, spawn_collectibles)
+                    .chain()
+                    .after(obstacle_collision)
+                    .in_set(PhysicsStep::Simulate),
+            )
+            .add_systems(Update, log_pickups)
+            .add_systems(OnExit(GameState::MainMenu), reset_score)
+            .add_systems(OnExit(GameState::GameOver), reset_score)
+    }
+}
@@ -1,29 +1,117 @@
 use bevy::prelude::*;
 
 /// Marker component for the player cube
-#[derive(Component, Debug)]
-pub struct Player {
-    pub acceleration: Vec2,
-    pub velocity: Vec2,
-    pub friction: f32,
+#[derive(Component, Debug, Default)]
+pub struct Player;
+
+/// Velocity in world units per second
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct LinearVelocity(pub Vec2);
+
+/// Acceleration applied on the next physics tick, cleared after integration
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Acceleration(pub Vec2);
+
+impl Acceleration {
+    pub fn zero() -> Self {
+        Self(Vec2::ZERO)
+    }
+}
+
+/// Per-entity friction, the fraction of velocity lost per second
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Friction(pub f32);
+
+impl Default for Friction {
+    fn default() -> Self {
+        Self(0.95)
+    }
+}
+
+/// Inertia of a body: force is divided by mass, so heavy bodies accelerate
+/// slower and are pushed around less. Bodies without it have a mass of 1.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Mass(pub f32);
+
+impl Default for Mass {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// Everything the physics integration needs to move an entity
+#[derive(Bundle, Debug, Default)]
+pub struct PhysicsBundle {
+    pub velocity: LinearVelocity,
+    pub acceleration: Acceleration,
+    pub friction: Friction,
+    pub mass: Mass,
 }
 
-impl Player {
-    pub fn new(acceleration: Vec2, velocity: Vec2, friction: f32) -> Self {
+impl PhysicsBundle {
+    pub fn new(velocity: Vec2, friction: f32) -> Self {
         Self {
-            acceleration,
-            velocity,
-            friction,
+            velocity: LinearVelocity(velocity),
+            acceleration: Acceleration::zero(),
+            friction: Friction(friction),
+            mass: Mass::default(),
         }
     }
 }
 
-impl Default for Player {
+/// Movement requested by an input source, applied as force on the fixed timestep
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct MovementIntent {
+    /// Desired direction, length at most 1 (analog input may be shorter)
+    pub direction: Vec2,
+    /// Boost button held (shift or gamepad)
+    pub boost: bool,
+    /// Left mouse boost held
+    pub mouse_boost: bool,
+}
+
+impl MovementIntent {
+    /// Number of boost sources currently held
+    pub fn boost_sources(&self) -> u32 {
+        self.boost as u32 + self.mouse_boost as u32
+    }
+}
+
+/// Fire request from an input source, turned into shots on the fixed timestep
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct FireIntent {
+    /// Fire button held
+    pub firing: bool,
+    /// World point to shoot at, `None` to shoot the way the player is heading
+    pub aim_at: Option<Vec2>,
+}
+
+/// Radius used for overlap tests such as pickups
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct CollisionRadius(pub f32);
+
+impl Default for CollisionRadius {
+    /// Half the size of the 50x50 player cube
     fn default() -> Self {
+        Self(25.0)
+    }
+}
+
+/// Static axis aligned box that physics bodies bounce off
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Obstacle {
+    pub half_size: Vec2,
+}
+
+impl Obstacle {
+    pub fn new(size: Vec2) -> Self {
         Self {
-            acceleration: Vec2::new(0.0, 0.0),
-            velocity: Vec2::new(0.0, 0.0),
-            friction: 0.95,
+            half_size: size / 2.0,
         }
     }
+
+    /// World space rectangle covered by an obstacle centered at `center`
+    pub fn rect(&self, center: Vec2) -> Rect {
+        Rect::from_center_half_size(center, self.half_size)
+    }
 }
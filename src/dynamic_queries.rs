@@ -67,6 +67,9 @@ pub fn setup_component_registry(world: &mut World) {
 
     registry.register_component::<Transform>(world, "Transform");
     registry.register_component::<Player>(world, "Player");
+    registry.register_component::<LinearVelocity>(world, "LinearVelocity");
+    registry.register_component::<Acceleration>(world, "Acceleration");
+    registry.register_component::<Friction>(world, "Friction");
 
     world.insert_resource(registry);
 }
@@ -321,9 +324,15 @@ impl DynamicComponent for Player {
         "Player"
     }
     fn as_debug_string(&self) -> String {
-        format!(
-            "Player(vel: {:?}, accel: {:?}, friction: {})",
-            self.velocity, self.acceleration, self.friction
-        )
+        "Player".to_string()
+    }
+}
+
+impl DynamicComponent for LinearVelocity {
+    fn type_name() -> &'static str {
+        "LinearVelocity"
+    }
+    fn as_debug_string(&self) -> String {
+        format!("LinearVelocity({:?})", self.0)
     }
 }
@@ -0,0 +1,336 @@
+use crate::components::{Acceleration, CollisionRadius, LinearVelocity, PhysicsBundle};
+use crate::entity_relations::Target;
+use crate::game_state::GameplayEntity;
+use crate::health::{DeathBehavior, Health};
+use crate::rng::GameRng;
+use crate::systems::{fixed_delta, world_friction, PhysicsStep};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Marker component for AI controlled entities
+#[derive(Component, Debug, Default)]
+pub struct Enemy;
+
+/// How an enemy reacts to its `Target`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SteeringBehavior {
+    /// Head straight for the target
+    #[default]
+    Seek,
+    /// Run straight away from the target while it is inside the panic radius
+    Flee,
+    /// Seek, slowing down to stop on the target
+    Arrive,
+    /// Seek the position the target will reach at its current velocity
+    Pursue,
+    /// Flee from the position the target will reach at its current velocity
+    Evade,
+    /// Meander randomly, ignores the target
+    Wander,
+}
+
+/// Steering tuning and state for one enemy. The resulting force is written to
+/// `Acceleration`, so enemies move through the same integration as the player.
+#[derive(Component, Debug, Clone)]
+pub struct Steering {
+    pub behavior: SteeringBehavior,
+    /// Largest acceleration the behaviors may produce
+    pub max_force: f32,
+    /// Speed the behaviors aim for
+    pub max_speed: f32,
+    /// Arrive starts slowing down inside this distance
+    pub slowing_radius: f32,
+    /// Flee and evade ignore targets further away than this
+    pub panic_radius: f32,
+    /// Longest look-ahead in seconds for pursue and evade
+    pub max_prediction: f32,
+    /// Radius of the wander circle
+    pub wander_radius: f32,
+    /// Distance of the wander circle ahead of the enemy
+    pub wander_distance: f32,
+    /// Largest change of the wander angle, in radians per second
+    pub wander_jitter: f32,
+    /// Current position on the wander circle, in radians
+    pub wander_angle: f32,
+    /// Other enemies closer than this push the enemy away
+    pub separation_radius: f32,
+    /// Weight of separation relative to the main behavior
+    pub separation_weight: f32,
+}
+
+impl Default for Steering {
+    fn default() -> Self {
+        Self {
+            behavior: SteeringBehavior::default(),
+            max_force: 2000.0,
+            max_speed: 400.0,
+            slowing_radius: 150.0,
+            panic_radius: 300.0,
+            max_prediction: 1.0,
+            wander_radius: 50.0,
+            wander_distance: 80.0,
+            wander_jitter: 6.0,
+            wander_angle: 0.0,
+            separation_radius: 60.0,
+            separation_weight: 1.5,
+        }
+    }
+}
+
+impl Steering {
+    pub fn new(behavior: SteeringBehavior) -> Self {
+        Self {
+            behavior,
+            ..default()
+        }
+    }
+
+    /// Acceleration that turns `velocity` into `desired_velocity`, capped at `max_force`
+    pub fn steer_towards(&self, desired_velocity: Vec2, velocity: Vec2) -> Vec2 {
+        let steering = (desired_velocity - velocity) * (self.max_force / self.max_speed);
+        steering.clamp_length_max(self.max_force)
+    }
+}
+
+/// Position of a moving target after the time the enemy needs to reach it,
+/// never looking further ahead than `max_prediction` seconds
+pub fn predict_position(
+    position: Vec2,
+    target: Vec2,
+    target_velocity: Vec2,
+    max_speed: f32,
+    max_prediction: f32,
+) -> Vec2 {
+    let time_to_reach = (position.distance(target) / max_speed).min(max_prediction);
+    target + target_velocity * time_to_reach
+}
+
+pub fn seek(position: Vec2, velocity: Vec2, target: Vec2, steering: &Steering) -> Vec2 {
+    let desired = (target - position).normalize_or_zero() * steering.max_speed;
+    steering.steer_towards(desired, velocity)
+}
+
+pub fn flee(position: Vec2, velocity: Vec2, threat: Vec2, steering: &Steering) -> Vec2 {
+    if position.distance(threat) > steering.panic_radius {
+        return Vec2::ZERO;
+    }
+    let desired = (position - threat).normalize_or_zero() * steering.max_speed;
+    steering.steer_towards(desired, velocity)
+}
+
+pub fn arrive(position: Vec2, velocity: Vec2, target: Vec2, steering: &Steering) -> Vec2 {
+    let to_target = target - position;
+    let distance = to_target.length();
+    if distance <= f32::EPSILON {
+        return steering.steer_towards(Vec2::ZERO, velocity);
+    }
+
+    // Desired speed ramps down linearly once inside the slowing radius
+    let ramp = (distance / steering.slowing_radius).min(1.0);
+    let desired = to_target / distance * steering.max_speed * ramp;
+    steering.steer_towards(desired, velocity)
+}
+
+pub fn pursue(
+    position: Vec2,
+    velocity: Vec2,
+    target: Vec2,
+    target_velocity: Vec2,
+    steering: &Steering,
+) -> Vec2 {
+    let predicted = predict_position(
+        position,
+        target,
+        target_velocity,
+        steering.max_speed,
+        steering.max_prediction,
+    );
+    seek(position, velocity, predicted, steering)
+}
+
+pub fn evade(
+    position: Vec2,
+    velocity: Vec2,
+    threat: Vec2,
+    threat_velocity: Vec2,
+    steering: &Steering,
+) -> Vec2 {
+    if position.distance(threat) > steering.panic_radius {
+        return Vec2::ZERO;
+    }
+    let predicted = predict_position(
+        position,
+        threat,
+        threat_velocity,
+        steering.max_speed,
+        steering.max_prediction,
+    );
+    let desired = (position - predicted).normalize_or_zero() * steering.max_speed;
+    steering.steer_towards(desired, velocity)
+}
+
+/// Steer toward a point on a circle projected ahead of the enemy. The point
+/// drifts by a random amount every tick, which gives smooth random turns.
+pub fn wander(velocity: Vec2, steering: &mut Steering, rng: &mut GameRng, delta: f32) -> Vec2 {
+    let jitter = steering.wander_jitter * delta;
+    steering.wander_angle += rng.0.range_f32(-jitter, jitter);
+
+    let heading = velocity.try_normalize().unwrap_or(Vec2::X);
+    let circle_center = heading * steering.wander_distance;
+    let offset = Vec2::from_angle(steering.wander_angle) * steering.wander_radius;
+    let desired = (circle_center + offset).normalize_or_zero() * steering.max_speed;
+    steering.steer_towards(desired, velocity)
+}
+
+/// Push away from neighbours inside `radius`, stronger the closer they are
+pub fn separation(
+    position: Vec2,
+    neighbours: impl IntoIterator<Item = Vec2>,
+    radius: f32,
+    max_force: f32,
+) -> Vec2 {
+    let mut push = Vec2::ZERO;
+    for neighbour in neighbours {
+        let away = position - neighbour;
+        let distance = away.length();
+        if distance >= radius {
+            continue;
+        }
+        // Neighbours sitting exactly on top of each other push along a fixed axis
+        let direction = away.try_normalize().unwrap_or(Vec2::X);
+        push += direction * (1.0 - distance / radius);
+    }
+    (push * max_force).clamp_length_max(max_force)
+}
+
+/// Enemy bodies the steering system drives
+pub type SteeringQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static LinearVelocity,
+        &'static mut Acceleration,
+        &'static mut Steering,
+        Option<&'static Target>,
+    ),
+    With<Enemy>,
+>;
+
+/// Runs every enemy's behavior plus separation and writes the result to its `Acceleration`.
+/// Enemies whose target is missing only keep their distance from each other.
+pub fn steer_enemies(
+    fixed_time: Res<FixedTime>,
+    mut rng: ResMut<GameRng>,
+    mut enemies: SteeringQuery,
+    targets: Query<(&Transform, Option<&LinearVelocity>)>,
+) {
+    let delta = fixed_delta(&fixed_time);
+    let positions: Vec<(Entity, Vec2)> = enemies
+        .iter()
+        .map(|(entity, transform, ..)| (entity, transform.translation.truncate()))
+        .collect();
+
+    for (entity, transform, velocity, mut acceleration, mut steering, target) in enemies.iter_mut()
+    {
+        let position = transform.translation.truncate();
+        let velocity = velocity.0;
+        let target = target.and_then(|target| targets.get(target.0).ok()).map(
+            |(transform, target_velocity)| {
+                (
+                    transform.translation.truncate(),
+                    target_velocity.map_or(Vec2::ZERO, |v| v.0),
+                )
+            },
+        );
+
+        let behavior = match (steering.behavior, target) {
+            (SteeringBehavior::Wander, _) => wander(velocity, &mut steering, &mut rng, delta),
+            (_, None) => Vec2::ZERO,
+            (SteeringBehavior::Seek, Some((target, _))) => {
+                seek(position, velocity, target, &steering)
+            }
+            (SteeringBehavior::Flee, Some((target, _))) => {
+                flee(position, velocity, target, &steering)
+            }
+            (SteeringBehavior::Arrive, Some((target, _))) => {
+                arrive(position, velocity, target, &steering)
+            }
+            (SteeringBehavior::Pursue, Some((target, target_velocity))) => {
+                pursue(position, velocity, target, target_velocity, &steering)
+            }
+            (SteeringBehavior::Evade, Some((target, target_velocity))) => {
+                evade(position, velocity, target, target_velocity, &steering)
+            }
+        };
+
+        let neighbours = positions
+            .iter()
+            .filter(|(other, _)| *other != entity)
+            .map(|(_, position)| *position);
+        let push = separation(
+            position,
+            neighbours,
+            steering.separation_radius,
+            steering.max_force,
+        );
+
+        acceleration.0 =
+            (behavior + push * steering.separation_weight).clamp_length_max(steering.max_force);
+    }
+}
+
+/// Spawn an enemy sprite with physics and health, optionally chasing `target`
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    position: Vec2,
+    steering: Steering,
+    target: Option<Entity>,
+) -> Entity {
+    let mut enemy = commands.spawn(enemy_bundle(position, steering));
+    if let Some(target) = target {
+        enemy.insert(Target(target));
+    }
+    enemy.id()
+}
+
+/// Everything `spawn_enemy` gives an enemy besides its target
+pub fn enemy_bundle(position: Vec2, steering: Steering) -> impl Bundle {
+    (
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.8, 0.3, 0.2),
+                custom_size: Some(Vec2::new(40.0, 40.0)),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(0.0)),
+            ..default()
+        },
+        Enemy,
+        steering,
+        PhysicsBundle::default(),
+        CollisionRadius(20.0),
+        // No invulnerability, so every shot counts
+        Health {
+            invulnerability: 0.0,
+            ..Health::new(30.0)
+        },
+        DeathBehavior::Despawn,
+        GameplayEntity,
+    )
+}
+
+/// Bundle enemy steering for easy registration
+pub struct EnemySystems;
+
+impl EnemySystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.init_resource::<GameRng>().add_systems(
+            FixedUpdate,
+            steer_enemies
+                .before(world_friction)
+                .in_set(PhysicsStep::Simulate),
+        )
+    }
+}
@@ -1,38 +1,42 @@
 use crate::components::*;
+use bevy::ecs::system::EntityCommands;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Entity relationship components for creating hierarchies and references
 /// These enable complex entity relationships beyond simple component composition
 
-/// Parent-child relationship components
-#[derive(Component, Debug)]
-pub struct Parent(pub Entity);
+/// Parent-child relationship components. A parent carries the `Parent`
+/// marker and lists its children in `Children`, each child names it in
+/// `ChildOf`. `attach_child` and `detach_child` keep the three in step.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Parent;
 
-#[derive(Component, Debug, Default)]
+#[derive(Component, Debug, Default, Clone, PartialEq, Eq)]
 pub struct Children(pub Vec<Entity>);
 
 /// Reverse lookup for child-to-parent relationships
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ChildOf(pub Entity);
 
 /// Target reference component - points to another entity
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Target(pub Entity);
 
 /// Owner component - tracks who owns this entity
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Owner(pub Entity);
 
 /// Group membership component - entities can belong to groups
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GroupMember {
     pub group_id: u32,
     pub role: String,
 }
 
 /// Group component - represents a collection of entities
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Group {
     pub id: u32,
     pub name: String,
@@ -192,98 +196,355 @@ pub fn find_all_descendants(
     }
 }
 
-/// Example 6: Validation System for Entity References
-pub fn validate_entity_references(
-    _commands: Commands,
-    // Check all entities with target references
-    targets: Query<(Entity, &Target)>,
-    // Check all entities with parent references
-    parents: Query<(Entity, &Parent)>,
-    // Check all entities with owner references
-    owners: Query<(Entity, &Owner)>,
-    // Get all valid entities
-    all_entities: Query<Entity>,
-) {
-    println!("=== Entity Reference Validation ===");
+// Example 6: Validation of entity references lives in `reference_validation`,
+// which also repairs what it finds
 
-    let valid_entities: std::collections::HashSet<Entity> = all_entities.iter().collect();
-    let mut invalid_references = Vec::new();
+/// Utility Systems for Managing Entity Relationships
 
-    // Validate target references
-    for (entity, target) in targets.iter() {
-        if !valid_entities.contains(&target.0) {
-            println!("Invalid target reference: {:?} -> {:?}", entity, target.0);
-            invalid_references.push(entity);
+/// Whether `ancestor` is `entity` or above it in the hierarchy
+pub fn is_ancestor(world: &World, ancestor: Entity, entity: Entity) -> bool {
+    let mut current = Some(entity);
+    while let Some(entity) = current {
+        if entity == ancestor {
+            return true;
         }
+        current = world.get::<ChildOf>(entity).map(|child_of| child_of.0);
     }
+    false
+}
 
-    // Validate parent references
-    for (entity, parent) in parents.iter() {
-        if !valid_entities.contains(&parent.0) {
-            println!("Invalid parent reference: {:?} -> {:?}", entity, parent.0);
-            invalid_references.push(entity);
+/// Make `child` a child of `parent`, taking it away from its old parent.
+/// Returns `false` and changes nothing when either entity is gone or the
+/// link would make `parent` its own ancestor.
+pub fn attach_child(world: &mut World, parent: Entity, child: Entity) -> bool {
+    if world.get_entity(parent).is_none()
+        || world.get_entity(child).is_none()
+        || is_ancestor(world, child, parent)
+    {
+        return false;
+    }
+    if world.get::<ChildOf>(child).map(|child_of| child_of.0) == Some(parent) {
+        return true;
+    }
+    detach_child(world, child);
+
+    world.entity_mut(child).insert(ChildOf(parent));
+    let mut parent = world.entity_mut(parent);
+    match parent.get_mut::<Children>() {
+        Some(mut children) => children.0.push(child),
+        None => {
+            parent.insert(Children(vec![child]));
         }
     }
+    parent.insert(Parent);
+    true
+}
 
-    // Validate owner references
-    for (entity, owner) in owners.iter() {
-        if !valid_entities.contains(&owner.0) {
-            println!("Invalid owner reference: {:?} -> {:?}", entity, owner.0);
-            invalid_references.push(entity);
+/// Cut `child` loose from its parent on both sides, returning the parent.
+/// A parent left without children is no longer a `Parent`.
+pub fn detach_child(world: &mut World, child: Entity) -> Option<Entity> {
+    let parent = world.get_entity_mut(child)?.take::<ChildOf>()?.0;
+    if let Some(mut parent_entity) = world.get_entity_mut(parent) {
+        if let Some(mut children) = parent_entity.get_mut::<Children>() {
+            children.0.retain(|&c| c != child);
+            if children.0.is_empty() {
+                parent_entity.remove::<(Children, Parent)>();
+            }
         }
     }
+    Some(parent)
+}
 
-    // Clean up invalid references (in a real system, you might want more sophisticated handling)
-    for entity in invalid_references {
-        println!("Cleaning up entity with invalid references: {:?}", entity);
-        // commands.entity(entity).despawn(); // Uncomment if you want to despawn
+/// `entity` and everything below it, parents before their children
+pub fn hierarchy_of(world: &World, entity: Entity) -> Vec<Entity> {
+    let mut entities = vec![entity];
+    let mut next = 0;
+    while let Some(&current) = entities.get(next) {
+        if let Some(children) = world.get::<Children>(current) {
+            entities.extend(children.0.iter().copied());
+        }
+        next += 1;
     }
+    entities
+}
 
-    println!("Reference validation complete.");
+/// Despawn `entity` with all its descendants and drop it from its parent's
+/// `Children`. `Owner` and `Target` links to them follow `RelationPolicies`.
+pub fn despawn_hierarchy(world: &mut World, entity: Entity) -> Vec<Entity> {
+    let policies = RelationPolicies {
+        child_of: OnDelete::Cascade,
+        ..world
+            .get_resource::<RelationPolicies>()
+            .cloned()
+            .unwrap_or_default()
+    };
+    despawn_with_policies(world, entity, &policies)
 }
 
-/// Utility Systems for Managing Entity Relationships
+/// The kinds of links that point from one entity to another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Relation {
+    ChildOf,
+    Owner,
+    Target,
+}
 
-/// Create parent-child relationship
-pub fn create_parent_child_relationship(mut commands: Commands, parent: Entity, child: Entity) {
-    // Add child to parent's children list
-    // In Bevy 0.11, we use a simpler approach
-    commands.entity(parent).insert(Children(vec![child]));
+impl Relation {
+    pub const ALL: [Relation; 3] = [Relation::ChildOf, Relation::Owner, Relation::Target];
 
-    // Set parent reference on child
-    commands.entity(child).insert(ChildOf(parent));
+    /// Entity `entity` points to through this relation
+    pub fn get(self, world: &World, entity: Entity) -> Option<Entity> {
+        match self {
+            Relation::ChildOf => world.get::<ChildOf>(entity).map(|r| r.0),
+            Relation::Owner => world.get::<Owner>(entity).map(|r| r.0),
+            Relation::Target => world.get::<Target>(entity).map(|r| r.0),
+        }
+    }
+
+    /// Entities pointing at `entity` through this relation
+    pub fn dependents(self, world: &mut World, entity: Entity) -> Vec<Entity> {
+        match self {
+            Relation::ChildOf => world
+                .query::<(Entity, &ChildOf)>()
+                .iter(world)
+                .filter(|(_, r)| r.0 == entity)
+                .map(|(dependent, _)| dependent)
+                .collect(),
+            Relation::Owner => world
+                .query::<(Entity, &Owner)>()
+                .iter(world)
+                .filter(|(_, r)| r.0 == entity)
+                .map(|(dependent, _)| dependent)
+                .collect(),
+            Relation::Target => world
+                .query::<(Entity, &Target)>()
+                .iter(world)
+                .filter(|(_, r)| r.0 == entity)
+                .map(|(dependent, _)| dependent)
+                .collect(),
+        }
+    }
+
+    /// Point `entity` at `other`, keeping both sides of a hierarchy link
+    pub fn set(self, world: &mut World, entity: Entity, other: Entity) -> bool {
+        match self {
+            Relation::ChildOf => return attach_child(world, other, entity),
+            Relation::Owner => world.entity_mut(entity).insert(Owner(other)),
+            Relation::Target => world.entity_mut(entity).insert(Target(other)),
+        };
+        true
+    }
+
+    /// Drop the link, on both sides for a hierarchy link
+    pub fn remove(self, world: &mut World, entity: Entity) {
+        match self {
+            Relation::ChildOf => {
+                detach_child(world, entity);
+            }
+            Relation::Owner => {
+                world.entity_mut(entity).remove::<Owner>();
+            }
+            Relation::Target => {
+                world.entity_mut(entity).remove::<Target>();
+            }
+        }
+    }
 }
 
-/// Remove parent-child relationship
-pub fn remove_parent_child_relationship(
-    mut commands: Commands,
-    parent: Entity,
-    child: Entity,
-    mut parents: Query<&mut Children>,
-) {
-    // Remove child from parent's children list
-    if let Ok(mut children) = parents.get_mut(parent) {
-        children.0.retain(|&c| c != child);
+/// What happens to an entity when the entity it points to is despawned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OnDelete {
+    /// Despawn it too, following its own dependents
+    Cascade,
+    /// Remove the relation component
+    Detach,
+    /// Point it where the despawned entity pointed: a child moves to its
+    /// grandparent, an item to its owner's owner. Detaches when there is none.
+    Reassign,
+    /// Point it at `RelationPolicies::default_owner`, detaches without one
+    DefaultOwner,
+}
+
+/// On-delete policy of every relation, used when despawning through
+/// `despawn_related` and `despawn_hierarchy`
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct RelationPolicies {
+    pub child_of: OnDelete,
+    pub owner: OnDelete,
+    pub target: OnDelete,
+    /// Entity that takes over relations under `OnDelete::DefaultOwner`
+    pub default_owner: Option<Entity>,
+}
+
+impl Default for RelationPolicies {
+    /// Attachments go with their parent, owned and targeting entities let go
+    fn default() -> Self {
+        Self {
+            child_of: OnDelete::Cascade,
+            owner: OnDelete::Detach,
+            target: OnDelete::Detach,
+            default_owner: None,
+        }
+    }
+}
+
+impl RelationPolicies {
+    pub fn policy(&self, relation: Relation) -> OnDelete {
+        match relation {
+            Relation::ChildOf => self.child_of,
+            Relation::Owner => self.owner,
+            Relation::Target => self.target,
+        }
+    }
+}
+
+/// Despawn `entity` and apply the `RelationPolicies` resource, or the
+/// default policies without one, to everything pointing at it. Returns the
+/// despawned entities.
+pub fn despawn_related(world: &mut World, entity: Entity) -> Vec<Entity> {
+    let policies = world
+        .get_resource::<RelationPolicies>()
+        .cloned()
+        .unwrap_or_default();
+    despawn_with_policies(world, entity, &policies)
+}
+
+/// Despawn `entity`, applying `policies` to the entities pointing at it
+pub fn despawn_with_policies(
+    world: &mut World,
+    entity: Entity,
+    policies: &RelationPolicies,
+) -> Vec<Entity> {
+    let mut despawned = Vec::new();
+    let mut pending = vec![entity];
+    while let Some(entity) = pending.pop() {
+        if world.get_entity(entity).is_none() {
+            continue;
+        }
+        for relation in Relation::ALL {
+            let replacement = match policies.policy(relation) {
+                OnDelete::Reassign => relation.get(world, entity),
+                OnDelete::DefaultOwner => policies.default_owner,
+                OnDelete::Cascade | OnDelete::Detach => None,
+            }
+            .filter(|&other| other != entity && world.get_entity(other).is_some());
+
+            for dependent in relation.dependents(world, entity) {
+                if policies.policy(relation) == OnDelete::Cascade {
+                    pending.push(dependent);
+                } else if !replacement.is_some_and(|other| relation.set(world, dependent, other)) {
+                    relation.remove(world, dependent);
+                }
+            }
+        }
+        detach_child(world, entity);
+        world.despawn(entity);
+        despawned.push(entity);
+    }
+    despawned
+}
+
+/// Hierarchy edits as entity commands, named apart from Bevy's own
+/// `BuildChildren`, which works on Bevy's hierarchy components
+pub trait HierarchyCommandsExt {
+    /// Adopt `child`, taking it from its old parent
+    fn attach_child(&mut self, child: Entity) -> &mut Self;
+    /// Move this entity under `parent`
+    fn attach_to(&mut self, parent: Entity) -> &mut Self;
+    /// Let go of `child` if it is one of this entity's children
+    fn detach_child(&mut self, child: Entity) -> &mut Self;
+    /// Leave the current parent, if any
+    fn detach_from_parent(&mut self) -> &mut Self;
+    /// Despawn this entity and its descendants, whatever the `ChildOf` policy
+    fn despawn_hierarchy(&mut self);
+}
+
+impl HierarchyCommandsExt for EntityCommands<'_, '_, '_> {
+    fn attach_child(&mut self, child: Entity) -> &mut Self {
+        let parent = self.id();
+        self.commands().add(move |world: &mut World| {
+            if !attach_child(world, parent, child) {
+                warn!("Could not attach {:?} to {:?}", child, parent);
+            }
+        });
+        self
+    }
+
+    fn attach_to(&mut self, parent: Entity) -> &mut Self {
+        let child = self.id();
+        self.commands().add(move |world: &mut World| {
+            if !attach_child(world, parent, child) {
+                warn!("Could not attach {:?} to {:?}", child, parent);
+            }
+        });
+        self
     }
 
-    // Remove parent reference from child
-    commands.entity(child).remove::<ChildOf>();
+    fn detach_child(&mut self, child: Entity) -> &mut Self {
+        let parent = self.id();
+        self.commands().add(move |world: &mut World| {
+            if world.get::<ChildOf>(child).map(|child_of| child_of.0) == Some(parent) {
+                detach_child(world, child);
+            }
+        });
+        self
+    }
+
+    fn detach_from_parent(&mut self) -> &mut Self {
+        let child = self.id();
+        self.commands().add(move |world: &mut World| {
+            detach_child(world, child);
+        });
+        self
+    }
+
+    fn despawn_hierarchy(&mut self) {
+        let entity = self.id();
+        self.commands().add(move |world: &mut World| {
+            despawn_hierarchy(world, entity);
+        });
+    }
+}
+
+/// Despawning that keeps the entities pointing at the despawned one valid
+pub trait RelationCommandsExt {
+    /// Despawn this entity, applying the `RelationPolicies`
+    fn despawn_related(&mut self);
+}
+
+impl RelationCommandsExt for EntityCommands<'_, '_, '_> {
+    fn despawn_related(&mut self) {
+        let entity = self.id();
+        self.commands().add(move |world: &mut World| {
+            despawn_related(world, entity);
+        });
+    }
+}
+
+/// Create parent-child relationship
+pub fn create_parent_child_relationship(mut commands: Commands, parent: Entity, child: Entity) {
+    commands.entity(parent).attach_child(child);
+}
+
+/// Remove parent-child relationship
+pub fn remove_parent_child_relationship(mut commands: Commands, parent: Entity, child: Entity) {
+    commands.entity(parent).detach_child(child);
 }
 
 /// Example 7: Complex Relationship Query - Find Related Entities
 pub fn query_related_entities(
     // Find entities that are both children and have physics
-    child_physics: Query<(Entity, &ChildOf, &Player)>,
+    child_physics: Query<(Entity, &ChildOf, &LinearVelocity), With<Player>>,
     // Find their parents
     parents: Query<&Transform, (With<Parent>, Without<Player>)>,
 ) {
     println!("=== Complex Relationship Query ===");
 
-    for (child_entity, child_of, player) in child_physics.iter() {
+    for (child_entity, child_of, velocity) in child_physics.iter() {
         if let Ok(parent_transform) = parents.get(child_of.0) {
             println!(
                 "Player child {:?} (vel: {:?}) has parent at {:?}",
-                child_entity, player.velocity, parent_transform.translation
+                child_entity, velocity.0, parent_transform.translation
             );
         }
     }
@@ -301,7 +562,6 @@ impl EntityRelationSystems {
                 query_entity_references_two_phase,
                 query_ownership_relationships,
                 query_group_memberships,
-                validate_entity_references,
                 query_related_entities,
             ),
         )
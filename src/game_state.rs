@@ -0,0 +1,166 @@
+use crate::entity_relations::RelationCommandsExt;
+use bevy::prelude::*;
+
+/// Top level game flow
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+/// Marker for entities that live for one play session (players, pickups, arena).
+/// They survive pausing and are despawned when the session ends.
+#[derive(Component, Debug)]
+pub struct GameplayEntity;
+
+/// Sent whenever the session's gameplay entities are torn down: quitting to
+/// the menu, restarting after game over, reloading the level or quickloading.
+/// Session state kept outside `GameplayEntity`s is cleared on it.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameplayReset;
+
+/// Despawns the entity when leaving the given state, used for menus and overlays
+#[derive(Component, Debug)]
+pub struct DespawnOnExit(pub GameState);
+
+/// Despawn every entity scoped to `state`. Registered on `OnExit(state)`.
+pub fn despawn_on_exit(state: GameState) -> impl FnMut(Commands, Query<(Entity, &DespawnOnExit)>) {
+    move |mut commands, query| {
+        for (entity, scope) in query.iter() {
+            if scope.0 == state {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+/// Despawn everything that belongs to the play session
+pub fn cleanup_gameplay(
+    mut commands: Commands,
+    query: Query<Entity, With<GameplayEntity>>,
+    mut resets: EventWriter<GameplayReset>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_related();
+    }
+    resets.send(GameplayReset);
+}
+
+/// Keyboard driven state transitions
+///
+/// - Main menu: Enter starts a session
+/// - Playing: Escape pauses
+/// - Paused: Escape resumes, Q quits to the main menu
+/// - Game over: Enter restarts, Escape returns to the main menu
+pub fn handle_state_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let pressed = |key| keyboard_input.just_pressed(key);
+
+    let next = match state.get() {
+        GameState::MainMenu if pressed(KeyCode::Return) => GameState::Playing,
+        GameState::Playing if pressed(KeyCode::Escape) => GameState::Paused,
+        GameState::Paused if pressed(KeyCode::Escape) => GameState::Playing,
+        GameState::Paused if pressed(KeyCode::Q) => GameState::MainMenu,
+        GameState::GameOver if pressed(KeyCode::Return) => GameState::Playing,
+        GameState::GameOver if pressed(KeyCode::Escape) => GameState::MainMenu,
+        _ => return,
+    };
+
+    next_state.set(next);
+}
+
+/// Pausing stops the clock, so `FixedTime` accumulates nothing while paused
+/// and physics does not catch up with a burst of ticks on resume
+pub fn pause_time(mut time: ResMut<Time>) {
+    time.pause();
+}
+
+pub fn resume_time(mut time: ResMut<Time>) {
+    time.unpause();
+}
+
+fn spawn_overlay(commands: &mut Commands, color: Color, scope: GameState) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::new(4000.0, 4000.0)),
+                ..default()
+            },
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, 100.0)),
+            ..default()
+        },
+        DespawnOnExit(scope),
+    ));
+}
+
+pub fn spawn_main_menu(mut commands: Commands) {
+    info!("Main menu: press Enter to start");
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.25, 0.25, 0.75),
+                custom_size: Some(Vec2::new(200.0, 200.0)),
+                ..default()
+            },
+            ..default()
+        },
+        DespawnOnExit(GameState::MainMenu),
+    ));
+}
+
+pub fn spawn_pause_overlay(mut commands: Commands) {
+    info!("Paused: Escape to resume, Q to quit");
+    spawn_overlay(
+        &mut commands,
+        Color::rgba(0.0, 0.0, 0.0, 0.5),
+        GameState::Paused,
+    );
+}
+
+pub fn spawn_game_over_overlay(mut commands: Commands) {
+    info!("Game over: Enter to restart, Escape for the main menu");
+    spawn_overlay(
+        &mut commands,
+        Color::rgba(0.6, 0.0, 0.0, 0.4),
+        GameState::GameOver,
+    );
+}
+
+/// Bundle the game state machine for easy registration
+pub struct GameStateSystems;
+
+impl GameStateSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.add_state::<GameState>()
+            .add_event::<GameplayReset>()
+            .add_systems(Update, handle_state_input)
+            .add_systems(
+                OnEnter(GameState::MainMenu),
+                (cleanup_gameplay, spawn_main_menu),
+            )
+            .add_systems(
+                OnExit(GameState::MainMenu),
+                despawn_on_exit(GameState::MainMenu),
+            )
+            .add_systems(
+                OnEnter(GameState::Paused),
+                (pause_time, spawn_pause_overlay),
+            )
+            .add_systems(
+                OnExit(GameState::Paused),
+                (resume_time, despawn_on_exit(GameState::Paused)),
+            )
+            .add_systems(OnEnter(GameState::GameOver), spawn_game_over_overlay)
+            .add_systems(
+                OnExit(GameState::GameOver),
+                (cleanup_gameplay, despawn_on_exit(GameState::GameOver)),
+            )
+    }
+}
@@ -0,0 +1,366 @@
+use crate::bots::{BotBehavior, BotSettings, BotSystems};
+use crate::collectibles::{CollectibleSystems, Score};
+use crate::components::{LinearVelocity, Player};
+use crate::enemy::EnemySystems;
+use crate::game_state::{GameState, GameStateSystems};
+use crate::health::{Health, HealthSystems};
+use crate::level::{CurrentLevel, LevelDefinition, LevelError, LevelSystems};
+use crate::modifiers::ModifierSystems;
+use crate::power_ups::PowerUpSystems;
+use crate::procgen::{ArenaError, ArenaSettings};
+use crate::projectiles::ProjectileSystems;
+use crate::race::RaceSystems;
+use crate::reference_validation::ReferenceValidationSystems;
+use crate::replay::{
+    InputRecorder, InputReplay, InputSlot, ReplayError, ReplaySystems, SimulationTick,
+};
+use crate::rng::{GameRng, DEFAULT_SEED};
+use crate::save::{capture_snapshot, SaveError};
+use crate::soak::{
+    read_replay, write_soak_report, SoakBudgets, SoakMonitor, SoakReportPath, SoakSystems,
+};
+use crate::stamina::{BoostSettings, StaminaDepleted, StaminaFull};
+use crate::systems::{PhysicsSet, PhysicsSystems};
+use crate::transform_propagation::TransformPropagationSystems;
+use bevy::app::{AppExit, ScheduleRunnerPlugin};
+use bevy::core::{FrameCountPlugin, TaskPoolPlugin, TypeRegistrationPlugin};
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::time::{TimePlugin, TimeUpdateStrategy};
+use std::fmt;
+use std::path::PathBuf;
+
+/// Ticks a stepped run simulates when none are given
+pub const DEFAULT_HEADLESS_TICKS: u32 = 600;
+/// Where a soak run writes its report when no path is given
+pub const DEFAULT_SOAK_REPORT: &str = "soak-report.ron";
+
+/// Why a headless run could not start or finish
+#[derive(Debug)]
+pub enum HeadlessError {
+    /// Bad command line
+    Usage(String),
+    Level(LevelError),
+    Arena(ArenaError),
+    Replay(ReplayError),
+    Export(SaveError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadlessError::Usage(message) => write!(f, "{}", message),
+            HeadlessError::Level(error) => write!(f, "{}", error),
+            HeadlessError::Arena(error) => write!(f, "{}", error),
+            HeadlessError::Replay(error) => write!(f, "{}", error),
+            HeadlessError::Export(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+/// Command line of the headless binary:
+/// `[level.ron | --arena N] [--ticks N] [--realtime] [--seed N] [--replay file] [--bot name]
+/// [--export file] [--soak] [--report file]`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeadlessConfig {
+    pub level: Option<PathBuf>,
+    /// Seed of a generated arena to play instead of a level file
+    pub arena: Option<u64>,
+    /// Stop after this many ticks. Without it only a real time run keeps going.
+    pub ticks: Option<u32>,
+    /// Tick at the fixed rate instead of as fast as possible, implied by
+    /// `--serve` and `--connect` since the peers cannot be stepped
+    pub realtime: bool,
+    /// Gameplay random seed, the replay's when playing one back
+    pub seed: Option<u64>,
+    pub replay: Option<PathBuf>,
+    pub bot: Option<BotBehavior>,
+    /// Write the final state as a save file
+    pub export: Option<PathBuf>,
+    /// Check invariants every tick and stop at the first violation
+    pub soak: bool,
+    /// Where a failed soak run writes its report, implies `soak`
+    pub report: Option<PathBuf>,
+}
+
+impl HeadlessConfig {
+    pub fn from_args(args: &[String]) -> Result<Self, HeadlessError> {
+        let mut config = Self::default();
+        let mut args = args.iter().peekable();
+        while let Some(arg) = args.next() {
+            let mut value = |flag: &str| {
+                args.next()
+                    .ok_or_else(|| HeadlessError::Usage(format!("{} needs a value", flag)))
+            };
+            match arg.as_str() {
+                "--arena" => config.arena = Some(parse(value("--arena")?)?),
+                "--ticks" => config.ticks = Some(parse(value("--ticks")?)?),
+                "--realtime" => config.realtime = true,
+                "--seed" => config.seed = Some(parse(value("--seed")?)?),
+                "--replay" => config.replay = Some(value("--replay")?.into()),
+                "--bot" => {
+                    config.bot = Some(value("--bot")?.parse().map_err(HeadlessError::Usage)?)
+                }
+                "--export" => config.export = Some(value("--export")?.into()),
+                "--soak" => config.soak = true,
+                "--report" => {
+                    config.report = Some(value("--report")?.into());
+                    config.soak = true;
+                }
+                // Network flags and their addresses are read by `ReplicationConfig`
+                "--serve" => {
+                    while args.next_if(|arg| !arg.starts_with("--")).is_some() {}
+                    config.realtime = true;
+                }
+                "--connect" => {
+                    value("--connect")?;
+                    value("--connect")?;
+                    config.realtime = true;
+                }
+                flag if flag.starts_with("--") => {
+                    return Err(HeadlessError::Usage(format!("unknown option {}", flag)))
+                }
+                path if config.level.is_none() => config.level = Some(path.into()),
+                other => return Err(HeadlessError::Usage(format!("unexpected '{}'", other))),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, HeadlessError> {
+    value
+        .parse()
+        .map_err(|_| HeadlessError::Usage(format!("'{}' is not a number", value)))
+}
+
+/// Final state of one player
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerSummary {
+    pub slot: Option<u8>,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub health: Option<f32>,
+}
+
+/// What a headless run prints when it ends
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeadlessSummary {
+    pub ticks: u32,
+    pub score: u32,
+    pub entities: usize,
+    pub players: Vec<PlayerSummary>,
+}
+
+impl HeadlessSummary {
+    pub fn capture(world: &mut World) -> Self {
+        let mut players: Vec<PlayerSummary> = world
+            .query_filtered::<(
+                &Transform,
+                Option<&LinearVelocity>,
+                Option<&Health>,
+                Option<&InputSlot>,
+            ), With<Player>>()
+            .iter(world)
+            .map(|(transform, velocity, health, slot)| PlayerSummary {
+                slot: slot.map(|slot| slot.0),
+                position: transform.translation.truncate(),
+                velocity: velocity.map_or(Vec2::ZERO, |velocity| velocity.0),
+                health: health.map(|health| health.current),
+            })
+            .collect();
+        players.sort_by_key(|player| player.slot);
+
+        Self {
+            ticks: world.resource::<SimulationTick>().0,
+            score: world
+                .get_resource::<Score>()
+                .map_or(0, |score| score.points),
+            entities: world.entities().len() as usize,
+            players,
+        }
+    }
+}
+
+impl fmt::Display for HeadlessSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} ticks, score {}, {} entities",
+            self.ticks, self.score, self.entities
+        )?;
+        for (index, player) in self.players.iter().enumerate() {
+            write!(
+                f,
+                "player {}: position ({:.2}, {:.2}) velocity ({:.2}, {:.2})",
+                player.slot.map_or(index, |slot| slot as usize),
+                player.position.x,
+                player.position.y,
+                player.velocity.x,
+                player.velocity.y
+            )?;
+            if let Some(health) = player.health {
+                write!(f, " health {:.1}", health)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Progress of a headless run
+#[derive(Resource, Debug, Clone, Default)]
+pub struct HeadlessRun {
+    /// Stop after this many ticks
+    pub ticks: Option<u32>,
+    pub export: Option<PathBuf>,
+    /// Set once the run ended, with the state it ended in
+    pub summary: Option<HeadlessSummary>,
+}
+
+/// Ends the run after the requested ticks, when the session stops, such as
+/// a finished race, or when a soak run broke an invariant: stores the final
+/// state in `HeadlessRun::summary`, exports it and exits the app
+pub fn finish_headless_run(world: &mut World) {
+    let run = world.resource::<HeadlessRun>();
+    let tick = world.resource::<SimulationTick>().0;
+    let playing = *world.resource::<State<GameState>>().get() == GameState::Playing;
+    let failed = world
+        .get_resource::<SoakMonitor>()
+        .is_some_and(|monitor| monitor.failed());
+    if run.summary.is_some() || (playing && !failed && run.ticks.is_none_or(|ticks| tick < ticks)) {
+        return;
+    }
+
+    let summary = HeadlessSummary::capture(world);
+    if let Some(path) = world.resource::<HeadlessRun>().export.clone() {
+        match capture_snapshot(world).write(&path) {
+            Ok(()) => info!("Final state written to {}", path.display()),
+            Err(error) => error!("{}", HeadlessError::Export(error)),
+        }
+    }
+    world.resource_mut::<HeadlessRun>().summary = Some(summary);
+    world.send_event(AppExit);
+}
+
+/// Register the fixed timestep simulation without anything that needs a
+/// window, renderer or camera: physics, attached transforms, pickups,
+/// enemies, levels, races, projectiles, health, power-ups, replays, bots and
+/// invariant checks
+pub fn add_simulation_systems(app: &mut App) -> &mut App {
+    app.init_resource::<BoostSettings>()
+        .add_event::<StaminaDepleted>()
+        .add_event::<StaminaFull>()
+        .configure_set(FixedUpdate, PhysicsSet.run_if(in_state(GameState::Playing)));
+    PhysicsSystems::add_to_app(app);
+    TransformPropagationSystems::add_to_app(app);
+    ReferenceValidationSystems::add_to_app(app);
+    CollectibleSystems::add_to_app(app);
+    EnemySystems::add_to_app(app);
+    LevelSystems::add_to_app(app);
+    RaceSystems::add_to_app(app);
+    ProjectileSystems::add_to_app(app);
+    HealthSystems::add_to_app(app);
+    ModifierSystems::add_to_app(app);
+    PowerUpSystems::add_to_app(app);
+    ReplaySystems::add_to_app(app);
+    BotSystems::add_to_app(app);
+    SoakSystems::add_to_app(app);
+    GameStateSystems::add_to_app(app)
+}
+
+/// App with minimal plugins and the simulation, already set to start playing.
+/// Stepped runs advance time by exactly one fixed tick per update.
+pub fn headless_app(config: &HeadlessConfig) -> Result<App, HeadlessError> {
+    let replay = config
+        .replay
+        .as_ref()
+        .map(read_replay)
+        .transpose()
+        .map_err(HeadlessError::Replay)?;
+    let (level_path, arena) = match (&config.level, config.arena, &replay) {
+        (Some(path), ..) => (Some(path.clone()), None),
+        (None, Some(seed), _) => (None, Some(ArenaSettings::with_seed(seed))),
+        (None, None, Some(replay)) => (replay.level.clone(), replay.arena.clone()),
+        (None, None, None) => (None, None),
+    };
+
+    let mut app = App::new();
+    app.add_plugins((
+        TaskPoolPlugin::default(),
+        TypeRegistrationPlugin,
+        FrameCountPlugin,
+        TimePlugin,
+        InputPlugin,
+    ));
+    add_simulation_systems(&mut app);
+
+    if let Some(path) = level_path.clone() {
+        let definition = LevelDefinition::load(&path).map_err(HeadlessError::Level)?;
+        app.insert_resource(CurrentLevel { definition, path });
+    }
+    if let Some(arena) = &arena {
+        let definition = arena.generate().map_err(HeadlessError::Arena)?;
+        app.insert_resource(CurrentLevel {
+            definition,
+            path: arena.level_path(),
+        });
+    }
+    let seed = config.seed.or(replay.as_ref().map(|replay| replay.seed));
+    if let Some(seed) = seed {
+        app.insert_resource(GameRng::from_seed(seed));
+    }
+    if config.soak {
+        app.insert_resource(SoakMonitor::new(SoakBudgets::default()))
+            .insert_resource(SoakReportPath(
+                config
+                    .report
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_SOAK_REPORT.into()),
+            ));
+        // Record what happens so a failure can be played back, a replay
+        // already is the recording
+        if replay.is_none() {
+            app.insert_resource(InputRecorder(InputReplay {
+                arena,
+                ..InputReplay::new(seed.unwrap_or(DEFAULT_SEED), level_path)
+            }));
+        }
+    }
+    if let Some(replay) = replay {
+        app.insert_resource(replay);
+    }
+    if let Some(bot) = config.bot {
+        app.insert_resource(BotSettings::new(bot, config.seed.unwrap_or_default()));
+    }
+
+    let period = app.world.resource::<FixedTime>().period;
+    if config.realtime {
+        app.add_plugins(ScheduleRunnerPlugin::run_loop(period));
+    } else {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(period));
+    }
+    app.insert_resource(HeadlessRun {
+        ticks: match config.realtime {
+            true => config.ticks,
+            false => Some(config.ticks.unwrap_or(DEFAULT_HEADLESS_TICKS)),
+        },
+        export: config.export.clone(),
+        summary: None,
+    })
+    .add_systems(Last, finish_headless_run.after(write_soak_report))
+    .world
+    .resource_mut::<NextState<GameState>>()
+    .set(GameState::Playing);
+    Ok(app)
+}
+
+/// Update a stepped app as fast as possible until the run ends
+pub fn run_headless(app: &mut App) -> Option<HeadlessSummary> {
+    while app.world.resource::<HeadlessRun>().summary.is_none() {
+        app.update();
+    }
+    app.world.resource::<HeadlessRun>().summary.clone()
+}
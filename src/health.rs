@@ -0,0 +1,368 @@
+use crate::components::{LinearVelocity, Mass};
+use crate::enemy::Enemy;
+use crate::entity_relations::{RelationCommandsExt, Target};
+use crate::game_state::GameplayReset;
+use crate::input::ControlledBy;
+use crate::level::{spawn_player, CurrentLevel};
+use crate::projectiles::{update_projectiles, ProjectileHit};
+use crate::race::RaceProgress;
+use crate::rollback::RollbackPlayer;
+use crate::systems::{fixed_delta, PhysicsStep, WallImpact};
+use crate::visuals::{VisualFeedback, VisualState};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Hit points of anything that can be hurt
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    /// Seconds of invulnerability after taking damage
+    pub invulnerability: f32,
+    /// Seconds of invulnerability left, damage is ignored while above zero
+    pub invulnerable_left: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            invulnerability: 0.5,
+            invulnerable_left: 0.0,
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable_left > 0.0
+    }
+
+    /// Take `amount` damage unless invulnerable or already dead.
+    /// Returns the health actually lost.
+    pub fn damage(&mut self, amount: f32) -> f32 {
+        if amount <= 0.0 || self.is_invulnerable() || self.is_dead() {
+            return 0.0;
+        }
+        let lost = amount.min(self.current);
+        self.current -= lost;
+        self.invulnerable_left = self.invulnerability;
+        lost
+    }
+}
+
+/// What happens to an entity when its health runs out. Entities without this
+/// component are despawned.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default)]
+pub enum DeathBehavior {
+    #[default]
+    Despawn,
+    /// Despawn, then spawn a fresh player at a level spawn point after `delay` seconds.
+    /// Anything that targeted the dead player lets go, then targets the new one.
+    Respawn { delay: f32 },
+}
+
+/// Request to hurt `target`. `source` is the entity responsible, if any.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub source: Option<Entity>,
+    pub amount: f32,
+    /// Impulse applied to the target when the damage lands, divided by its `Mass`
+    pub knockback: Vec2,
+}
+
+/// Fired once when an entity's health runs out
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DeathEvent {
+    pub entity: Entity,
+    /// Source of the killing blow
+    pub killer: Option<Entity>,
+    pub position: Vec2,
+}
+
+/// Tuning for the damage sources
+#[derive(Resource, Debug, Clone)]
+pub struct DamageSettings {
+    /// Speed given to bodies hit by a projectile, away from the projectile
+    pub projectile_knockback: f32,
+    /// Optional rule: hitting a wall hard hurts
+    pub wall_damage: bool,
+    /// Wall hits slower than this never hurt
+    pub wall_damage_min_speed: f32,
+    /// Damage per unit of impact speed above the minimum
+    pub wall_damage_per_speed: f32,
+}
+
+impl Default for DamageSettings {
+    fn default() -> Self {
+        Self {
+            projectile_knockback: 150.0,
+            wall_damage: false,
+            wall_damage_min_speed: 900.0,
+            wall_damage_per_speed: 0.02,
+        }
+    }
+}
+
+impl DamageSettings {
+    pub fn wall_damage_for(&self, speed: f32) -> f32 {
+        if !self.wall_damage || speed <= self.wall_damage_min_speed {
+            return 0.0;
+        }
+        (speed - self.wall_damage_min_speed) * self.wall_damage_per_speed
+    }
+}
+
+/// Dead players waiting to come back, with the entity they replace
+#[derive(Resource, Debug, Clone, Default)]
+pub struct PendingRespawns {
+    pub entries: Vec<PendingRespawn>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PendingRespawn {
+    pub previous: Entity,
+    pub time_left: f32,
+    /// Input binding the new player takes over
+    pub controlled_by: Option<ControlledBy>,
+    /// Netplay handle the new player takes over, so it is driven from its
+    /// first simulated tick
+    pub rollback_player: Option<RollbackPlayer>,
+    /// Race run the new player carries on, its clock keeps running while dead
+    pub race: Option<RaceProgress>,
+    /// Entities that targeted the dead player, they target the new one
+    pub chasers: Vec<Entity>,
+}
+
+/// Spawn point furthest from every `threat`, the first one when there are none
+pub fn safest_spawn_point(spawn_points: &[Vec2], threats: &[Vec2]) -> Option<Vec2> {
+    let clearance = |point: &Vec2| {
+        threats
+            .iter()
+            .map(|threat| threat.distance(*point))
+            .fold(f32::INFINITY, f32::min)
+    };
+    spawn_points.iter().copied().reduce(|best, point| {
+        if clearance(&point) > clearance(&best) {
+            point
+        } else {
+            best
+        }
+    })
+}
+
+/// Projectile hits on bodies become damage attributed to the shooter
+pub fn projectile_damage(
+    settings: Res<DamageSettings>,
+    mut hits: EventReader<ProjectileHit>,
+    targets: Query<&Transform>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for hit in hits.iter() {
+        let Some(target) = hit.target else {
+            continue;
+        };
+        let away = targets
+            .get(target)
+            .map(|transform| (transform.translation.truncate() - hit.position).normalize_or_zero())
+            .unwrap_or_default();
+        damage_events.send(DamageEvent {
+            target,
+            source: hit.owner,
+            amount: hit.damage,
+            knockback: away * settings.projectile_knockback,
+        });
+    }
+}
+
+/// Optional rule: fast wall hits hurt the body that hit the wall
+pub fn wall_impact_damage(
+    settings: Res<DamageSettings>,
+    mut impacts: EventReader<WallImpact>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for impact in impacts.iter() {
+        let amount = settings.wall_damage_for(impact.speed);
+        if amount > 0.0 {
+            damage_events.send(DamageEvent {
+                target: impact.entity,
+                source: None,
+                amount,
+                knockback: Vec2::ZERO,
+            });
+        }
+    }
+}
+
+pub fn tick_invulnerability(fixed_time: Res<FixedTime>, mut healths: Query<&mut Health>) {
+    let delta = fixed_delta(&fixed_time);
+    for mut health in healths.iter_mut() {
+        if health.invulnerable_left > 0.0 {
+            health.invulnerable_left = (health.invulnerable_left - delta).max(0.0);
+        }
+    }
+}
+
+/// Damage and knockback receivers
+pub type DamageQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Health,
+        &'static Transform,
+        Option<&'static mut LinearVelocity>,
+        Option<&'static Mass>,
+        Option<&'static mut VisualFeedback>,
+        Option<&'static DeathBehavior>,
+    ),
+>;
+
+/// Applies damage, flashes the sprite, starts invulnerability and handles death
+pub fn apply_damage(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut targets: DamageQuery,
+    carried: Query<(
+        Option<&ControlledBy>,
+        Option<&RollbackPlayer>,
+        Option<&RaceProgress>,
+    )>,
+    chasers: Query<(Entity, &Target)>,
+    mut respawns: ResMut<PendingRespawns>,
+    mut death_events: EventWriter<DeathEvent>,
+) {
+    for event in damage_events.iter() {
+        let Ok((mut health, transform, velocity, mass, feedback, death)) =
+            targets.get_mut(event.target)
+        else {
+            continue;
+        };
+        if health.damage(event.amount) <= 0.0 {
+            continue;
+        }
+
+        if let Some(mut velocity) = velocity {
+            velocity.0 += event.knockback / mass.copied().unwrap_or_default().0;
+        }
+        if let Some(mut feedback) = feedback {
+            feedback.trigger(VisualState::Hurt, 0.15);
+            feedback.trigger(VisualState::Invulnerable, health.invulnerability);
+        }
+        if !health.is_dead() {
+            continue;
+        }
+
+        death_events.send(DeathEvent {
+            entity: event.target,
+            killer: event.source,
+            position: transform.translation.truncate(),
+        });
+        if let Some(DeathBehavior::Respawn { delay }) = death.copied() {
+            let (controlled_by, rollback_player, race) =
+                carried.get(event.target).unwrap_or_default();
+            respawns.entries.push(PendingRespawn {
+                previous: event.target,
+                time_left: delay,
+                controlled_by: controlled_by.copied(),
+                rollback_player: rollback_player.copied(),
+                race: race.cloned(),
+                chasers: chasers
+                    .iter()
+                    .filter(|(_, target)| target.0 == event.target)
+                    .map(|(chaser, _)| chaser)
+                    .collect(),
+            });
+        }
+        commands.entity(event.target).despawn_related();
+    }
+}
+
+/// Brings dead players back at the safest spawn point once their delay is up,
+/// with the input binding, netplay handle and race run of the player they replace
+pub fn respawn_players(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    level: Res<CurrentLevel>,
+    mut respawns: ResMut<PendingRespawns>,
+    threats: Query<&Transform, With<Enemy>>,
+) {
+    let delta = fixed_delta(&fixed_time);
+    let threat_positions: Vec<Vec2> = threats.iter().map(|t| t.translation.truncate()).collect();
+
+    for entry in respawns.entries.iter_mut() {
+        entry.time_left -= delta;
+        if let Some(race) = entry.race.as_mut().filter(|race| !race.finished) {
+            race.ticks += 1;
+        }
+    }
+    let (ready, waiting): (Vec<_>, Vec<_>) = respawns
+        .entries
+        .drain(..)
+        .partition(|entry| entry.time_left <= 0.0);
+    respawns.entries = waiting;
+
+    for entry in ready {
+        let Some(point) = safest_spawn_point(&level.definition.spawn_points, &threat_positions)
+        else {
+            continue;
+        };
+        let player = spawn_player(&mut commands, point);
+        if let Some(controlled_by) = entry.controlled_by {
+            commands.entity(player).insert(controlled_by);
+        }
+        if let Some(rollback_player) = entry.rollback_player {
+            commands.entity(player).insert(rollback_player);
+        }
+        if let Some(race) = entry.race {
+            commands.entity(player).insert(race);
+        }
+        for chaser in entry.chasers {
+            if let Some(mut chaser) = commands.get_entity(chaser) {
+                chaser.insert(Target(player));
+            }
+        }
+    }
+}
+
+/// Nobody comes back into a session that was torn down
+pub fn clear_pending_respawns(
+    mut resets: EventReader<GameplayReset>,
+    mut respawns: ResMut<PendingRespawns>,
+) {
+    if resets.iter().count() > 0 {
+        respawns.entries.clear();
+    }
+}
+
+/// Bundle health, damage and respawning for easy registration
+pub struct HealthSystems;
+
+impl HealthSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.init_resource::<DamageSettings>()
+            .init_resource::<PendingRespawns>()
+            .add_event::<DamageEvent>()
+            .add_event::<DeathEvent>()
+            .add_event::<ProjectileHit>()
+            .add_event::<WallImpact>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    tick_invulnerability,
+                    projectile_damage,
+                    wall_impact_damage,
+                    apply_damage,
+                    respawn_players,
+                )
+                    .chain()
+                    .after(update_projectiles)
+                    .in_set(PhysicsStep::Simulate),
+            )
+            .add_event::<GameplayReset>()
+            .add_systems(Last, clear_pending_respawns)
+    }
+}
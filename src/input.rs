@@ -1,54 +1,251 @@
-use crate::components::Player;
+use crate::components::{Acceleration, MovementIntent};
+use crate::modifiers::{Stat, StatModifiers};
+use crate::mouse_control::ControlMode;
+use crate::stamina::{is_boosting, BoostSettings, Stamina};
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-/// Input handling system for WASD movement
-pub fn handle_input(
-    keyboard_input: Res<Input<KeyCode>>,
-    mouse_input: Res<Input<MouseButton>>,
-    mut query: Query<(&mut Player, &mut Sprite)>,
-) {
-    let mut input_force: f32 = 3000.0; // Base acceleration force from input
+/// Keyboard layouts that let two players share one keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KeyboardScheme {
+    /// WASD, left shift boosts, also owns the mouse
+    A,
+    /// Arrow keys, right shift boosts
+    B,
+}
 
-    for (mut player, mut sprite) in query.iter_mut() {
-        let mut input_vector = Vec2::ZERO;
+/// Input source a player entity reads its movement from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputSource {
+    Keyboard(KeyboardScheme),
+    /// Gamepad by id, as assigned by Bevy when the pad connects
+    Gamepad(usize),
+}
 
-        sprite.color = Color::rgb(0.25, 0.25, 0.75);
-        if keyboard_input.pressed(KeyCode::ShiftLeft) || keyboard_input.pressed(KeyCode::ShiftRight)
-        {
-            input_force = input_force * 3.0;
-            sprite.color = Color::rgb(0.9, 0.25, 0.75);
-        }
-        if mouse_input.pressed(MouseButton::Left)
-        {
-            input_force = input_force * 3.0;
-            sprite.color = Color::rgb(0.9, 0.9, 0.75);
-        }
+/// Binds a player entity to one input source for local multiplayer.
+/// Players without this component react to every keyboard binding and the mouse.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlledBy(pub InputSource);
 
-        if keyboard_input.pressed(KeyCode::L) || keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right) {
-            input_vector.x += 1.0;
-        }
+impl ControlledBy {
+    pub fn keyboard(scheme: KeyboardScheme) -> Self {
+        Self(InputSource::Keyboard(scheme))
+    }
+
+    pub fn gamepad(id: usize) -> Self {
+        Self(InputSource::Gamepad(id))
+    }
 
-        // Check WASD keys and build input vector
-        if keyboard_input.pressed(KeyCode::K) || keyboard_input.pressed(KeyCode::W) || keyboard_input.pressed(KeyCode::Up) {
-            input_vector.y += 1.0;
+    /// Binding for the `index`th local player: keyboard scheme A, then B,
+    /// then gamepads in the order they connected
+    pub fn for_player(index: usize) -> Self {
+        match index {
+            0 => Self::keyboard(KeyboardScheme::A),
+            1 => Self::keyboard(KeyboardScheme::B),
+            _ => Self::gamepad(index - 2),
         }
-        if keyboard_input.pressed(KeyCode::J) || keyboard_input.pressed(KeyCode::S) || keyboard_input.pressed(KeyCode::Down) {
-            input_vector.y -= 1.0;
+    }
+}
+
+/// Whether a player reads the mouse: scheme A players and unbound players do
+pub fn uses_mouse(controlled_by: Option<&ControlledBy>) -> bool {
+    match controlled_by {
+        None => true,
+        Some(ControlledBy(source)) => *source == InputSource::Keyboard(KeyboardScheme::A),
+    }
+}
+
+/// Key table for one keyboard layout
+#[derive(Debug, Clone, Copy)]
+pub struct KeyBindings {
+    pub up: &'static [KeyCode],
+    pub down: &'static [KeyCode],
+    pub left: &'static [KeyCode],
+    pub right: &'static [KeyCode],
+    pub boost: &'static [KeyCode],
+    pub fire: &'static [KeyCode],
+}
+
+const SCHEME_A_BINDINGS: KeyBindings = KeyBindings {
+    up: &[KeyCode::W],
+    down: &[KeyCode::S],
+    left: &[KeyCode::A],
+    right: &[KeyCode::D],
+    boost: &[KeyCode::ShiftLeft],
+    fire: &[KeyCode::Space],
+};
+
+const SCHEME_B_BINDINGS: KeyBindings = KeyBindings {
+    up: &[KeyCode::Up],
+    down: &[KeyCode::Down],
+    left: &[KeyCode::Left],
+    right: &[KeyCode::Right],
+    boost: &[KeyCode::ShiftRight],
+    fire: &[KeyCode::ControlRight],
+};
+
+/// Single player bindings: WASD, arrows and HJKL all move the cube
+const SHARED_BINDINGS: KeyBindings = KeyBindings {
+    up: &[KeyCode::K, KeyCode::W, KeyCode::Up],
+    down: &[KeyCode::J, KeyCode::S, KeyCode::Down],
+    left: &[KeyCode::H, KeyCode::A, KeyCode::Left],
+    right: &[KeyCode::L, KeyCode::D, KeyCode::Right],
+    boost: &[KeyCode::ShiftLeft, KeyCode::ShiftRight],
+    fire: &[KeyCode::Space, KeyCode::ControlRight],
+};
+
+impl KeyboardScheme {
+    pub fn bindings(self) -> KeyBindings {
+        match self {
+            KeyboardScheme::A => SCHEME_A_BINDINGS,
+            KeyboardScheme::B => SCHEME_B_BINDINGS,
         }
-        if keyboard_input.pressed(KeyCode::H) || keyboard_input.pressed(KeyCode::A) || keyboard_input.pressed(KeyCode::Left) {
-            input_vector.x -= 1.0;
+    }
+}
+
+/// Raw movement read from one input source for one frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SourceInput {
+    /// Movement direction, length at most 1
+    pub direction: Vec2,
+    pub boost: bool,
+    pub fire: bool,
+}
+
+/// Read a keyboard layout into a direction plus boost and fire flags
+pub fn read_keyboard(keyboard_input: &Input<KeyCode>, bindings: &KeyBindings) -> SourceInput {
+    let pressed = |keys: &[KeyCode]| keys.iter().any(|&key| keyboard_input.pressed(key));
+
+    let mut input_vector = Vec2::ZERO;
+    if pressed(bindings.up) {
+        input_vector.y += 1.0;
+    }
+    if pressed(bindings.down) {
+        input_vector.y -= 1.0;
+    }
+    if pressed(bindings.left) {
+        input_vector.x -= 1.0;
+    }
+    if pressed(bindings.right) {
+        input_vector.x += 1.0;
+    }
+
+    // Normalize diagonal movement to prevent faster diagonal speed
+    SourceInput {
+        direction: input_vector.normalize_or_zero(),
+        boost: pressed(bindings.boost),
+        fire: pressed(bindings.fire),
+    }
+}
+
+/// Read a gamepad's left stick and d-pad into a direction plus boost and fire flags
+pub fn read_gamepad(
+    gamepad: Gamepad,
+    axes: &Axis<GamepadAxis>,
+    buttons: &Input<GamepadButton>,
+) -> SourceInput {
+    let axis = |axis_type| {
+        axes.get(GamepadAxis::new(gamepad, axis_type))
+            .unwrap_or(0.0)
+    };
+    let button = |button_type| buttons.pressed(GamepadButton::new(gamepad, button_type));
+
+    let mut input_vector = Vec2::new(
+        axis(GamepadAxisType::LeftStickX),
+        axis(GamepadAxisType::LeftStickY),
+    );
+    if button(GamepadButtonType::DPadUp) {
+        input_vector.y += 1.0;
+    }
+    if button(GamepadButtonType::DPadDown) {
+        input_vector.y -= 1.0;
+    }
+    if button(GamepadButtonType::DPadLeft) {
+        input_vector.x -= 1.0;
+    }
+    if button(GamepadButtonType::DPadRight) {
+        input_vector.x += 1.0;
+    }
+
+    // Keep analog magnitude for gentle stick input, but never exceed full force
+    SourceInput {
+        direction: input_vector.clamp_length_max(1.0),
+        boost: button(GamepadButtonType::South) || button(GamepadButtonType::RightTrigger2),
+        fire: button(GamepadButtonType::West) || button(GamepadButtonType::RightTrigger),
+    }
+}
+
+/// Raw input for the player bound to `controlled_by`, unbound players read the shared keys
+pub fn read_source(
+    controlled_by: Option<&ControlledBy>,
+    keyboard_input: &Input<KeyCode>,
+    gamepad_axes: &Axis<GamepadAxis>,
+    gamepad_buttons: &Input<GamepadButton>,
+) -> SourceInput {
+    match controlled_by {
+        None => read_keyboard(keyboard_input, &SHARED_BINDINGS),
+        Some(ControlledBy(InputSource::Keyboard(scheme))) => {
+            read_keyboard(keyboard_input, &scheme.bindings())
         }
-        if keyboard_input.pressed(KeyCode::L) || keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right) {
-            input_vector.x += 1.0;
+        Some(ControlledBy(InputSource::Gamepad(id))) => {
+            read_gamepad(Gamepad::new(*id), gamepad_axes, gamepad_buttons)
         }
+    }
+}
 
+/// Base acceleration force applied for full input
+pub const BASE_INPUT_FORCE: f32 = 3000.0;
 
-        // Normalize diagonal movement to prevent faster diagonal speed
-        if input_vector != Vec2::ZERO {
-            input_vector = input_vector.normalize();
-        }
+/// Input handling system, each player reads the source it is bound to and
+/// records a `MovementIntent` that the fixed timestep turns into force.
+/// Visual feedback for boosting is derived from the intent in `visuals.rs`.
+pub fn handle_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    control_mode: Res<ControlMode>,
+    mut query: Query<(&mut MovementIntent, Option<&ControlledBy>)>,
+) {
+    for (mut intent, controlled_by) in query.iter_mut() {
+        let source_input = read_source(
+            controlled_by,
+            &keyboard_input,
+            &gamepad_axes,
+            &gamepad_buttons,
+        );
+
+        *intent = MovementIntent {
+            direction: source_input.direction,
+            boost: source_input.boost,
+            // In the mouse control modes the left button steers instead of boosting
+            mouse_boost: uses_mouse(controlled_by)
+                && !control_mode.uses_mouse()
+                && mouse_input.pressed(MouseButton::Left),
+        };
+    }
+}
+
+/// Turns each player's intent into acceleration on the fixed timestep.
+/// Boost only applies while the player's stamina allows it; players without
+/// `Stamina` boost freely. The force comes from `StatModifiers` when present.
+pub fn apply_movement_intent(
+    boost_settings: Res<BoostSettings>,
+    mut query: Query<(
+        &MovementIntent,
+        &mut Acceleration,
+        Option<&Stamina>,
+        Option<&StatModifiers>,
+    )>,
+) {
+    for (intent, mut acceleration, stamina, modifiers) in query.iter_mut() {
+        let multiplier = if is_boosting(intent, stamina) {
+            boost_settings.multiplier(intent)
+        } else {
+            1.0
+        };
 
-        // Apply input force to acceleration
-        player.acceleration = input_vector * input_force;
+        let force = modifiers.map_or(BASE_INPUT_FORCE, |m| m.value(Stat::Force));
+        acceleration.0 = intent.direction * force * multiplier;
     }
 }
@@ -0,0 +1,547 @@
+use crate::camera::CameraTarget;
+use crate::collectibles::{spawn_collectible, Collectible};
+use crate::components::{
+    CollisionRadius, FireIntent, MovementIntent, Obstacle, PhysicsBundle, Player,
+};
+use crate::enemy::{spawn_enemy, Steering, SteeringBehavior};
+use crate::entity_relations::RelationCommandsExt;
+use crate::game_state::{GameState, GameplayEntity, GameplayReset};
+use crate::health::{DeathBehavior, Health};
+use crate::input::ControlledBy;
+use crate::modifiers::StatModifiers;
+use crate::particles::{BoostTrail, ParticleEffect, ParticleEmitter};
+use crate::power_ups::{spawn_power_up, PowerUp, PowerUpKind};
+use crate::projectiles::Weapon;
+use crate::race::{spawn_checkpoint, Checkpoint, RaceProgress};
+use crate::replication::ReplicationClient;
+use crate::resources::WorldBounds;
+use crate::stamina::Stamina;
+use crate::visuals::VisualFeedback;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Level played when no other level has been loaded. Embedded so it also works on WASM.
+pub const DEFAULT_LEVEL_PATH: &str = "assets/levels/arena.ron";
+const DEFAULT_LEVEL_SOURCE: &str = include_str!("../assets/levels/arena.ron");
+
+/// Playable area of a level
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoundsDefinition {
+    pub min: Vec2,
+    pub max: Vec2,
+    /// World friction, the fraction of velocity lost per second
+    pub friction: f32,
+    /// Fraction of speed kept when bouncing off the bounds, at most 1.0
+    #[serde(default = "default_bounce_factor")]
+    pub bounce_factor: f32,
+}
+
+fn default_bounce_factor() -> f32 {
+    0.8
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObstacleDefinition {
+    /// Center of the box
+    pub position: Vec2,
+    pub size: Vec2,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PickupDefinition {
+    pub position: Vec2,
+    /// Points before the combo multiplier, the collectible default when omitted
+    #[serde(default)]
+    pub value: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnemyDefinition {
+    pub position: Vec2,
+    #[serde(default)]
+    pub behavior: SteeringBehavior,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PowerUpDefinition {
+    pub position: Vec2,
+    pub kind: PowerUpKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointDefinition {
+    pub position: Vec2,
+    #[serde(default = "default_checkpoint_radius")]
+    pub radius: f32,
+}
+
+fn default_checkpoint_radius() -> f32 {
+    60.0
+}
+
+/// Time-attack course: checkpoints passed in order, `laps` times
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RaceDefinition {
+    pub checkpoints: Vec<CheckpointDefinition>,
+    #[serde(default = "default_laps")]
+    pub laps: u32,
+}
+
+fn default_laps() -> u32 {
+    1
+}
+
+/// Everything needed to build an arena, read from a RON level file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelDefinition {
+    pub name: String,
+    pub bounds: BoundsDefinition,
+    /// Player start positions, one player per spawn point
+    pub spawn_points: Vec<Vec2>,
+    #[serde(default)]
+    pub obstacles: Vec<ObstacleDefinition>,
+    #[serde(default)]
+    pub pickups: Vec<PickupDefinition>,
+    #[serde(default)]
+    pub enemies: Vec<EnemyDefinition>,
+    #[serde(default)]
+    pub power_ups: Vec<PowerUpDefinition>,
+    /// Turns the level into a time-attack course
+    #[serde(default)]
+    pub race: Option<RaceDefinition>,
+}
+
+/// Why a level could not be loaded. Every variant names the file it came from.
+#[derive(Debug)]
+pub enum LevelError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    Invalid {
+        path: PathBuf,
+        /// Path to the offending field, such as `enemies[2].position`
+        field: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io { path, source } => {
+                write!(f, "{}: could not read level: {}", path.display(), source)
+            }
+            LevelError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            LevelError::Invalid {
+                path,
+                field,
+                message,
+            } => write!(f, "{}: `{}` {}", path.display(), field, message),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LevelError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl LevelDefinition {
+    /// Parse and validate RON level source. `path` is only used in error messages.
+    pub fn from_ron(source: &str, path: impl AsRef<Path>) -> Result<Self, LevelError> {
+        let path = path.as_ref();
+        let level: LevelDefinition = ron::from_str(source).map_err(|error| LevelError::Parse {
+            path: path.to_path_buf(),
+            line: error.position.line,
+            column: error.position.col,
+            message: error.code.to_string(),
+        })?;
+        level.validate(path)?;
+        Ok(level)
+    }
+
+    /// Read, parse and validate a level file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LevelError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| LevelError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_ron(&source, path)
+    }
+
+    /// The embedded default arena
+    pub fn builtin() -> Self {
+        Self::from_ron(DEFAULT_LEVEL_SOURCE, DEFAULT_LEVEL_PATH)
+            .expect("the embedded default level is valid")
+    }
+
+    /// Check the values serde cannot, reporting the first invalid field
+    pub fn validate(&self, path: &Path) -> Result<(), LevelError> {
+        let invalid = |field: String, message: &str| LevelError::Invalid {
+            path: path.to_path_buf(),
+            field,
+            message: message.to_string(),
+        };
+        let bounds = self.world_bounds();
+
+        if !(self.bounds.min.is_finite() && self.bounds.max.is_finite()) {
+            return Err(invalid("bounds".into(), "must be finite"));
+        }
+        if self.bounds.min.cmpge(self.bounds.max).any() {
+            return Err(invalid(
+                "bounds.max".into(),
+                "must be greater than bounds.min",
+            ));
+        }
+        if !(0.0..1.0).contains(&self.bounds.friction) {
+            return Err(invalid("bounds.friction".into(), "must be in 0.0..1.0"));
+        }
+        if !(0.0..=1.0).contains(&self.bounds.bounce_factor) {
+            return Err(invalid(
+                "bounds.bounce_factor".into(),
+                "must be in 0.0..=1.0",
+            ));
+        }
+
+        if self.spawn_points.is_empty() {
+            return Err(invalid("spawn_points".into(), "needs at least one entry"));
+        }
+        for (i, point) in self.spawn_points.iter().enumerate() {
+            if !bounds.contains(*point) {
+                return Err(invalid(
+                    format!("spawn_points[{i}]"),
+                    "is outside the bounds",
+                ));
+            }
+        }
+
+        for (i, obstacle) in self.obstacles.iter().enumerate() {
+            if obstacle.size.cmple(Vec2::ZERO).any() {
+                return Err(invalid(format!("obstacles[{i}].size"), "must be positive"));
+            }
+            if !bounds.contains(obstacle.position) {
+                return Err(invalid(
+                    format!("obstacles[{i}].position"),
+                    "is outside the bounds",
+                ));
+            }
+            let rect = Obstacle::new(obstacle.size).rect(obstacle.position);
+            if let Some(j) = self.spawn_points.iter().position(|p| rect.contains(*p)) {
+                return Err(invalid(
+                    format!("spawn_points[{j}]"),
+                    &format!("is inside obstacles[{i}]"),
+                ));
+            }
+        }
+
+        for (i, pickup) in self.pickups.iter().enumerate() {
+            if !bounds.contains(pickup.position) {
+                return Err(invalid(
+                    format!("pickups[{i}].position"),
+                    "is outside the bounds",
+                ));
+            }
+            if pickup.value == Some(0) {
+                return Err(invalid(format!("pickups[{i}].value"), "must be positive"));
+            }
+        }
+
+        for (i, enemy) in self.enemies.iter().enumerate() {
+            if !bounds.contains(enemy.position) {
+                return Err(invalid(
+                    format!("enemies[{i}].position"),
+                    "is outside the bounds",
+                ));
+            }
+        }
+
+        for (i, power_up) in self.power_ups.iter().enumerate() {
+            if !bounds.contains(power_up.position) {
+                return Err(invalid(
+                    format!("power_ups[{i}].position"),
+                    "is outside the bounds",
+                ));
+            }
+        }
+
+        if let Some(race) = &self.race {
+            if race.checkpoints.is_empty() {
+                return Err(invalid(
+                    "race.checkpoints".into(),
+                    "needs at least one entry",
+                ));
+            }
+            if race.laps == 0 {
+                return Err(invalid("race.laps".into(), "must be positive"));
+            }
+            for (i, checkpoint) in race.checkpoints.iter().enumerate() {
+                if !bounds.contains(checkpoint.position) {
+                    return Err(invalid(
+                        format!("race.checkpoints[{i}].position"),
+                        "is outside the bounds",
+                    ));
+                }
+                if checkpoint.radius <= 0.0 {
+                    return Err(invalid(
+                        format!("race.checkpoints[{i}].radius"),
+                        "must be positive",
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn world_bounds(&self) -> WorldBounds {
+        WorldBounds {
+            min: self.bounds.min,
+            max: self.bounds.max,
+            friction: self.bounds.friction,
+            bounce_factor: self.bounds.bounce_factor,
+        }
+    }
+}
+
+/// Level the session is built from, and the file it was read from
+#[derive(Resource, Debug, Clone)]
+pub struct CurrentLevel {
+    pub definition: LevelDefinition,
+    pub path: PathBuf,
+}
+
+impl Default for CurrentLevel {
+    fn default() -> Self {
+        Self {
+            definition: LevelDefinition::builtin(),
+            path: PathBuf::from(DEFAULT_LEVEL_PATH),
+        }
+    }
+}
+
+/// Request to replace the current level with the one in the given file
+#[derive(Event, Debug, Clone)]
+pub struct LoadLevel(pub PathBuf);
+
+/// Spawn a player cube with physics, stamina, health, a weapon, stat modifiers,
+/// visual feedback and a boost trail
+pub fn spawn_player(commands: &mut Commands, position: Vec2) -> Entity {
+    commands.spawn(player_bundle(position)).id()
+}
+
+/// Everything `spawn_player` gives a player
+pub fn player_bundle(position: Vec2) -> impl Bundle {
+    let color = Color::rgb(0.25, 0.25, 0.75);
+    (
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::new(50.0, 50.0)),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(0.0)),
+            ..default()
+        },
+        Player,
+        PhysicsBundle::default(),
+        StatModifiers::default(),
+        MovementIntent::default(),
+        FireIntent::default(),
+        Weapon::default(),
+        Stamina::default(),
+        Health::new(100.0),
+        DeathBehavior::Respawn { delay: 2.0 },
+        VisualFeedback::new(color),
+        CollisionRadius::default(),
+        CameraTarget,
+        (
+            BoostTrail,
+            ParticleEmitter::new(ParticleEffect::boost_trail(), 60.0),
+        ),
+        GameplayEntity,
+    )
+}
+
+/// Spawn a grey obstacle box centered at `position`
+pub fn spawn_obstacle(commands: &mut Commands, position: Vec2, obstacle: Obstacle) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(0.4, 0.4, 0.45),
+                    custom_size: Some(obstacle.half_size * 2.0),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(0.0)),
+                ..default()
+            },
+            obstacle,
+            GameplayEntity,
+        ))
+        .id()
+}
+
+/// Spawn every entity a level describes and return the players.
+/// With several spawn points each player is bound to its own keys or
+/// gamepad, a lone player reads them all. Enemies target the first player;
+/// on a race course every player starts a run.
+pub fn spawn_level(commands: &mut Commands, level: &LevelDefinition) -> Vec<Entity> {
+    for obstacle in level.obstacles.iter() {
+        spawn_obstacle(commands, obstacle.position, Obstacle::new(obstacle.size));
+    }
+
+    for pickup in level.pickups.iter() {
+        let mut collectible = Collectible::default();
+        if let Some(value) = pickup.value {
+            collectible.value = value;
+        }
+        spawn_collectible(commands, pickup.position, collectible);
+    }
+
+    for power_up in level.power_ups.iter() {
+        spawn_power_up(commands, power_up.position, PowerUp::new(power_up.kind));
+    }
+
+    let players: Vec<Entity> = level
+        .spawn_points
+        .iter()
+        .map(|point| spawn_player(commands, *point))
+        .collect();
+    if players.len() > 1 {
+        for (index, player) in players.iter().enumerate() {
+            commands
+                .entity(*player)
+                .insert(ControlledBy::for_player(index));
+        }
+    }
+
+    for enemy in level.enemies.iter() {
+        spawn_enemy(
+            commands,
+            enemy.position,
+            Steering::new(enemy.behavior),
+            players.first().copied(),
+        );
+    }
+
+    if let Some(race) = &level.race {
+        for (index, checkpoint) in race.checkpoints.iter().enumerate() {
+            let gate = Checkpoint {
+                index,
+                radius: checkpoint.radius,
+            };
+            spawn_checkpoint(commands, checkpoint.position, gate);
+        }
+        for player in players.iter() {
+            commands
+                .entity(*player)
+                .insert(RaceProgress::new(race.checkpoints.len(), race.laps));
+        }
+    }
+
+    players
+}
+
+/// Build the current level when a session starts. A replication client only
+/// takes the bounds, its entities come from the server.
+pub fn spawn_current_level(
+    mut commands: Commands,
+    level: Res<CurrentLevel>,
+    mut bounds: ResMut<WorldBounds>,
+    client: Option<Res<ReplicationClient>>,
+) {
+    *bounds = level.definition.world_bounds();
+    if client.is_some() {
+        return;
+    }
+    info!("Loading level \"{}\"", level.definition.name);
+    spawn_level(&mut commands, &level.definition);
+}
+
+/// F5 reloads the current level file from disk
+pub fn handle_level_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    level: Res<CurrentLevel>,
+    mut load_events: EventWriter<LoadLevel>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        load_events.send(LoadLevel(level.path.clone()));
+    }
+}
+
+/// Replaces the current level. A running session is torn down and rebuilt from
+/// the new level; an invalid file is reported and the current level is kept.
+pub fn load_level(
+    mut commands: Commands,
+    mut load_events: EventReader<LoadLevel>,
+    state: Res<State<GameState>>,
+    mut level: ResMut<CurrentLevel>,
+    mut bounds: ResMut<WorldBounds>,
+    gameplay: Query<Entity, With<GameplayEntity>>,
+    mut resets: EventWriter<GameplayReset>,
+) {
+    let Some(LoadLevel(path)) = load_events.iter().last() else {
+        return;
+    };
+
+    let definition = match LevelDefinition::load(path) {
+        Ok(definition) => definition,
+        Err(error) => {
+            error!("{}", error);
+            return;
+        }
+    };
+
+    *level = CurrentLevel {
+        definition,
+        path: path.clone(),
+    };
+
+    if !matches!(state.get(), GameState::Playing | GameState::Paused) {
+        return;
+    }
+
+    info!("Reloading level \"{}\"", level.definition.name);
+    for entity in gameplay.iter() {
+        commands.entity(entity).despawn_related();
+    }
+    resets.send(GameplayReset);
+    *bounds = level.definition.world_bounds();
+    spawn_level(&mut commands, &level.definition);
+}
+
+/// Bundle level loading for easy registration
+pub struct LevelSystems;
+
+impl LevelSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.init_resource::<CurrentLevel>()
+            .init_resource::<WorldBounds>()
+            .add_event::<LoadLevel>()
+            .add_event::<GameplayReset>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                spawn_current_level.run_if(not(any_with_component::<GameplayEntity>())),
+            )
+            .add_systems(Update, (handle_level_input, load_level).chain())
+    }
+}
@@ -1,15 +1,69 @@
 pub mod components;
 pub mod resources;
 pub mod input;
+pub mod mouse_control;
 pub mod systems;
+pub mod physics_debug;
+pub mod stamina;
+pub mod visuals;
 pub mod query_utils;
 pub mod entity_relations;
 pub mod dynamic_queries;
+pub mod game_state;
+pub mod rng;
+pub mod collectibles;
+pub mod enemy;
+pub mod level;
+pub mod procgen;
+pub mod save;
+pub mod camera;
+pub mod particles;
+pub mod race;
+pub mod projectiles;
+pub mod health;
+pub mod modifiers;
+pub mod power_ups;
+pub mod transport;
+pub mod rollback;
+pub mod replication;
+pub mod replay;
+pub mod bots;
+pub mod soak;
+pub mod headless;
+pub mod transform_propagation;
+pub mod reference_validation;
 
 pub use components::*;
 pub use resources::*;
 pub use input::*;
+pub use mouse_control::*;
 pub use systems::*;
+pub use physics_debug::*;
+pub use stamina::*;
+pub use visuals::*;
 pub use query_utils::*;
 pub use entity_relations::*;
 pub use dynamic_queries::*;
+pub use game_state::*;
+pub use rng::*;
+pub use collectibles::*;
+pub use enemy::*;
+pub use level::*;
+pub use procgen::*;
+pub use save::*;
+pub use camera::*;
+pub use particles::*;
+pub use race::*;
+pub use projectiles::*;
+pub use health::*;
+pub use modifiers::*;
+pub use power_ups::*;
+pub use transport::*;
+pub use rollback::*;
+pub use replication::*;
+pub use replay::*;
+pub use bots::*;
+pub use soak::*;
+pub use headless::*;
+pub use transform_propagation::*;
+pub use reference_validation::*;
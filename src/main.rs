@@ -1,40 +1,129 @@
 use bevy::prelude::*;
-use bevy_wasm_game::{components::*, input::*, resources::*, systems::*};
+use bevy_wasm_game::{
+    camera::*, collectibles::*, enemy::*, game_state::*, health::*, input::*, level::*,
+    modifiers::*, mouse_control::*, particles::*, physics_debug::*, power_ups::*, procgen::*,
+    projectiles::*, race::*, reference_validation::*, replication::*, rollback::*, save::*,
+    stamina::*, systems::*, transform_propagation::*, visuals::*,
+};
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "Physics Cube Game".to_string(),
-                canvas: Some("#bevy".to_owned()),
-                ..default()
-            }),
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins.set(WindowPlugin {
+        primary_window: Some(Window {
+            title: "Physics Cube Game".to_string(),
+            canvas: Some("#bevy".to_owned()),
             ..default()
-        }))
-        .insert_resource(WorldBounds::default_bounds())
-        .add_systems(Startup, setup)
-        .add_systems(Update, handle_input)
-        .add_systems(
-            FixedUpdate,
-            (world_friction, player_physics_integration, boundary_collision).chain(),
+        }),
+        ..default()
+    }))
+    .init_resource::<ControlMode>()
+    .init_resource::<MouseSteeringSettings>()
+    .init_resource::<CursorWorldPosition>()
+    .init_resource::<BoostSettings>()
+    .init_resource::<VisualPalette>()
+    .add_event::<StaminaDepleted>()
+    .add_event::<StaminaFull>()
+    .add_systems(Startup, setup)
+    .add_systems(
+        Update,
+        (
+            update_cursor_world_position,
+            cycle_control_mode,
+            handle_input,
+            mouse_control,
         )
-        .run();
+            .chain()
+            .run_if(in_state(GameState::Playing)),
+    )
+    .add_systems(
+        Update,
+        (derive_boost_visuals, apply_visual_feedback)
+            .chain()
+            .after(mouse_control),
+    )
+    .configure_set(FixedUpdate, PhysicsSet.run_if(in_state(GameState::Playing)));
+    PhysicsSystems::add_to_app(&mut app);
+    TransformPropagationSystems::add_to_app(&mut app);
+    ReferenceValidationSystems::add_to_app(&mut app);
+    PhysicsDebugSystems::add_to_app(&mut app);
+    CollectibleSystems::add_to_app(&mut app);
+    EnemySystems::add_to_app(&mut app);
+    LevelSystems::add_to_app(&mut app);
+    RaceSystems::add_to_app(&mut app);
+    SaveSystems::add_to_app(&mut app);
+    CameraSystems::add_to_app(&mut app);
+    ParticleSystems::add_to_app(&mut app);
+    ProjectileSystems::add_to_app(&mut app);
+    HealthSystems::add_to_app(&mut app);
+    ModifierSystems::add_to_app(&mut app);
+    PowerUpSystems::add_to_app(&mut app);
+    RollbackSystems::add_to_app(&mut app);
+    ReplicationSystems::add_to_app(&mut app);
+    GameStateSystems::add_to_app(&mut app);
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // An optional level file on the command line replaces the default arena
+    if let Some(path) = args.first().filter(|arg| !arg.starts_with("--")) {
+        match LevelDefinition::load(path) {
+            Ok(definition) => {
+                app.insert_resource(CurrentLevel {
+                    definition,
+                    path: path.into(),
+                });
+            }
+            Err(error) => error!("{}", error),
+        }
+    }
+
+    // `--arena <seed>` plays a generated arena, the same one for everyone with the seed
+    if let Some(seed) = args
+        .iter()
+        .position(|arg| arg == "--arena")
+        .and_then(|index| args.get(index + 1))
+    {
+        match seed.parse() {
+            Ok(seed) => {
+                let settings = ArenaSettings::with_seed(seed);
+                match settings.generate() {
+                    Ok(definition) => {
+                        app.insert_resource(CurrentLevel {
+                            definition,
+                            path: settings.level_path(),
+                        });
+                    }
+                    Err(error) => error!("{}", error),
+                }
+            }
+            Err(_) => error!("--arena needs a number, got '{}'", seed),
+        }
+    }
+
+    // `--netplay <local addr> <peer addr> <player>` plays against another instance over UDP
+    #[cfg(not(target_arch = "wasm32"))]
+    match NetplayConfig::from_args(&args).and_then(|config| config.map(|c| c.start()).transpose()) {
+        Ok(Some(session)) => {
+            app.insert_resource(session);
+        }
+        Ok(None) => {}
+        Err(error) => error!("{}", error),
+    }
+
+    // `--serve <local addr> <client addr>...` hosts an authoritative session,
+    // `--connect <local addr> <server addr>` joins one
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Err(error) = ReplicationConfig::from_args(&args)
+        .and_then(|config| config.map_or(Ok(()), |c| c.start(&mut app.world)))
+    {
+        error!("{}", error);
+    }
+    app.run();
 }
 
 fn setup(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
-
-    // Spawn player cube with physics components
     commands.spawn((
-        SpriteBundle {
-            sprite: Sprite {
-                color: Color::rgb(0.25, 0.25, 0.75),
-                custom_size: Some(Vec2::new(50.0, 50.0)),
-                ..default()
-            },
-            transform: Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)),
-            ..default()
-        },
-        Player::default(),
+        Camera2dBundle::default(),
+        CameraFollow::default(),
+        CameraShake::default(),
     ));
 }
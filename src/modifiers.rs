@@ -0,0 +1,236 @@
+use crate::components::{Friction, Mass};
+use crate::input::{apply_movement_intent, BASE_INPUT_FORCE};
+use crate::power_ups::PowerUpKind;
+use crate::systems::{fixed_delta, PhysicsStep};
+use bevy::prelude::*;
+
+/// Entity values that modifiers can change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stat {
+    /// Input force before boost, replaces `BASE_INPUT_FORCE`
+    Force,
+    /// Written to the entity's `Friction`
+    Friction,
+    /// Written to the entity's `Mass`
+    Mass,
+}
+
+/// Where a modifier came from. Stacking rules compare sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModifierSource {
+    PowerUp(PowerUpKind),
+    Entity(Entity),
+}
+
+/// One change to a stat: `add` is summed onto the base, `mul` multiplies the sum
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatModifier {
+    pub stat: Stat,
+    pub add: f32,
+    pub mul: f32,
+    /// Seconds left, `None` for a modifier that lasts until removed
+    pub remaining: Option<f32>,
+    pub source: ModifierSource,
+}
+
+impl StatModifier {
+    pub fn add(stat: Stat, amount: f32, source: ModifierSource) -> Self {
+        Self {
+            stat,
+            add: amount,
+            mul: 1.0,
+            remaining: None,
+            source,
+        }
+    }
+
+    pub fn mul(stat: Stat, factor: f32, source: ModifierSource) -> Self {
+        Self {
+            stat,
+            add: 0.0,
+            mul: factor,
+            remaining: None,
+            source,
+        }
+    }
+
+    /// Expire after `seconds`
+    pub fn lasting(self, seconds: f32) -> Self {
+        Self {
+            remaining: Some(seconds),
+            ..self
+        }
+    }
+}
+
+/// What happens when a modifier arrives while one from the same source
+/// already changes the same stat
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StackingRule {
+    /// Both apply, up to `max` modifiers from the source. At the cap the one
+    /// closest to expiring is replaced.
+    Stack { max: usize },
+    /// The new modifier replaces the old one, restarting its duration
+    #[default]
+    Refresh,
+    /// The old modifier stays and its duration grows by the new one's
+    Extend,
+    /// The old modifier stays unchanged and the new one is dropped
+    Ignore,
+}
+
+/// Base stats of an entity plus the modifiers currently applied to them.
+/// A stat's value is `(base + sum of add) * product of mul`.
+#[derive(Component, Debug, Clone)]
+pub struct StatModifiers {
+    pub base_force: f32,
+    pub base_friction: f32,
+    pub base_mass: f32,
+    modifiers: Vec<StatModifier>,
+}
+
+impl Default for StatModifiers {
+    fn default() -> Self {
+        Self::new(BASE_INPUT_FORCE, Friction::default().0, Mass::default().0)
+    }
+}
+
+impl StatModifiers {
+    pub fn new(force: f32, friction: f32, mass: f32) -> Self {
+        Self {
+            base_force: force,
+            base_friction: friction,
+            base_mass: mass,
+            modifiers: Vec::new(),
+        }
+    }
+
+    pub fn base(&self, stat: Stat) -> f32 {
+        match stat {
+            Stat::Force => self.base_force,
+            Stat::Friction => self.base_friction,
+            Stat::Mass => self.base_mass,
+        }
+    }
+
+    /// Current value of `stat`, kept inside the range the physics can use
+    pub fn value(&self, stat: Stat) -> f32 {
+        let (add, mul) = self
+            .modifiers
+            .iter()
+            .filter(|modifier| modifier.stat == stat)
+            .fold((0.0, 1.0), |(add, mul), modifier| {
+                (add + modifier.add, mul * modifier.mul)
+            });
+        let value = (self.base(stat) + add) * mul;
+        match stat {
+            Stat::Force => value.max(0.0),
+            Stat::Friction => value.clamp(0.0, 0.999),
+            Stat::Mass => value.max(0.01),
+        }
+    }
+
+    pub fn modifiers(&self) -> &[StatModifier] {
+        &self.modifiers
+    }
+
+    /// Add a modifier following `rule` for modifiers from the same source on the same stat
+    pub fn apply(&mut self, modifier: StatModifier, rule: StackingRule) {
+        let same = |existing: &StatModifier| {
+            existing.source == modifier.source && existing.stat == modifier.stat
+        };
+
+        match rule {
+            StackingRule::Stack { max } => {
+                let count = self.modifiers.iter().filter(|m| same(m)).count();
+                if count >= max.max(1) {
+                    // Drop the one that would expire first, permanent ones last
+                    let oldest = self
+                        .modifiers
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, m)| same(m))
+                        .min_by(|(_, a), (_, b)| {
+                            let left = |m: &StatModifier| m.remaining.unwrap_or(f32::INFINITY);
+                            left(a).total_cmp(&left(b))
+                        })
+                        .map(|(i, _)| i);
+                    if let Some(index) = oldest {
+                        self.modifiers.remove(index);
+                    }
+                }
+                self.modifiers.push(modifier);
+            }
+            StackingRule::Refresh => {
+                self.modifiers.retain(|m| !same(m));
+                self.modifiers.push(modifier);
+            }
+            StackingRule::Extend => match self.modifiers.iter_mut().find(|m| same(m)) {
+                Some(existing) => {
+                    existing.remaining = match (existing.remaining, modifier.remaining) {
+                        (Some(left), Some(extra)) => Some(left + extra),
+                        _ => None,
+                    };
+                }
+                None => self.modifiers.push(modifier),
+            },
+            StackingRule::Ignore => {
+                if !self.modifiers.iter().any(same) {
+                    self.modifiers.push(modifier);
+                }
+            }
+        }
+    }
+
+    /// Remove every modifier from `source`
+    pub fn remove_source(&mut self, source: ModifierSource) {
+        self.modifiers.retain(|modifier| modifier.source != source);
+    }
+
+    /// Count down timed modifiers and drop the expired ones.
+    /// Returns the number that expired.
+    pub fn tick(&mut self, delta: f32) -> usize {
+        for modifier in self.modifiers.iter_mut() {
+            if let Some(remaining) = modifier.remaining.as_mut() {
+                *remaining -= delta;
+            }
+        }
+        let before = self.modifiers.len();
+        self.modifiers
+            .retain(|modifier| modifier.remaining.is_none_or(|left| left > 0.0));
+        before - self.modifiers.len()
+    }
+}
+
+/// Expires modifiers on the fixed timestep and writes friction and mass to the
+/// physics components. Force is read by `apply_movement_intent`.
+pub fn update_stat_modifiers(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(&mut StatModifiers, Option<&mut Friction>, Option<&mut Mass>)>,
+) {
+    let delta = fixed_delta(&fixed_time);
+
+    for (mut modifiers, friction, mass) in query.iter_mut() {
+        modifiers.tick(delta);
+        if let Some(mut friction) = friction {
+            friction.0 = modifiers.value(Stat::Friction);
+        }
+        if let Some(mut mass) = mass {
+            mass.0 = modifiers.value(Stat::Mass);
+        }
+    }
+}
+
+/// Bundle stat modifiers for easy registration
+pub struct ModifierSystems;
+
+impl ModifierSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.add_systems(
+            FixedUpdate,
+            update_stat_modifiers
+                .before(apply_movement_intent)
+                .in_set(PhysicsStep::Simulate),
+        )
+    }
+}
@@ -0,0 +1,229 @@
+use crate::components::{LinearVelocity, MovementIntent, Player};
+use crate::input::{uses_mouse, ControlledBy, BASE_INPUT_FORCE};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+/// Control scheme used to drive the player cube
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ControlMode {
+    /// WASD / arrow keys, left mouse button boosts
+    #[default]
+    Keyboard,
+    /// Hold the left mouse button to accelerate toward the cursor
+    MouseSteer,
+    /// Click to set a destination, the cube slows down as it arrives
+    ClickToMove,
+}
+
+impl ControlMode {
+    /// Next mode in the cycle Keyboard -> MouseSteer -> ClickToMove -> Keyboard
+    pub fn next(self) -> Self {
+        match self {
+            ControlMode::Keyboard => ControlMode::MouseSteer,
+            ControlMode::MouseSteer => ControlMode::ClickToMove,
+            ControlMode::ClickToMove => ControlMode::Keyboard,
+        }
+    }
+
+    pub fn uses_mouse(self) -> bool {
+        self != ControlMode::Keyboard
+    }
+}
+
+/// Tuning values for the mouse driven control modes
+#[derive(Resource, Debug, Clone)]
+pub struct MouseSteeringSettings {
+    /// Acceleration used to scale the arrive steering, matches `BASE_INPUT_FORCE`
+    pub max_force: f32,
+    /// Top speed the arrive behavior aims for outside the slowing radius
+    pub max_speed: f32,
+    /// Distance from the destination at which the cube starts slowing down
+    pub slowing_radius: f32,
+    /// Distance at which the destination counts as reached
+    pub arrival_radius: f32,
+    /// Hold-to-steer ignores the cursor when it is closer than this
+    pub dead_zone: f32,
+}
+
+impl Default for MouseSteeringSettings {
+    fn default() -> Self {
+        Self {
+            max_force: BASE_INPUT_FORCE,
+            max_speed: 800.0,
+            slowing_radius: 200.0,
+            arrival_radius: 5.0,
+            dead_zone: 10.0,
+        }
+    }
+}
+
+/// Cursor position in world coordinates, `None` when the cursor is outside the window
+#[derive(Resource, Debug, Default)]
+pub struct CursorWorldPosition(pub Option<Vec2>);
+
+/// Destination set by a click in `ControlMode::ClickToMove`
+#[derive(Component, Debug)]
+pub struct MoveDestination(pub Vec2);
+
+/// Acceleration pointing straight at the target, zero inside the dead zone
+pub fn seek_acceleration(position: Vec2, target: Vec2, max_force: f32, dead_zone: f32) -> Vec2 {
+    let to_target = target - position;
+    if to_target.length() <= dead_zone {
+        return Vec2::ZERO;
+    }
+    to_target.normalize() * max_force
+}
+
+/// Acceleration that steers toward the target and brakes inside the slowing radius,
+/// so the cube comes to rest on the target instead of overshooting it
+pub fn arrive_acceleration(
+    position: Vec2,
+    velocity: Vec2,
+    target: Vec2,
+    settings: &MouseSteeringSettings,
+) -> Vec2 {
+    let to_target = target - position;
+    let distance = to_target.length();
+    if distance <= f32::EPSILON {
+        return (-velocity * settings.max_force).clamp_length_max(settings.max_force);
+    }
+
+    // Desired speed ramps down linearly once inside the slowing radius
+    let ramp = (distance / settings.slowing_radius).min(1.0);
+    let desired_velocity = to_target / distance * settings.max_speed * ramp;
+
+    // Steering is the velocity correction, scaled so small errors still respond quickly
+    let steering = (desired_velocity - velocity) * (settings.max_force / settings.max_speed);
+    steering.clamp_length_max(settings.max_force)
+}
+
+/// Converts the cursor's screen position into world space through the 2D camera
+pub fn update_cursor_world_position(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mut cursor: ResMut<CursorWorldPosition>,
+) {
+    cursor.0 = None;
+
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let Some(screen_position) = window.cursor_position() else {
+        return;
+    };
+
+    for (camera, camera_transform) in cameras.iter() {
+        if !camera.is_active {
+            continue;
+        }
+        if let Some(world_position) = camera.viewport_to_world_2d(camera_transform, screen_position)
+        {
+            cursor.0 = Some(world_position);
+            return;
+        }
+    }
+}
+
+/// Cycles through the control modes with the M key
+pub fn cycle_control_mode(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut mode: ResMut<ControlMode>,
+    destinations: Query<Entity, With<MoveDestination>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::M) {
+        return;
+    }
+
+    *mode = mode.next();
+    info!("Control mode: {:?}", *mode);
+
+    // A destination left over from click-to-move must not keep pulling the cube
+    for entity in destinations.iter() {
+        commands.entity(entity).remove::<MoveDestination>();
+    }
+}
+
+/// Players the mouse may steer, with their current destination and binding
+pub type MouseSteeredQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static LinearVelocity,
+        &'static mut MovementIntent,
+        Option<&'static MoveDestination>,
+        Option<&'static ControlledBy>,
+    ),
+    With<Player>,
+>;
+
+/// Mouse steering system, runs after `handle_input` and overrides the keyboard
+/// intent while a mouse mode is actively steering. Only players that own
+/// the mouse (unbound or keyboard scheme A) are steered.
+pub fn mouse_control(
+    mut commands: Commands,
+    mode: Res<ControlMode>,
+    settings: Res<MouseSteeringSettings>,
+    cursor: Res<CursorWorldPosition>,
+    mouse_input: Res<Input<MouseButton>>,
+    mut query: MouseSteeredQuery,
+) {
+    match *mode {
+        ControlMode::Keyboard => {}
+        ControlMode::MouseSteer => {
+            let Some(cursor_position) = cursor.0 else {
+                return;
+            };
+            if !mouse_input.pressed(MouseButton::Left) {
+                return;
+            }
+
+            for (_, transform, _, mut intent, _, controlled_by) in query.iter_mut() {
+                if !uses_mouse(controlled_by) {
+                    continue;
+                }
+                intent.direction = seek_acceleration(
+                    transform.translation.truncate(),
+                    cursor_position,
+                    1.0,
+                    settings.dead_zone,
+                );
+            }
+        }
+        ControlMode::ClickToMove => {
+            let clicked = mouse_input.just_pressed(MouseButton::Left);
+
+            for (entity, transform, velocity, mut intent, destination, controlled_by) in
+                query.iter_mut()
+            {
+                if !uses_mouse(controlled_by) {
+                    continue;
+                }
+                let destination = match (clicked, cursor.0, destination) {
+                    (true, Some(cursor_position), _) => {
+                        commands
+                            .entity(entity)
+                            .insert(MoveDestination(cursor_position));
+                        cursor_position
+                    }
+                    (_, _, Some(destination)) => destination.0,
+                    _ => continue,
+                };
+
+                let position = transform.translation.truncate();
+                if position.distance(destination) <= settings.arrival_radius
+                    && velocity.0.length() <= settings.arrival_radius
+                {
+                    commands.entity(entity).remove::<MoveDestination>();
+                    continue;
+                }
+
+                intent.direction =
+                    arrive_acceleration(position, velocity.0, destination, &settings)
+                        / settings.max_force;
+            }
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use crate::collectibles::{detect_pickups, PickupEvent};
+use crate::components::{LinearVelocity, MovementIntent};
+use crate::game_state::GameplayReset;
+use crate::rng::GameRng;
+use crate::stamina::{is_boosting, Stamina};
+use crate::systems::{fixed_delta, obstacle_collision, PhysicsStep, WallImpact};
+use crate::visuals::VisualStyle;
+use bevy::prelude::*;
+
+/// Particle systems in `FixedUpdate`. They are cosmetic, so netcode can keep
+/// them out of the ticks it replays.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParticleSet;
+
+/// Look and motion of the particles an emitter or burst produces
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParticleEffect {
+    /// Seconds a particle lives, picked uniformly from this range
+    pub lifetime: (f32, f32),
+    /// Launch speed range
+    pub speed: (f32, f32),
+    /// Half angle in radians of the cone particles are launched in
+    pub spread: f32,
+    /// Fraction of the source velocity particles keep
+    pub inherit_velocity: f32,
+    /// Fraction of velocity lost per second
+    pub drag: f32,
+    /// Color and size at birth and at death, blended linearly over the lifetime
+    pub start: VisualStyle,
+    pub end: VisualStyle,
+}
+
+impl ParticleEffect {
+    /// Short lived sparks left behind a boosting player
+    pub fn boost_trail() -> Self {
+        Self {
+            lifetime: (0.25, 0.45),
+            speed: (60.0, 120.0),
+            spread: 0.5,
+            inherit_velocity: 0.2,
+            drag: 0.9,
+            start: VisualStyle::new(Color::rgba(0.9, 0.25, 0.75, 0.9), 10.0),
+            end: VisualStyle::new(Color::rgba(0.4, 0.1, 0.9, 0.0), 3.0),
+        }
+    }
+
+    /// Debris thrown off a wall along its normal
+    pub fn impact() -> Self {
+        Self {
+            lifetime: (0.2, 0.5),
+            speed: (150.0, 400.0),
+            spread: 1.2,
+            inherit_velocity: 0.0,
+            drag: 0.95,
+            start: VisualStyle::new(Color::rgba(1.0, 0.9, 0.6, 1.0), 6.0),
+            end: VisualStyle::new(Color::rgba(1.0, 0.4, 0.1, 0.0), 2.0),
+        }
+    }
+
+    /// Ring of sparkles where a collectible was picked up
+    pub fn pickup() -> Self {
+        Self {
+            lifetime: (0.3, 0.6),
+            speed: (80.0, 200.0),
+            spread: std::f32::consts::PI,
+            inherit_velocity: 0.0,
+            drag: 0.9,
+            start: VisualStyle::new(Color::rgba(1.0, 0.85, 0.2, 1.0), 8.0),
+            end: VisualStyle::new(Color::rgba(1.0, 1.0, 0.8, 0.0), 2.0),
+        }
+    }
+}
+
+/// One simulated particle. The sprite is only a view of this state,
+/// so the simulation runs the same without a renderer.
+#[derive(Component, Debug, Clone)]
+pub struct Particle {
+    pub velocity: Vec2,
+    pub age: f32,
+    pub lifetime: f32,
+    pub drag: f32,
+    pub start: VisualStyle,
+    pub end: VisualStyle,
+}
+
+impl Particle {
+    /// Fraction of the lifetime used up, 0 at birth and 1 at death
+    pub fn progress(&self) -> f32 {
+        (self.age / self.lifetime.max(f32::EPSILON)).min(1.0)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.age >= self.lifetime
+    }
+
+    /// Color and size for the current age
+    pub fn style(&self) -> VisualStyle {
+        self.start.lerp(self.end, self.progress())
+    }
+
+    /// Age the particle and return how far it moved
+    pub fn advance(&mut self, delta: f32) -> Vec2 {
+        self.age += delta;
+        self.velocity *= (1.0 - self.drag).powf(delta);
+        self.velocity * delta
+    }
+}
+
+/// Emits particles continuously from the entity it is attached to
+#[derive(Component, Debug, Clone)]
+pub struct ParticleEmitter {
+    pub effect: ParticleEffect,
+    /// Particles per second while enabled
+    pub rate: f32,
+    pub enabled: bool,
+    /// Fractional particles carried over between ticks
+    accumulator: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(effect: ParticleEffect, rate: f32) -> Self {
+        Self {
+            effect,
+            rate,
+            enabled: true,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Number of particles due this tick. Disabled emitters drop any carry over.
+    pub fn due(&mut self, delta: f32) -> u32 {
+        if !self.enabled {
+            self.accumulator = 0.0;
+            return 0;
+        }
+        self.accumulator += self.rate * delta;
+        let count = self.accumulator.floor();
+        self.accumulator -= count;
+        count as u32
+    }
+}
+
+/// Turns the entity's `ParticleEmitter` on while the entity is boosting
+#[derive(Component, Debug, Default)]
+pub struct BoostTrail;
+
+/// One-off burst of particles at a point
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ParticleBurst {
+    pub position: Vec2,
+    /// Center of the launch cone, any direction when zero
+    pub direction: Vec2,
+    pub count: u32,
+    pub effect: ParticleEffect,
+}
+
+/// Global particle limits and how gameplay events map to bursts
+#[derive(Resource, Debug, Clone)]
+pub struct ParticleSettings {
+    /// Live particles never exceed this, new ones are dropped instead
+    pub max_particles: usize,
+    /// Wall hits slower than this make no debris
+    pub impact_min_speed: f32,
+    /// Debris particles per unit of impact speed
+    pub impact_particles_per_speed: f32,
+    pub max_impact_particles: u32,
+    pub pickup_particles: u32,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        Self {
+            max_particles: 500,
+            impact_min_speed: 150.0,
+            impact_particles_per_speed: 0.02,
+            max_impact_particles: 24,
+            pickup_particles: 16,
+        }
+    }
+}
+
+impl ParticleSettings {
+    pub fn impact_particles(&self, speed: f32) -> u32 {
+        if speed < self.impact_min_speed {
+            return 0;
+        }
+        ((speed * self.impact_particles_per_speed).ceil() as u32).min(self.max_impact_particles)
+    }
+}
+
+/// Roll a new particle for `effect`, launched around `direction` on top of `source_velocity`
+pub fn roll_particle(
+    rng: &mut GameRng,
+    effect: &ParticleEffect,
+    direction: Vec2,
+    source_velocity: Vec2,
+) -> Particle {
+    let angle = match direction.try_normalize() {
+        Some(direction) => {
+            direction.y.atan2(direction.x) + rng.0.range_f32(-effect.spread, effect.spread)
+        }
+        None => rng.0.range_f32(-std::f32::consts::PI, std::f32::consts::PI),
+    };
+    let speed = rng.0.range_f32(effect.speed.0, effect.speed.1);
+
+    Particle {
+        velocity: Vec2::from_angle(angle) * speed + source_velocity * effect.inherit_velocity,
+        age: 0.0,
+        lifetime: rng.0.range_f32(effect.lifetime.0, effect.lifetime.1),
+        drag: effect.drag,
+        start: effect.start,
+        end: effect.end,
+    }
+}
+
+/// Spawn a particle sprite at `position`
+pub fn spawn_particle(commands: &mut Commands, position: Vec2, particle: Particle) -> Entity {
+    let style = particle.style();
+    commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: style.color,
+                    custom_size: Some(Vec2::splat(style.scale)),
+                    ..default()
+                },
+                // Slightly in front of the arena so trails draw over the floor
+                transform: Transform::from_translation(position.extend(5.0)),
+                ..default()
+            },
+            particle,
+        ))
+        .id()
+}
+
+/// Boost trails follow the boost state of their entity
+pub fn update_boost_trails(
+    mut trails: Query<(&MovementIntent, Option<&Stamina>, &mut ParticleEmitter), With<BoostTrail>>,
+) {
+    for (intent, stamina, mut emitter) in trails.iter_mut() {
+        emitter.enabled = is_boosting(intent, stamina);
+    }
+}
+
+/// Fast wall hits throw debris off the wall
+pub fn impact_bursts(
+    settings: Res<ParticleSettings>,
+    mut impacts: EventReader<WallImpact>,
+    mut bursts: EventWriter<ParticleBurst>,
+) {
+    for impact in impacts.iter() {
+        let count = settings.impact_particles(impact.speed);
+        if count > 0 {
+            bursts.send(ParticleBurst {
+                position: impact.position,
+                direction: impact.normal,
+                count,
+                effect: ParticleEffect::impact(),
+            });
+        }
+    }
+}
+
+pub fn pickup_bursts(
+    settings: Res<ParticleSettings>,
+    mut pickups: EventReader<PickupEvent>,
+    mut bursts: EventWriter<ParticleBurst>,
+) {
+    for pickup in pickups.iter() {
+        bursts.send(ParticleBurst {
+            position: pickup.position,
+            direction: Vec2::ZERO,
+            count: settings.pickup_particles,
+            effect: ParticleEffect::pickup(),
+        });
+    }
+}
+
+/// Spawns particles for emitters and bursts until the budget is used up.
+/// Emitters spray away from the direction their entity is moving.
+/// Particles roll their own generator, so cosmetic effects never shift the
+/// gameplay random sequence that rollback and replays depend on.
+pub fn emit_particles(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    settings: Res<ParticleSettings>,
+    mut rng: Local<GameRng>,
+    mut bursts: EventReader<ParticleBurst>,
+    mut emitters: Query<(&Transform, Option<&LinearVelocity>, &mut ParticleEmitter)>,
+    particles: Query<(), With<Particle>>,
+) {
+    let delta = fixed_delta(&fixed_time);
+    let mut budget = settings
+        .max_particles
+        .saturating_sub(particles.iter().count());
+
+    for (transform, velocity, mut emitter) in emitters.iter_mut() {
+        let due = emitter.due(delta) as usize;
+        let velocity = velocity.map_or(Vec2::ZERO, |v| v.0);
+        let position = transform.translation.truncate();
+        for _ in 0..due.min(budget) {
+            let particle = roll_particle(&mut rng, &emitter.effect, -velocity, velocity);
+            spawn_particle(&mut commands, position, particle);
+        }
+        budget = budget.saturating_sub(due);
+    }
+
+    for burst in bursts.iter() {
+        let count = (burst.count as usize).min(budget);
+        for _ in 0..count {
+            let particle = roll_particle(&mut rng, &burst.effect, burst.direction, Vec2::ZERO);
+            spawn_particle(&mut commands, burst.position, particle);
+        }
+        budget -= count;
+    }
+}
+
+/// Ages, moves and fades every particle, despawning the expired ones
+pub fn simulate_particles(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, Option<&mut Sprite>)>,
+) {
+    let delta = fixed_delta(&fixed_time);
+
+    for (entity, mut particle, mut transform, sprite) in particles.iter_mut() {
+        let movement = particle.advance(delta);
+        if particle.is_expired() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        transform.translation += movement.extend(0.0);
+        if let Some(mut sprite) = sprite {
+            let style = particle.style();
+            sprite.color = style.color;
+            sprite.custom_size = Some(Vec2::splat(style.scale));
+        }
+    }
+}
+
+/// Particles are not part of the session, they only go when it is torn down
+pub fn clear_particles(
+    mut commands: Commands,
+    mut resets: EventReader<GameplayReset>,
+    particles: Query<Entity, With<Particle>>,
+) {
+    if resets.iter().count() == 0 {
+        return;
+    }
+    for entity in particles.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Bundle the particle simulation and its effect sources for easy registration
+pub struct ParticleSystems;
+
+impl ParticleSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.init_resource::<GameRng>()
+            .init_resource::<ParticleSettings>()
+            .add_event::<ParticleBurst>()
+            .add_event::<WallImpact>()
+            .add_event::<PickupEvent>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    simulate_particles,
+                    update_boost_trails,
+                    impact_bursts,
+                    pickup_bursts,
+                    emit_particles,
+                )
+                    .chain()
+                    .after(obstacle_collision)
+                    .after(detect_pickups)
+                    .in_set(PhysicsStep::Simulate)
+                    .in_set(ParticleSet),
+            )
+            .add_event::<GameplayReset>()
+            .add_systems(Last, clear_particles)
+    }
+}
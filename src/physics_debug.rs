@@ -0,0 +1,237 @@
+use crate::components::{Acceleration, LinearVelocity};
+use crate::systems::PhysicsStep;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+/// Frame-step debugger for the fixed timestep physics.
+/// While enabled the simulation is frozen and only advances by explicitly
+/// requested ticks, one `FixedUpdate` run per tick.
+#[derive(Resource, Debug, Default)]
+pub struct PhysicsStepper {
+    enabled: bool,
+    pending_steps: u32,
+    stepping: bool,
+    steps_taken: u64,
+}
+
+impl PhysicsStepper {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Freeze the simulation
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    /// Resume normal simulation, dropping any queued steps
+    pub fn disable(&mut self) {
+        self.enabled = false;
+        self.pending_steps = 0;
+    }
+
+    pub fn toggle(&mut self) {
+        if self.enabled {
+            self.disable();
+        } else {
+            self.enable();
+        }
+    }
+
+    /// Queue `ticks` fixed ticks to run, freezing the simulation if it was running
+    pub fn step(&mut self, ticks: u32) {
+        self.enabled = true;
+        self.pending_steps += ticks;
+    }
+
+    /// Ticks still queued
+    pub fn pending_steps(&self) -> u32 {
+        self.pending_steps
+    }
+
+    /// Ticks advanced through the debugger since startup
+    pub fn steps_taken(&self) -> u64 {
+        self.steps_taken
+    }
+
+    /// Whether the simulation runs on the current fixed tick
+    pub fn simulation_allowed(&self) -> bool {
+        !self.enabled || self.stepping
+    }
+}
+
+/// State of one physics body at one moment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BodyState {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub acceleration: Vec2,
+}
+
+/// Before and after states of every physics body for one debugger step
+#[derive(Debug, Clone)]
+pub struct StepRecord {
+    pub step: u64,
+    pub before: Vec<BodyState>,
+    pub after: Vec<BodyState>,
+}
+
+/// Most recent debugger steps, oldest first
+#[derive(Resource, Debug)]
+pub struct PhysicsStepLog {
+    pub records: VecDeque<StepRecord>,
+    pub capacity: usize,
+    pending_before: Vec<BodyState>,
+}
+
+impl Default for PhysicsStepLog {
+    fn default() -> Self {
+        Self {
+            records: VecDeque::new(),
+            capacity: 64,
+            pending_before: Vec::new(),
+        }
+    }
+}
+
+impl PhysicsStepLog {
+    pub fn last(&self) -> Option<&StepRecord> {
+        self.records.back()
+    }
+}
+
+/// Every entity moved by the physics integration
+type BodyQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static LinearVelocity,
+        &'static Acceleration,
+    ),
+>;
+
+fn capture_bodies(query: &BodyQuery) -> Vec<BodyState> {
+    let mut bodies: Vec<BodyState> = query
+        .iter()
+        .map(|(entity, transform, velocity, acceleration)| BodyState {
+            entity,
+            position: transform.translation.truncate(),
+            velocity: velocity.0,
+            acceleration: acceleration.0,
+        })
+        .collect();
+    bodies.sort_by_key(|body| body.entity);
+    bodies
+}
+
+/// Run condition for the simulation phase
+pub fn physics_should_run(stepper: Res<PhysicsStepper>) -> bool {
+    stepper.simulation_allowed()
+}
+
+/// F9 toggles the debugger, F10 steps one tick, Shift+F10 steps ten
+pub fn handle_stepper_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut stepper: ResMut<PhysicsStepper>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F9) {
+        stepper.toggle();
+        info!(
+            "Physics stepper {}",
+            if stepper.is_enabled() { "on" } else { "off" }
+        );
+    }
+
+    if keyboard_input.just_pressed(KeyCode::F10) {
+        let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        stepper.step(if shift { 10 } else { 1 });
+    }
+}
+
+/// Starts a queued step and records the bodies before the simulation runs
+pub fn begin_physics_step(
+    mut stepper: ResMut<PhysicsStepper>,
+    mut log: ResMut<PhysicsStepLog>,
+    query: BodyQuery,
+) {
+    if !stepper.enabled || stepper.pending_steps == 0 {
+        return;
+    }
+
+    stepper.stepping = true;
+    log.pending_before = capture_bodies(&query);
+}
+
+/// Finishes the running step, logging how every body changed
+pub fn end_physics_step(
+    mut stepper: ResMut<PhysicsStepper>,
+    mut log: ResMut<PhysicsStepLog>,
+    query: BodyQuery,
+) {
+    if !stepper.stepping {
+        return;
+    }
+
+    stepper.stepping = false;
+    stepper.pending_steps -= 1;
+    stepper.steps_taken += 1;
+
+    let record = StepRecord {
+        step: stepper.steps_taken,
+        before: std::mem::take(&mut log.pending_before),
+        after: capture_bodies(&query),
+    };
+
+    info!("=== Physics step {} ===", record.step);
+    for after in record.after.iter() {
+        match record
+            .before
+            .iter()
+            .find(|before| before.entity == after.entity)
+        {
+            Some(before) => info!(
+                "{:?}: pos {:?} -> {:?}, vel {:?} -> {:?}, accel {:?}",
+                after.entity,
+                before.position,
+                after.position,
+                before.velocity,
+                after.velocity,
+                before.acceleration,
+            ),
+            None => info!(
+                "{:?}: spawned at {:?}, vel {:?}",
+                after.entity, after.position, after.velocity
+            ),
+        }
+    }
+
+    while log.records.len() >= log.capacity {
+        log.records.pop_front();
+    }
+    log.records.push_back(record);
+}
+
+/// Bundle the physics frame-step debugger for easy registration
+pub struct PhysicsDebugSystems;
+
+impl PhysicsDebugSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.init_resource::<PhysicsStepper>()
+            .init_resource::<PhysicsStepLog>()
+            .configure_set(
+                FixedUpdate,
+                PhysicsStep::Simulate.run_if(physics_should_run),
+            )
+            .add_systems(Update, handle_stepper_input)
+            .add_systems(
+                FixedUpdate,
+                (
+                    begin_physics_step.in_set(PhysicsStep::Begin),
+                    end_physics_step.in_set(PhysicsStep::End),
+                ),
+            )
+    }
+}
@@ -0,0 +1,167 @@
+use crate::components::{CollisionRadius, Player};
+use crate::entity_relations::RelationCommandsExt;
+use crate::game_state::GameplayEntity;
+use crate::modifiers::{ModifierSource, StackingRule, Stat, StatModifier, StatModifiers};
+use crate::systems::{obstacle_collision, PhysicsStep};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Kinds of power-up pickups and the modifiers they grant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PowerUpKind {
+    /// More input force
+    Speed,
+    /// Much less friction, the player glides
+    LowFriction,
+    /// Heavier and stronger: accelerates a little slower but shrugs off knockback
+    HeavyMass,
+}
+
+impl PowerUpKind {
+    /// Modifiers granted on pickup, all from `ModifierSource::PowerUp(self)`
+    pub fn modifiers(self) -> Vec<StatModifier> {
+        let source = ModifierSource::PowerUp(self);
+        match self {
+            PowerUpKind::Speed => vec![StatModifier::mul(Stat::Force, 1.5, source).lasting(5.0)],
+            PowerUpKind::LowFriction => {
+                vec![StatModifier::mul(Stat::Friction, 0.2, source).lasting(6.0)]
+            }
+            PowerUpKind::HeavyMass => vec![
+                StatModifier::add(Stat::Mass, 2.0, source).lasting(8.0),
+                StatModifier::mul(Stat::Force, 2.0, source).lasting(8.0),
+            ],
+        }
+    }
+
+    /// Speed restarts its timer, low friction adds up its time and heavy mass
+    /// stacks twice
+    pub fn stacking(self) -> StackingRule {
+        match self {
+            PowerUpKind::Speed => StackingRule::Refresh,
+            PowerUpKind::LowFriction => StackingRule::Extend,
+            PowerUpKind::HeavyMass => StackingRule::Stack { max: 2 },
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            PowerUpKind::Speed => Color::rgb(0.2, 0.9, 0.9),
+            PowerUpKind::LowFriction => Color::rgb(0.6, 0.8, 1.0),
+            PowerUpKind::HeavyMass => Color::rgb(0.55, 0.35, 0.2),
+        }
+    }
+
+    /// Grant this power-up's modifiers
+    pub fn apply_to(self, modifiers: &mut StatModifiers) {
+        for modifier in self.modifiers() {
+            modifiers.apply(modifier, self.stacking());
+        }
+    }
+}
+
+/// Pickup that grants timed stat modifiers to the player touching it
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    pub radius: f32,
+}
+
+impl PowerUp {
+    pub fn new(kind: PowerUpKind) -> Self {
+        Self { kind, radius: 14.0 }
+    }
+}
+
+/// Fired when a player picks up a power-up
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PowerUpCollected {
+    pub collector: Entity,
+    pub kind: PowerUpKind,
+    pub position: Vec2,
+}
+
+/// Spawn a power-up diamond
+pub fn spawn_power_up(commands: &mut Commands, position: Vec2, power_up: PowerUp) -> Entity {
+    commands.spawn(power_up_bundle(position, power_up)).id()
+}
+
+/// Everything `spawn_power_up` gives a power-up
+pub fn power_up_bundle(position: Vec2, power_up: PowerUp) -> impl Bundle {
+    (
+        SpriteBundle {
+            sprite: Sprite {
+                color: power_up.kind.color(),
+                custom_size: Some(Vec2::splat(power_up.radius * 1.5)),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(0.0))
+                .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+            ..default()
+        },
+        power_up,
+        GameplayEntity,
+    )
+}
+
+/// Overlap test between players and power-ups, granting each power-up once
+pub fn detect_power_up_pickups(
+    mut commands: Commands,
+    mut players: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&CollisionRadius>,
+            &mut StatModifiers,
+        ),
+        With<Player>,
+    >,
+    power_ups: Query<(Entity, &Transform, &PowerUp)>,
+    mut collected_events: EventWriter<PowerUpCollected>,
+) {
+    let mut collected = Vec::new();
+
+    for (collector, player_transform, radius, mut modifiers) in players.iter_mut() {
+        let player_position = player_transform.translation.truncate();
+        let player_radius = radius.copied().unwrap_or_default().0;
+
+        for (entity, transform, power_up) in power_ups.iter() {
+            let position = transform.translation.truncate();
+            if collected.contains(&entity)
+                || player_position.distance(position) > player_radius + power_up.radius
+            {
+                continue;
+            }
+
+            collected.push(entity);
+            commands.entity(entity).despawn_related();
+            power_up.kind.apply_to(&mut modifiers);
+            collected_events.send(PowerUpCollected {
+                collector,
+                kind: power_up.kind,
+                position,
+            });
+        }
+    }
+}
+
+pub fn log_power_ups(mut collected_events: EventReader<PowerUpCollected>) {
+    for event in collected_events.iter() {
+        info!("{:?} picked up {:?}", event.collector, event.kind);
+    }
+}
+
+/// Bundle power-up pickups for easy registration
+pub struct PowerUpSystems;
+
+impl PowerUpSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.add_event::<PowerUpCollected>()
+            .add_systems(
+                FixedUpdate,
+                detect_power_up_pickups
+                    .after(obstacle_collision)
+                    .in_set(PhysicsStep::Simulate),
+            )
+            .add_systems(Update, log_power_ups)
+    }
+}
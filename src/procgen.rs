@@ -0,0 +1,431 @@
+use crate::enemy::SteeringBehavior;
+use crate::level::{
+    BoundsDefinition, EnemyDefinition, LevelDefinition, ObstacleDefinition, PickupDefinition,
+    PowerUpDefinition,
+};
+use crate::power_ups::PowerUpKind;
+use crate::rng::SeededRng;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Placement attempts per obstacle before the generator gives up on it
+const OBSTACLE_ATTEMPTS: usize = 16;
+
+const ENEMY_BEHAVIORS: [SteeringBehavior; 4] = [
+    SteeringBehavior::Seek,
+    SteeringBehavior::Arrive,
+    SteeringBehavior::Pursue,
+    SteeringBehavior::Wander,
+];
+
+const POWER_UP_KINDS: [PowerUpKind; 3] = [
+    PowerUpKind::Speed,
+    PowerUpKind::LowFriction,
+    PowerUpKind::HeavyMass,
+];
+
+/// Rules for a generated arena. The layout lives on a grid of square cells:
+/// obstacles cover whole cells and everything else sits on a cell center.
+/// Distances are counted in cells, the larger of the x and y steps.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArenaSettings {
+    pub seed: u64,
+    /// Width and height of the playable area, centered on the origin
+    pub size: Vec2,
+    /// Side of a grid cell. Gaps between obstacles are at least one cell,
+    /// so it should be wider than a player.
+    pub cell_size: f32,
+    pub friction: f32,
+    pub bounce_factor: f32,
+    pub players: usize,
+    /// Obstacles placed at most, fewer when they would cut the arena apart
+    pub obstacles: usize,
+    /// Longest obstacle side in cells
+    pub max_obstacle_cells: u32,
+    /// Items placed at most, fewer when the spacing rules leave no room
+    pub pickups: usize,
+    pub power_ups: usize,
+    pub enemies: usize,
+    /// Least distance between spawn points
+    pub spawn_spacing: u32,
+    /// Cells around a spawn point kept clear of obstacles
+    pub spawn_clearance: u32,
+    /// Least distance from an enemy to any spawn point
+    pub enemy_distance: u32,
+    /// Least distance between any two items
+    pub item_spacing: u32,
+}
+
+impl Default for ArenaSettings {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            size: Vec2::new(1200.0, 600.0),
+            cell_size: 60.0,
+            friction: 0.1,
+            bounce_factor: 0.8,
+            players: 1,
+            obstacles: 8,
+            max_obstacle_cells: 4,
+            pickups: 8,
+            power_ups: 3,
+            enemies: 2,
+            spawn_spacing: 8,
+            spawn_clearance: 1,
+            enemy_distance: 5,
+            item_spacing: 2,
+        }
+    }
+}
+
+/// Why arena settings cannot produce an arena
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArenaError {
+    /// The offending setting, such as `cell_size`
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for ArenaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "arena settings: `{}` {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for ArenaError {}
+
+/// Grid cell, column and row from the bottom left
+pub type Cell = (u32, u32);
+
+/// Walkable cells of an arena. A cell is blocked when an obstacle overlaps it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArenaGrid {
+    pub min: Vec2,
+    pub cell_size: f32,
+    pub columns: u32,
+    pub rows: u32,
+    blocked: Vec<bool>,
+}
+
+impl ArenaGrid {
+    /// Empty grid covering `min..max`, cells that do not fit whole are dropped
+    pub fn new(min: Vec2, max: Vec2, cell_size: f32) -> Self {
+        let cells = ((max - min) / cell_size).floor();
+        let (columns, rows) = (cells.x.max(0.0) as u32, cells.y.max(0.0) as u32);
+        Self {
+            min,
+            cell_size,
+            columns,
+            rows,
+            blocked: vec![false; (columns * rows) as usize],
+        }
+    }
+
+    /// Grid of a level with its obstacles blocked
+    pub fn from_level(level: &LevelDefinition, cell_size: f32) -> Self {
+        let mut grid = Self::new(level.bounds.min, level.bounds.max, cell_size);
+        for obstacle in level.obstacles.iter() {
+            grid.block_area(obstacle.position, obstacle.size);
+        }
+        grid
+    }
+
+    fn index(&self, (column, row): Cell) -> usize {
+        (row * self.columns + column) as usize
+    }
+
+    pub fn center(&self, (column, row): Cell) -> Vec2 {
+        self.min + Vec2::new(column as f32 + 0.5, row as f32 + 0.5) * self.cell_size
+    }
+
+    /// Cell containing `position`, if it is on the grid
+    pub fn cell_at(&self, position: Vec2) -> Option<Cell> {
+        let cell = ((position - self.min) / self.cell_size).floor();
+        (cell.x >= 0.0
+            && cell.y >= 0.0
+            && cell.x < self.columns as f32
+            && cell.y < self.rows as f32)
+            .then_some((cell.x as u32, cell.y as u32))
+    }
+
+    pub fn is_blocked(&self, cell: Cell) -> bool {
+        self.blocked[self.index(cell)]
+    }
+
+    /// Block every cell the box centered at `position` overlaps
+    pub fn block_area(&mut self, position: Vec2, size: Vec2) {
+        // Touching a cell's edge does not block it
+        let slack = self.cell_size * 1e-3;
+        let low = (position - size / 2.0 - self.min + slack) / self.cell_size;
+        let high = (position + size / 2.0 - self.min - slack) / self.cell_size;
+        let first = low.floor().max(Vec2::ZERO);
+        let last = high
+            .floor()
+            .min(Vec2::new(self.columns as f32 - 1.0, self.rows as f32 - 1.0));
+        if first.cmpgt(last).any() {
+            return;
+        }
+        for row in first.y as u32..=last.y as u32 {
+            for column in first.x as u32..=last.x as u32 {
+                let index = self.index((column, row));
+                self.blocked[index] = true;
+            }
+        }
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = Cell> + '_ {
+        (0..self.rows).flat_map(move |row| (0..self.columns).map(move |column| (column, row)))
+    }
+
+    pub fn free_cells(&self) -> impl Iterator<Item = Cell> + '_ {
+        self.cells().filter(|cell| !self.is_blocked(*cell))
+    }
+
+    /// Which cells can be walked to from `start`, moving between free
+    /// neighbours that share a side
+    pub fn reachable_from(&self, start: Cell) -> Vec<bool> {
+        let mut reached = vec![false; self.blocked.len()];
+        if self.is_blocked(start) {
+            return reached;
+        }
+        reached[self.index(start)] = true;
+        let mut queue = VecDeque::from([start]);
+        while let Some((column, row)) = queue.pop_front() {
+            let neighbours = [
+                (column.wrapping_sub(1), row),
+                (column + 1, row),
+                (column, row.wrapping_sub(1)),
+                (column, row + 1),
+            ];
+            for next in neighbours {
+                if next.0 >= self.columns || next.1 >= self.rows || self.is_blocked(next) {
+                    continue;
+                }
+                let index = self.index(next);
+                if !reached[index] {
+                    reached[index] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Whether every free cell can be reached from every other
+    pub fn is_connected(&self) -> bool {
+        let Some(start) = self.free_cells().next() else {
+            return true;
+        };
+        let reached = self.reachable_from(start);
+        self.free_cells().all(|cell| reached[self.index(cell)])
+    }
+
+    /// Whether a body at `from` can get to `to` around the obstacles
+    pub fn connects(&self, from: Vec2, to: Vec2) -> bool {
+        match (self.cell_at(from), self.cell_at(to)) {
+            (Some(from), Some(to)) => self.reachable_from(from)[self.index(to)],
+            _ => false,
+        }
+    }
+}
+
+/// Steps between two cells, diagonal steps count as one
+fn distance(a: Cell, b: Cell) -> u32 {
+    a.0.abs_diff(b.0).max(a.1.abs_diff(b.1))
+}
+
+impl ArenaSettings {
+    /// Arena settings with the defaults and `seed`
+    pub fn with_seed(seed: u64) -> Self {
+        Self { seed, ..default() }
+    }
+
+    /// Path generated levels report as their file, so replays can name them
+    pub fn level_path(&self) -> PathBuf {
+        PathBuf::from(format!("arena-{}.ron", self.seed))
+    }
+
+    fn validate(&self) -> Result<(), ArenaError> {
+        let invalid = |field: &str, message: &str| ArenaError {
+            field: field.to_string(),
+            message: message.to_string(),
+        };
+        if !(self.cell_size.is_finite() && self.cell_size > 0.0) {
+            return Err(invalid("cell_size", "must be positive"));
+        }
+        if !self.size.is_finite() || self.size.cmplt(Vec2::splat(self.cell_size)).any() {
+            return Err(invalid("size", "must hold at least one cell"));
+        }
+        if !(0.0..1.0).contains(&self.friction) {
+            return Err(invalid("friction", "must be in 0.0..1.0"));
+        }
+        if !(0.0..=1.0).contains(&self.bounce_factor) {
+            return Err(invalid("bounce_factor", "must be in 0.0..=1.0"));
+        }
+        if self.players == 0 {
+            return Err(invalid("players", "must be at least 1"));
+        }
+        if self.max_obstacle_cells == 0 {
+            return Err(invalid("max_obstacle_cells", "must be at least 1"));
+        }
+        Ok(())
+    }
+
+    /// Build the arena. The same settings give the same level on every
+    /// platform: choices come from the integer `SeededRng`, and positions
+    /// only add and multiply whole cell counts.
+    pub fn generate(&self) -> Result<LevelDefinition, ArenaError> {
+        self.validate()?;
+        let mut rng = SeededRng::new(self.seed);
+        let half = self.size / 2.0;
+        let mut grid = ArenaGrid::new(-half, half, self.cell_size);
+
+        // Spawn points first, as far apart as the spacing asks, or else as
+        // far from the others as the grid allows
+        let mut spawns: Vec<Cell> = Vec::new();
+        while spawns.len() < self.players {
+            let nearest = |cell: Cell| spawns.iter().map(|s| distance(*s, cell)).min();
+            let mut candidates: Vec<Cell> = grid
+                .cells()
+                .filter(|cell| nearest(*cell).is_none_or(|d| d >= self.spawn_spacing))
+                .collect();
+            if candidates.is_empty() {
+                let farthest = grid.cells().filter_map(nearest).max().unwrap_or(0);
+                candidates = grid
+                    .cells()
+                    .filter(|cell| nearest(*cell) == Some(farthest) && farthest > 0)
+                    .collect();
+            }
+            let Some(cell) = pick(&mut rng, &candidates).copied() else {
+                return Err(ArenaError {
+                    field: "players".into(),
+                    message: "do not fit on the grid".into(),
+                });
+            };
+            spawns.push(cell);
+        }
+
+        // Obstacles that keep the spawn points clear and all free cells
+        // connected, so everything placed later can be reached
+        let mut obstacles = Vec::new();
+        for _ in 0..self.obstacles * OBSTACLE_ATTEMPTS {
+            if obstacles.len() == self.obstacles {
+                break;
+            }
+            let cells = UVec2::new(
+                1 + rng.below(self.max_obstacle_cells.min(grid.columns)),
+                1 + rng.below(self.max_obstacle_cells.min(grid.rows)),
+            );
+            let corner = (
+                rng.below(grid.columns - cells.x + 1),
+                rng.below(grid.rows - cells.y + 1),
+            );
+            let covered: Vec<Cell> = (corner.1..corner.1 + cells.y)
+                .flat_map(|row| (corner.0..corner.0 + cells.x).map(move |column| (column, row)))
+                .collect();
+            let clear_of_spawns = covered.iter().all(|cell| {
+                spawns
+                    .iter()
+                    .all(|spawn| distance(*spawn, *cell) > self.spawn_clearance)
+            });
+            if !clear_of_spawns || covered.iter().any(|cell| grid.is_blocked(*cell)) {
+                continue;
+            }
+
+            let mut next = grid.clone();
+            for cell in covered.iter() {
+                let index = next.index(*cell);
+                next.blocked[index] = true;
+            }
+            if !next.is_connected() {
+                continue;
+            }
+            grid = next;
+            let size = cells.as_vec2() * self.cell_size;
+            obstacles.push(ObstacleDefinition {
+                position: grid.min
+                    + Vec2::new(corner.0 as f32, corner.1 as f32) * self.cell_size
+                    + size / 2.0,
+                size,
+            });
+        }
+
+        // Items on free cells, spaced from each other and the spawn points
+        let mut taken = spawns.clone();
+        let mut place = |rng: &mut SeededRng, from_spawns: u32| {
+            let candidates: Vec<Cell> = grid
+                .free_cells()
+                .filter(|cell| {
+                    spawns.iter().all(|s| distance(*s, *cell) >= from_spawns)
+                        && taken
+                            .iter()
+                            .all(|t| distance(*t, *cell) >= self.item_spacing)
+                })
+                .collect();
+            let cell = *pick(rng, &candidates)?;
+            taken.push(cell);
+            Some(grid.center(cell))
+        };
+        let item_distance = self.item_spacing.max(1);
+        let enemies: Vec<EnemyDefinition> = (0..self.enemies)
+            .map_while(|_| {
+                let position = place(&mut rng, self.enemy_distance.max(1))?;
+                Some(EnemyDefinition {
+                    position,
+                    behavior: *pick(&mut rng, &ENEMY_BEHAVIORS).unwrap(),
+                })
+            })
+            .collect();
+        let power_ups: Vec<PowerUpDefinition> = (0..self.power_ups)
+            .map_while(|_| {
+                let position = place(&mut rng, item_distance)?;
+                Some(PowerUpDefinition {
+                    position,
+                    kind: *pick(&mut rng, &POWER_UP_KINDS).unwrap(),
+                })
+            })
+            .collect();
+        let pickups: Vec<PickupDefinition> = (0..self.pickups)
+            .map_while(|_| {
+                Some(PickupDefinition {
+                    position: place(&mut rng, item_distance)?,
+                    value: None,
+                })
+            })
+            .collect();
+
+        let level = LevelDefinition {
+            name: format!("Arena {}", self.seed),
+            bounds: BoundsDefinition {
+                min: -half,
+                max: half,
+                friction: self.friction,
+                bounce_factor: self.bounce_factor,
+            },
+            spawn_points: spawns.iter().map(|cell| grid.center(*cell)).collect(),
+            obstacles,
+            pickups,
+            enemies,
+            power_ups,
+            race: None,
+        };
+        level
+            .validate(&self.level_path())
+            .map_err(|error| ArenaError {
+                field: "seed".into(),
+                message: error.to_string(),
+            })?;
+        Ok(level)
+    }
+}
+
+fn pick<'a, T>(rng: &mut SeededRng, items: &'a [T]) -> Option<&'a T> {
+    match items.len() {
+        0 => None,
+        len => items.get(rng.below(len as u32) as usize),
+    }
+}
@@ -0,0 +1,295 @@
+use crate::components::{CollisionRadius, FireIntent, LinearVelocity, MovementIntent, Obstacle};
+use crate::entity_relations::{Owner, RelationCommandsExt};
+use crate::game_state::{GameState, GameplayEntity};
+use crate::input::{read_source, uses_mouse, ControlledBy};
+use crate::mouse_control::{update_cursor_world_position, CursorWorldPosition};
+use crate::resources::WorldBounds;
+use crate::systems::{fixed_delta, obstacle_collision, obstacle_penetration, PhysicsStep};
+use bevy::prelude::*;
+
+/// Gun carried by an entity, fired through its `FireIntent`
+#[derive(Component, Debug, Clone)]
+pub struct Weapon {
+    /// Speed added along the aim direction on top of the shooter's velocity
+    pub muzzle_speed: f32,
+    /// Fraction of the shooter's velocity the projectile inherits
+    pub inherit_velocity: f32,
+    /// Seconds between two shots
+    pub cooldown: f32,
+    /// Seconds a projectile flies before it disappears
+    pub lifetime: f32,
+    pub projectile_radius: f32,
+    pub damage: f32,
+    /// Distance from the shooter's center where projectiles appear
+    pub muzzle_offset: f32,
+    /// Seconds until the weapon can fire again
+    pub cooldown_left: f32,
+    /// Last direction fired, used when there is nothing else to aim with
+    pub facing: Vec2,
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Self {
+            muzzle_speed: 900.0,
+            inherit_velocity: 1.0,
+            cooldown: 0.15,
+            lifetime: 1.2,
+            projectile_radius: 6.0,
+            damage: 10.0,
+            muzzle_offset: 32.0,
+            cooldown_left: 0.0,
+            facing: Vec2::X,
+        }
+    }
+}
+
+impl Weapon {
+    /// Counts down the cooldown and reports whether a held trigger fires this tick
+    pub fn tick(&mut self, firing: bool, delta: f32) -> bool {
+        self.cooldown_left = (self.cooldown_left - delta).max(0.0);
+        if !firing || self.cooldown_left > 0.0 {
+            return false;
+        }
+        self.cooldown_left = self.cooldown;
+        true
+    }
+
+    /// Launch velocity for a shot along `direction` from a shooter moving at `shooter_velocity`
+    pub fn launch_velocity(&self, direction: Vec2, shooter_velocity: Vec2) -> Vec2 {
+        direction * self.muzzle_speed + shooter_velocity * self.inherit_velocity
+    }
+}
+
+/// Shot in flight. Projectiles move themselves instead of going through the
+/// body physics, so they do not bounce, slow down or shake the camera.
+/// The shooter is linked through `Owner` until it is despawned.
+#[derive(Component, Debug, Clone)]
+pub struct Projectile {
+    pub velocity: Vec2,
+    /// Seconds left before the projectile expires
+    pub lifetime: f32,
+    pub radius: f32,
+    pub damage: f32,
+}
+
+/// Fired when a projectile hits something. `target` is `None` for walls and obstacles.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ProjectileHit {
+    pub projectile: Entity,
+    /// Shooter, from the projectile's `Owner`. `None` once the shooter is gone.
+    pub owner: Option<Entity>,
+    pub target: Option<Entity>,
+    pub position: Vec2,
+    pub damage: f32,
+}
+
+/// Whether a circle moving from `start` to `end` touches a circle at `center`
+pub fn swept_circle_hit(start: Vec2, end: Vec2, center: Vec2, radius: f32) -> bool {
+    let path = end - start;
+    let t = if path.length_squared() > f32::EPSILON {
+        ((center - start).dot(path) / path.length_squared()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (start + path * t).distance(center) <= radius
+}
+
+/// Direction a shot goes: toward the aim point, else the way the shooter is
+/// steering or moving, else the last direction fired
+pub fn aim_direction(
+    position: Vec2,
+    fire: &FireIntent,
+    intent: Option<&MovementIntent>,
+    velocity: Vec2,
+    facing: Vec2,
+) -> Vec2 {
+    fire.aim_at
+        .and_then(|target| (target - position).try_normalize())
+        .or_else(|| intent.and_then(|intent| intent.direction.try_normalize()))
+        .or_else(|| velocity.try_normalize())
+        .unwrap_or(facing)
+}
+
+/// Spawn a projectile sprite owned by `owner`, despawned with the session
+pub fn spawn_projectile(
+    commands: &mut Commands,
+    owner: Entity,
+    position: Vec2,
+    projectile: Projectile,
+) -> Entity {
+    commands
+        .spawn((projectile_bundle(position, projectile), Owner(owner)))
+        .id()
+}
+
+/// Everything `spawn_projectile` gives a projectile besides its owner
+pub fn projectile_bundle(position: Vec2, projectile: Projectile) -> impl Bundle {
+    (
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(1.0, 0.95, 0.6),
+                custom_size: Some(Vec2::splat(projectile.radius * 2.0)),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(1.0)),
+            ..default()
+        },
+        projectile,
+        GameplayEntity,
+    )
+}
+
+/// Records the fire button and, for mouse users, the cursor as the aim point
+pub fn handle_fire_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    cursor: Res<CursorWorldPosition>,
+    mut query: Query<(&mut FireIntent, Option<&ControlledBy>)>,
+) {
+    for (mut fire, controlled_by) in query.iter_mut() {
+        let source_input = read_source(
+            controlled_by,
+            &keyboard_input,
+            &gamepad_axes,
+            &gamepad_buttons,
+        );
+        let mouse = uses_mouse(controlled_by);
+
+        *fire = FireIntent {
+            firing: source_input.fire || (mouse && mouse_input.pressed(MouseButton::Right)),
+            aim_at: if mouse { cursor.0 } else { None },
+        };
+    }
+}
+
+/// Armed entities and what they aim with
+pub type ShooterQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static FireIntent,
+        Option<&'static MovementIntent>,
+        Option<&'static LinearVelocity>,
+        &'static mut Weapon,
+    ),
+>;
+
+/// Fires every weapon whose trigger is held and whose cooldown has run out
+pub fn fire_weapons(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    mut shooters: ShooterQuery,
+) {
+    let delta = fixed_delta(&fixed_time);
+
+    for (shooter, transform, fire, intent, velocity, mut weapon) in shooters.iter_mut() {
+        if !weapon.tick(fire.firing, delta) {
+            continue;
+        }
+
+        let position = transform.translation.truncate();
+        let velocity = velocity.map_or(Vec2::ZERO, |v| v.0);
+        let direction = aim_direction(position, fire, intent, velocity, weapon.facing);
+        weapon.facing = direction;
+
+        spawn_projectile(
+            &mut commands,
+            shooter,
+            position + direction * weapon.muzzle_offset,
+            Projectile {
+                velocity: weapon.launch_velocity(direction, velocity),
+                lifetime: weapon.lifetime,
+                radius: weapon.projectile_radius,
+                damage: weapon.damage,
+            },
+        );
+    }
+}
+
+/// Bodies a projectile can hit
+pub type ProjectileTargetQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static Transform, &'static CollisionRadius), Without<Projectile>>;
+
+/// Moves projectiles and resolves what they hit. A projectile stops at the
+/// first body along its path other than its owner, or at a wall, and expires
+/// at the end of its lifetime. Shots of a despawned shooter keep flying.
+pub fn update_projectiles(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    bounds: Res<WorldBounds>,
+    mut projectiles: Query<(Entity, &mut Transform, &mut Projectile, Option<&Owner>)>,
+    targets: ProjectileTargetQuery,
+    obstacles: Query<(&Transform, &Obstacle), Without<Projectile>>,
+    mut hits: EventWriter<ProjectileHit>,
+) {
+    let delta = fixed_delta(&fixed_time);
+
+    for (entity, mut transform, mut projectile, owner) in projectiles.iter_mut() {
+        let owner = owner.map(|owner| owner.0);
+        let start = transform.translation.truncate();
+        let end = start + projectile.velocity * delta;
+        transform.translation = end.extend(transform.translation.z);
+        projectile.lifetime -= delta;
+
+        let hit_body = targets
+            .iter()
+            .filter(|(target, ..)| Some(*target) != owner)
+            .filter(|(_, target_transform, radius)| {
+                let center = target_transform.translation.truncate();
+                swept_circle_hit(start, end, center, radius.0 + projectile.radius)
+            })
+            .min_by(|(_, a, _), (_, b, _)| {
+                let distance = |t: &Transform| t.translation.truncate().distance_squared(start);
+                distance(a).total_cmp(&distance(b))
+            })
+            .map(|(target, ..)| target);
+
+        let hit_wall = !bounds.contains(end)
+            || obstacles.iter().any(|(obstacle_transform, obstacle)| {
+                let center = obstacle_transform.translation.truncate();
+                obstacle_penetration(end, projectile.radius, center, obstacle.half_size).is_some()
+            });
+
+        if hit_body.is_some() || hit_wall {
+            hits.send(ProjectileHit {
+                projectile: entity,
+                owner,
+                target: hit_body,
+                position: end,
+                damage: projectile.damage,
+            });
+        }
+        if hit_body.is_some() || hit_wall || projectile.lifetime <= 0.0 {
+            commands.entity(entity).despawn_related();
+        }
+    }
+}
+
+/// Bundle shooting and projectile simulation for easy registration
+pub struct ProjectileSystems;
+
+impl ProjectileSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.init_resource::<CursorWorldPosition>()
+            .init_resource::<WorldBounds>()
+            .add_event::<ProjectileHit>()
+            .add_systems(
+                Update,
+                handle_fire_input
+                    .after(update_cursor_world_position)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (fire_weapons, update_projectiles)
+                    .chain()
+                    .after(obstacle_collision)
+                    .in_set(PhysicsStep::Simulate),
+            )
+    }
+}
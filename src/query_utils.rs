@@ -9,15 +9,17 @@ pub type PhysicsQuery<'w, 's> = Query<
     's,
     (
         &'static mut Transform,
-        &'static mut Player,
+        &'static mut LinearVelocity,
+        &'static mut Acceleration,
+        &'static Friction,
     ),
 >;
 
 /// Player-specific queries
 pub type PlayerQuery<'w, 's> =
-    Query<'w, 's, (&'static Transform, &'static Player)>;
+    Query<'w, 's, (&'static Transform, &'static LinearVelocity), With<Player>>;
 pub type PlayerMutQuery<'w, 's> =
-    Query<'w, 's, (&'static mut Transform, &'static mut Player)>;
+    Query<'w, 's, (&'static mut Transform, &'static mut LinearVelocity), With<Player>>;
 
 /// Read-only physics queries for analysis/display systems
 pub type PhysicsReadQuery<'w, 's> = Query<
@@ -25,7 +27,9 @@ pub type PhysicsReadQuery<'w, 's> = Query<
     's,
     (
         &'static Transform,
-        &'static Player,
+        &'static LinearVelocity,
+        &'static Acceleration,
+        &'static Friction,
     ),
 >;
 
@@ -35,7 +39,9 @@ pub type OptionalPhysicsQuery<'w, 's> = Query<
     's,
     (
         &'static Transform,
-        Option<&'static Player>,
+        Option<&'static LinearVelocity>,
+        Option<&'static Acceleration>,
+        Option<&'static Friction>,
     ),
 >;
 
@@ -58,15 +64,21 @@ pub fn query_with_or_filter(
 
 pub fn query_optional_components(query: OptionalPhysicsQuery) {
     println!("=== Optional Components Query ===");
-    for (transform, player_opt) in query.iter() {
-        match player_opt {
-            Some(player) => {
+    for (transform, velocity_opt, accel_opt, friction_opt) in query.iter() {
+        match (velocity_opt, accel_opt, friction_opt) {
+            (Some(velocity), Some(accel), Some(friction)) => {
                 println!(
-                    "Player entity at {:?}: vel={:?}, accel={:?}, friction={}",
-                    transform.translation, player.velocity, player.acceleration, player.friction
+                    "Full physics entity at {:?}: vel={:?}, accel={:?}, friction={}",
+                    transform.translation, velocity.0, accel.0, friction.0
                 );
             }
-            None => {
+            (Some(velocity), _, _) => {
+                println!(
+                    "Moving entity at {:?}: vel={:?}",
+                    transform.translation, velocity.0
+                );
+            }
+            _ => {
                 println!("Transform-only entity at {:?}", transform.translation);
             }
         }
@@ -77,24 +89,24 @@ pub fn query_optional_components(query: OptionalPhysicsQuery) {
 /// Demonstrating different iteration approaches for performance
 
 pub fn optimized_iteration_patterns(
-    mut query: Query<(&Transform, &mut Player)>,
+    mut query: Query<(&Transform, &mut LinearVelocity)>,
     _time: Res<Time>,
 ) {
     // Pattern 1: Using for_each for potential vectorization
     // This is the recommended approach in modern Bevy
-    query.iter_mut().for_each(|(_transform, mut player)| {
+    query.iter_mut().for_each(|(_transform, mut velocity)| {
         // Apply some physics calculation
-        player.velocity *= 0.99; // Simple damping
+        velocity.0 *= 0.99; // Simple damping
     });
 
     // Pattern 2: Traditional for loop (less optimal for simple operations)
-    // for (transform, mut player) in query.iter_mut() {
-    //     player.velocity *= 0.99;
+    // for (transform, mut velocity) in query.iter_mut() {
+    //     velocity.0 *= 0.99;
     // }
 
     // Pattern 3: Parallel iteration (when no conflicts exist)
-    // query.par_for_each_mut(32, |(transform, mut player)| {
-    //     player.velocity *= 0.99;
+    // query.par_for_each_mut(32, |(transform, mut velocity)| {
+    //     velocity.0 *= 0.99;
     // });
 }
 
@@ -117,7 +129,7 @@ pub fn analyze_archetype_fragmentation(
     all_entities: Query<Entity>,
     transform_entities: Query<Entity, With<Transform>>,
     player_entities: Query<Entity, With<Player>>,
-    physics_entities: Query<Entity, (With<Transform>, With<Player>)>,
+    physics_entities: Query<Entity, (With<Transform>, With<LinearVelocity>)>,
 ) {
     println!("=== Archetype Analysis ===");
     println!("Total entities: {}", all_entities.iter().count());
@@ -0,0 +1,338 @@
+use crate::components::{CollisionRadius, Player};
+use crate::game_state::{GameState, GameplayEntity};
+use crate::level::CurrentLevel;
+use crate::systems::{fixed_delta, obstacle_collision, PhysicsStep};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Where the best race times are kept between sessions
+pub const BEST_TIMES_PATH: &str = "saves/best_times.ron";
+
+/// One gate of a time-attack course. Gates must be passed in `index` order.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Checkpoint {
+    pub index: usize,
+    pub radius: f32,
+}
+
+/// A player's run through the course. Times are counted in fixed ticks so a
+/// run is timed the same regardless of frame rate.
+#[derive(Component, Debug, Clone, Default)]
+pub struct RaceProgress {
+    /// Checkpoint the player has to reach next
+    pub next: usize,
+    pub lap: u32,
+    pub laps: u32,
+    pub checkpoint_count: usize,
+    /// Ticks since the start
+    pub ticks: u32,
+    /// Ticks since the start at every checkpoint passed, across all laps
+    pub splits: Vec<u32>,
+    pub finished: bool,
+}
+
+impl RaceProgress {
+    pub fn new(checkpoint_count: usize, laps: u32) -> Self {
+        Self {
+            laps: laps.max(1),
+            checkpoint_count,
+            ..default()
+        }
+    }
+
+    /// Record passing the next checkpoint and return its split
+    pub fn pass_checkpoint(&mut self) -> u32 {
+        self.splits.push(self.ticks);
+        self.next += 1;
+        if self.next >= self.checkpoint_count {
+            self.next = 0;
+            self.lap += 1;
+            self.finished = self.lap >= self.laps;
+        }
+        self.ticks
+    }
+}
+
+/// Fastest complete run per level, keyed by level name
+#[derive(Resource, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BestTimes {
+    pub levels: HashMap<String, Vec<u32>>,
+}
+
+impl BestTimes {
+    pub fn splits(&self, level: &str) -> Option<&[u32]> {
+        self.levels.get(level).map(Vec::as_slice)
+    }
+
+    /// Ticks ahead (negative) or behind (positive) the best run at split `index`
+    pub fn delta(&self, level: &str, index: usize, ticks: u32) -> Option<i64> {
+        let best = *self.splits(level)?.get(index)?;
+        Some(ticks as i64 - best as i64)
+    }
+
+    /// Store `splits` if the run beats the best one. Returns whether it did.
+    pub fn submit(&mut self, level: &str, splits: &[u32]) -> bool {
+        let Some(&total) = splits.last() else {
+            return false;
+        };
+        let is_best = match self.splits(level) {
+            Some(best) => best.len() != splits.len() || best.last().is_some_and(|&b| total < b),
+            None => true,
+        };
+        if is_best {
+            self.levels.insert(level.to_string(), splits.to_vec());
+        }
+        is_best
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+        ron::from_str(&source).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let source = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|error| format!("{}: {}", path.display(), error))?;
+        }
+        std::fs::write(path, source).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+}
+
+/// Fired when a player passes a checkpoint in order
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CheckpointReached {
+    pub player: Entity,
+    pub index: usize,
+    pub lap: u32,
+    /// Ticks since the start
+    pub split: u32,
+    /// Ticks against the best run at the same split, negative when ahead
+    pub delta_to_best: Option<i64>,
+}
+
+/// Fired when a player passes the last checkpoint of the last lap
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RaceFinished {
+    pub player: Entity,
+    pub total: u32,
+    pub new_best: bool,
+}
+
+/// Spawn a translucent checkpoint gate
+pub fn spawn_checkpoint(commands: &mut Commands, position: Vec2, checkpoint: Checkpoint) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(0.2, 0.9, 0.5, 0.25),
+                    custom_size: Some(Vec2::splat(checkpoint.radius * 2.0)),
+                    ..default()
+                },
+                // Behind the players so they stay visible inside the gate
+                transform: Transform::from_translation(position.extend(-1.0)),
+                ..default()
+            },
+            checkpoint,
+            GameplayEntity,
+        ))
+        .id()
+}
+
+/// Format a tick count as `m:ss.mmm`
+pub fn format_ticks(ticks: u32, tick_seconds: f32) -> String {
+    let millis = (ticks as f64 * tick_seconds as f64 * 1000.0).round() as u64;
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+pub fn tick_race_timers(mut runners: Query<&mut RaceProgress>) {
+    for mut progress in runners.iter_mut() {
+        if !progress.finished {
+            progress.ticks += 1;
+        }
+    }
+}
+
+/// Advances players through the checkpoints they touch in order. Touching any
+/// other checkpoint does nothing.
+pub fn detect_checkpoints(
+    level: Res<CurrentLevel>,
+    mut best_times: ResMut<BestTimes>,
+    mut runners: Query<(
+        Entity,
+        &Transform,
+        Option<&CollisionRadius>,
+        &mut RaceProgress,
+    )>,
+    checkpoints: Query<(&Transform, &Checkpoint)>,
+    mut reached_events: EventWriter<CheckpointReached>,
+    mut finished_events: EventWriter<RaceFinished>,
+) {
+    let level_name = &level.definition.name;
+
+    for (player, transform, radius, mut progress) in runners.iter_mut() {
+        if progress.finished {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        let radius = radius.copied().unwrap_or_default().0;
+
+        let touched = checkpoints
+            .iter()
+            .any(|(checkpoint_transform, checkpoint)| {
+                checkpoint.index == progress.next
+                    && position.distance(checkpoint_transform.translation.truncate())
+                        <= radius + checkpoint.radius
+            });
+        if !touched {
+            continue;
+        }
+
+        let index = progress.next;
+        let lap = progress.lap;
+        let split = progress.pass_checkpoint();
+        reached_events.send(CheckpointReached {
+            player,
+            index,
+            lap,
+            split,
+            delta_to_best: best_times.delta(level_name, progress.splits.len() - 1, split),
+        });
+
+        if progress.finished {
+            finished_events.send(RaceFinished {
+                player,
+                total: split,
+                new_best: best_times.submit(level_name, &progress.splits),
+            });
+        }
+    }
+}
+
+/// Logs splits and results against the best run
+pub fn log_race_events(
+    fixed_time: Res<FixedTime>,
+    mut reached_events: EventReader<CheckpointReached>,
+    mut finished_events: EventReader<RaceFinished>,
+) {
+    let tick_seconds = fixed_delta(&fixed_time);
+    for event in reached_events.iter() {
+        let delta = match event.delta_to_best {
+            Some(delta) if delta < 0 => {
+                format!(
+                    " (-{})",
+                    format_ticks(delta.unsigned_abs() as u32, tick_seconds)
+                )
+            }
+            Some(delta) => format!(" (+{})", format_ticks(delta as u32, tick_seconds)),
+            None => String::new(),
+        };
+        info!(
+            "{:?} checkpoint {} lap {}: {}{}",
+            event.player,
+            event.index + 1,
+            event.lap + 1,
+            format_ticks(event.split, tick_seconds),
+            delta
+        );
+    }
+    for event in finished_events.iter() {
+        info!(
+            "{:?} finished in {}{}",
+            event.player,
+            format_ticks(event.total, tick_seconds),
+            if event.new_best { ", new best!" } else { "" }
+        );
+    }
+}
+
+/// Once every racing player is through the finish the session ends, and a new
+/// best time is written to disk
+pub fn finish_race(
+    mut finished_events: EventReader<RaceFinished>,
+    best_times: Res<BestTimes>,
+    runners: Query<&RaceProgress, With<Player>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let mut new_best = false;
+    let mut any_finished = false;
+    for event in finished_events.iter() {
+        any_finished = true;
+        new_best |= event.new_best;
+    }
+    if !any_finished {
+        return;
+    }
+
+    if new_best {
+        if let Err(error) = best_times.write(BEST_TIMES_PATH) {
+            warn!("Could not store best times: {}", error);
+        }
+    }
+    if runners.iter().all(|progress| progress.finished) {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+/// The next checkpoint of the first player is drawn brighter
+pub fn highlight_next_checkpoint(
+    runners: Query<&RaceProgress, With<Player>>,
+    mut checkpoints: Query<(&Checkpoint, &mut Sprite)>,
+) {
+    let next = runners
+        .iter()
+        .find(|progress| !progress.finished)
+        .map(|p| p.next);
+    for (checkpoint, mut sprite) in checkpoints.iter_mut() {
+        let alpha = if Some(checkpoint.index) == next {
+            0.6
+        } else {
+            0.25
+        };
+        sprite.color.set_a(alpha);
+    }
+}
+
+/// Bundle the time-attack mode for easy registration. Only levels with a
+/// `race` section spawn checkpoints, so other levels are unaffected.
+pub struct RaceSystems;
+
+impl RaceSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        let best_times = match BestTimes::read(BEST_TIMES_PATH) {
+            Ok(best_times) => best_times,
+            Err(error) => {
+                info!("No best times loaded: {}", error);
+                BestTimes::default()
+            }
+        };
+
+        app.insert_resource(best_times)
+            .add_event::<CheckpointReached>()
+            .add_event::<RaceFinished>()
+            .add_systems(
+                FixedUpdate,
+                (tick_race_timers, detect_checkpoints)
+                    .chain()
+                    .after(obstacle_collision)
+                    .in_set(PhysicsStep::Simulate),
+            )
+            .add_systems(
+                Update,
+                (log_race_events, finish_race, highlight_next_checkpoint)
+                    .run_if(in_state(GameState::Playing)),
+            )
+    }
+}
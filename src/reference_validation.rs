@@ -0,0 +1,359 @@
+use crate::entity_relations::{
+    despawn_related, ChildOf, Children, Owner, Parent, Relation, Target,
+};
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// What the validator does about a broken reference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RepairAction {
+    /// Only list it in the report
+    Report,
+    /// Remove the relation component, and the entry in the parent's
+    /// `Children` for a hierarchy link
+    Remove,
+    /// Despawn the referring entity through `despawn_related`
+    Despawn,
+    /// Point the reference at `ReferenceValidation::default_owner`, removes
+    /// it without one
+    DefaultOwner,
+}
+
+/// How each relation is checked and repaired. `Children` and the `Parent`
+/// marker are the other side of `ChildOf` and are checked with it.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct ReferenceValidation {
+    pub child_of: RepairAction,
+    pub owner: RepairAction,
+    pub target: RepairAction,
+    pub default_owner: Option<Entity>,
+    /// Panic on any broken reference instead of repairing it, for tests
+    pub strict: bool,
+}
+
+impl Default for ReferenceValidation {
+    /// Attachments of a despawned parent go with it. Projectiles keep flying
+    /// without their shooter and enemies are retargeted when a player
+    /// respawns, so dangling owners and targets are only reported.
+    fn default() -> Self {
+        Self {
+            child_of: RepairAction::Despawn,
+            owner: RepairAction::Report,
+            target: RepairAction::Report,
+            default_owner: None,
+            strict: false,
+        }
+    }
+}
+
+impl ReferenceValidation {
+    /// Default actions, panicking on the first broken reference
+    pub fn strict() -> Self {
+        Self {
+            strict: true,
+            ..default()
+        }
+    }
+
+    pub fn action(&self, relation: Relation) -> RepairAction {
+        match relation {
+            Relation::ChildOf => self.child_of,
+            Relation::Owner => self.owner,
+            Relation::Target => self.target,
+        }
+    }
+}
+
+/// What is wrong with a reference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReferenceFault {
+    /// It points to an entity that no longer exists
+    Dangling,
+    /// Both sides of a hierarchy link disagree, such as a child missing from
+    /// its parent's `Children`, or a parent whose `Parent` marker does not
+    /// match its `Children`. The latter refers to the parent itself.
+    Inconsistent,
+}
+
+/// One broken reference from `entity` to `referenced`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReferenceProblem {
+    pub relation: Relation,
+    pub fault: ReferenceFault,
+    pub entity: Entity,
+    pub referenced: Entity,
+    /// What was done about it
+    pub action: RepairAction,
+}
+
+/// Outcome of the last validation pass
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct ReferenceReport {
+    /// Broken references per relation
+    pub counts: BTreeMap<Relation, usize>,
+    pub problems: Vec<ReferenceProblem>,
+}
+
+impl ReferenceReport {
+    pub fn is_clean(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    pub fn count(&self, relation: Relation) -> usize {
+        self.counts.get(&relation).copied().unwrap_or(0)
+    }
+}
+
+impl fmt::Display for ReferenceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} broken references", self.problems.len())?;
+        for (relation, count) in self.counts.iter() {
+            write!(f, ", {:?}: {}", relation, count)?;
+        }
+        for problem in self.problems.iter() {
+            write!(
+                f,
+                "\n  {:?} {:?} -> {:?} ({:?}, {:?})",
+                problem.relation, problem.entity, problem.referenced, problem.fault, problem.action
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Sent for every reference the validator changed
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReferenceRepaired {
+    pub relation: Relation,
+    pub entity: Entity,
+    /// What the reference pointed to before the repair
+    pub referenced: Entity,
+    pub action: RepairAction,
+}
+
+/// Every broken reference in `world`, without changing anything
+pub fn find_reference_problems(
+    world: &mut World,
+    validation: &ReferenceValidation,
+) -> Vec<ReferenceProblem> {
+    let alive = |world: &World, entity: Entity| world.get_entity(entity).is_some();
+    let mut problems = Vec::new();
+    let mut problem = |relation, fault, entity, referenced| {
+        problems.push(ReferenceProblem {
+            relation,
+            fault,
+            entity,
+            referenced,
+            action: validation.action(relation),
+        })
+    };
+
+    let links: Vec<(Entity, Entity)> = world
+        .query::<(Entity, &ChildOf)>()
+        .iter(world)
+        .map(|(entity, child_of)| (entity, child_of.0))
+        .collect();
+    for (entity, referenced) in links {
+        if !alive(world, referenced) {
+            problem(
+                Relation::ChildOf,
+                ReferenceFault::Dangling,
+                entity,
+                referenced,
+            );
+        } else if !world
+            .get::<Children>(referenced)
+            .is_some_and(|children| children.0.contains(&entity))
+        {
+            problem(
+                Relation::ChildOf,
+                ReferenceFault::Inconsistent,
+                entity,
+                referenced,
+            );
+        }
+    }
+
+    // Parents marked as such exactly when they have children
+    let marks: Vec<Entity> = world
+        .query::<(Entity, Option<&Children>, Option<&Parent>)>()
+        .iter(world)
+        .filter(|(_, children, marker)| {
+            children.is_some_and(|c| !c.0.is_empty()) != marker.is_some()
+        })
+        .map(|(entity, ..)| entity)
+        .collect();
+    for parent in marks {
+        problem(
+            Relation::ChildOf,
+            ReferenceFault::Inconsistent,
+            parent,
+            parent,
+        );
+    }
+
+    // Children listed by a parent they do not name
+    let lists: Vec<(Entity, Vec<Entity>)> = world
+        .query::<(Entity, &Children)>()
+        .iter(world)
+        .map(|(entity, children)| (entity, children.0.clone()))
+        .collect();
+    for (parent, children) in lists {
+        for child in children {
+            if world.get::<ChildOf>(child).map(|r| r.0) != Some(parent) {
+                problem(
+                    Relation::ChildOf,
+                    ReferenceFault::Inconsistent,
+                    parent,
+                    child,
+                );
+            }
+        }
+    }
+
+    for relation in [Relation::Owner, Relation::Target] {
+        let references: Vec<(Entity, Entity)> = match relation {
+            Relation::Owner => world
+                .query::<(Entity, &Owner)>()
+                .iter(world)
+                .map(|(entity, r)| (entity, r.0))
+                .collect(),
+            _ => world
+                .query::<(Entity, &Target)>()
+                .iter(world)
+                .map(|(entity, r)| (entity, r.0))
+                .collect(),
+        };
+        for (entity, referenced) in references {
+            if !alive(world, referenced) {
+                problem(relation, ReferenceFault::Dangling, entity, referenced);
+            }
+        }
+    }
+    problems
+}
+
+/// Make `problem`'s reference valid again. Returns whether anything changed.
+fn repair(world: &mut World, problem: &ReferenceProblem, default_owner: Option<Entity>) -> bool {
+    let ReferenceProblem {
+        relation,
+        fault,
+        entity,
+        referenced,
+        action,
+    } = *problem;
+    if action == RepairAction::Report || world.get_entity(entity).is_none() {
+        return false;
+    }
+
+    // A hierarchy link that only disagrees with itself is made whole
+    if fault == ReferenceFault::Inconsistent {
+        let parent = if world.get::<ChildOf>(entity).map(|r| r.0) == Some(referenced) {
+            // The child names a live parent
+            let mut parent = world.entity_mut(referenced);
+            match parent.get_mut::<Children>() {
+                Some(mut children) if !children.0.contains(&entity) => children.0.push(entity),
+                Some(_) => {}
+                None => {
+                    parent.insert(Children(vec![entity]));
+                }
+            }
+            referenced
+        } else {
+            // The parent lists a child that names someone else, or is marked wrong
+            if let Some(mut children) = world.get_mut::<Children>(entity) {
+                children.0.retain(|&child| child != referenced);
+            }
+            entity
+        };
+        // Mark the parent exactly when children are left
+        let mut parent = world.entity_mut(parent);
+        if parent.get::<Children>().is_some_and(|c| !c.0.is_empty()) {
+            parent.insert(Parent);
+        } else {
+            parent.remove::<(Children, Parent)>();
+        }
+        return true;
+    }
+
+    let remove = |world: &mut World| match relation {
+        Relation::ChildOf => {
+            world.entity_mut(entity).remove::<ChildOf>();
+        }
+        Relation::Owner => {
+            world.entity_mut(entity).remove::<Owner>();
+        }
+        Relation::Target => {
+            world.entity_mut(entity).remove::<Target>();
+        }
+    };
+    match action {
+        RepairAction::Report => return false,
+        RepairAction::Remove => remove(world),
+        RepairAction::Despawn => {
+            despawn_related(world, entity);
+        }
+        RepairAction::DefaultOwner => {
+            match default_owner
+                .filter(|&owner| owner != entity && world.get_entity(owner).is_some())
+            {
+                Some(owner) => {
+                    remove(world);
+                    relation.set(world, entity, owner);
+                }
+                None => remove(world),
+            }
+        }
+    }
+    true
+}
+
+/// Find broken references, repair them with the configured actions, send a
+/// `ReferenceRepaired` for each repair and store a `ReferenceReport`. In
+/// strict mode any broken reference panics with the report instead.
+pub fn validate_entity_references(world: &mut World) {
+    let validation = world
+        .get_resource::<ReferenceValidation>()
+        .cloned()
+        .unwrap_or_default();
+    let problems = find_reference_problems(world, &validation);
+
+    let mut report = ReferenceReport::default();
+    for problem in problems.iter() {
+        *report.counts.entry(problem.relation).or_default() += 1;
+    }
+    report.problems = problems;
+    if validation.strict && !report.is_clean() {
+        panic!("{}", report);
+    }
+
+    let mut repaired = Vec::new();
+    for problem in report.problems.iter() {
+        if repair(world, problem, validation.default_owner) {
+            repaired.push(ReferenceRepaired {
+                relation: problem.relation,
+                entity: problem.entity,
+                referenced: problem.referenced,
+                action: problem.action,
+            });
+        }
+    }
+    if let Some(mut events) = world.get_resource_mut::<Events<ReferenceRepaired>>() {
+        events.extend(repaired);
+    }
+    world.insert_resource(report);
+}
+
+/// Bundle reference validation for easy registration. It runs once a frame,
+/// after the fixed ticks and gameplay systems despawned what they will.
+pub struct ReferenceValidationSystems;
+
+impl ReferenceValidationSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.init_resource::<ReferenceValidation>()
+            .init_resource::<ReferenceReport>()
+            .add_event::<ReferenceRepaired>()
+            .add_systems(PostUpdate, validate_entity_references)
+    }
+}
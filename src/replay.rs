@@ -0,0 +1,261 @@
+use crate::components::{FireIntent, MovementIntent, Player};
+use crate::game_state::GameState;
+use crate::procgen::ArenaSettings;
+use crate::rollback::NetInput;
+use crate::systems::PhysicsStep;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Fixed ticks simulated since the session started. Replays and bots key
+/// their inputs on it.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimulationTick(pub u32);
+
+/// Which recorded input stream drives this player
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InputSlot(pub u8);
+
+/// From `tick` on, `slot` holds `input`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayInput {
+    pub tick: u32,
+    pub slot: u8,
+    pub input: NetInput,
+}
+
+/// Player inputs of a session, stored only when they change. Played back
+/// with the same seed and level, the simulation takes the same course.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputReplay {
+    /// Seed of the gameplay random generator
+    pub seed: u64,
+    /// Level file the inputs were recorded on, the default arena when `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<PathBuf>,
+    /// Generated arena the inputs were recorded on, instead of a level file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arena: Option<ArenaSettings>,
+    /// Sorted by tick
+    pub inputs: Vec<ReplayInput>,
+}
+
+/// Why a replay could not be written or read
+#[derive(Debug)]
+pub enum ReplayError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Serialize(String),
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            ReplayError::Serialize(message) => {
+                write!(f, "could not serialize replay: {}", message)
+            }
+            ReplayError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReplayError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl InputReplay {
+    pub fn new(seed: u64, level: Option<PathBuf>) -> Self {
+        Self {
+            seed,
+            level,
+            arena: None,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Input `slot` holds on `tick`, idle before its first recorded input
+    pub fn input(&self, tick: u32, slot: u8) -> NetInput {
+        let recorded = self.inputs.partition_point(|entry| entry.tick <= tick);
+        self.inputs[..recorded]
+            .iter()
+            .rev()
+            .find(|entry| entry.slot == slot)
+            .map(|entry| entry.input)
+            .unwrap_or_default()
+    }
+
+    /// Store `input` for `slot` on `tick` if it differs from what the slot
+    /// holds. Ticks must not go backwards.
+    pub fn record(&mut self, tick: u32, slot: u8, input: NetInput) {
+        if self.input(tick, slot) != input {
+            self.inputs.push(ReplayInput { tick, slot, input });
+        }
+    }
+
+    /// Last tick with a recorded change
+    pub fn last_tick(&self) -> u32 {
+        self.inputs.last().map_or(0, |entry| entry.tick)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ReplayError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| ReplayError::Serialize(error.to_string()))
+    }
+
+    /// `path` is only used in error messages
+    pub fn from_ron(source: &str, path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let mut replay: Self = ron::from_str(source).map_err(|error| ReplayError::Parse {
+            path: path.as_ref().to_path_buf(),
+            line: error.position.line,
+            column: error.position.col,
+            message: error.code.to_string(),
+        })?;
+        replay.inputs.sort_by_key(|entry| entry.tick);
+        Ok(replay)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        let path = path.as_ref();
+        let io_error = |source| ReplayError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        std::fs::write(path, self.to_ron()?).map_err(io_error)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| ReplayError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_ron(&source, path)
+    }
+}
+
+pub fn count_ticks(mut tick: ResMut<SimulationTick>) {
+    tick.0 += 1;
+}
+
+pub fn reset_simulation_tick(mut tick: ResMut<SimulationTick>) {
+    tick.0 = 0;
+}
+
+/// Players that have no input slot yet
+pub type UnslottedPlayerQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static Transform), (With<Player>, Without<InputSlot>)>;
+
+/// Gives players without a slot the lowest free slots, sorted by position so
+/// the order does not depend on spawn order. A respawned player takes over
+/// the slot of the one it replaces.
+pub fn assign_input_slots(
+    mut commands: Commands,
+    assigned: Query<&InputSlot>,
+    unassigned: UnslottedPlayerQuery,
+) {
+    let mut free = (0..=u8::MAX).filter(|slot| !assigned.iter().any(|s| s.0 == *slot));
+    let mut players: Vec<(Entity, Vec2)> = unassigned
+        .iter()
+        .map(|(entity, transform)| (entity, transform.translation.truncate()))
+        .collect();
+    players.sort_by(|(_, a), (_, b)| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+
+    for (entity, _) in players {
+        let Some(slot) = free.next() else {
+            break;
+        };
+        commands.entity(entity).insert(InputSlot(slot));
+    }
+}
+
+/// Writes the replayed input of every slot into its player's intents
+pub fn play_input_replay(
+    replay: Res<InputReplay>,
+    tick: Res<SimulationTick>,
+    mut players: Query<(&InputSlot, &mut MovementIntent, Option<&mut FireIntent>)>,
+) {
+    for (slot, mut intent, fire) in players.iter_mut() {
+        let input = replay.input(tick.0, slot.0);
+        *intent = input.intent();
+        if let Some(mut fire) = fire {
+            *fire = input.fire();
+        }
+    }
+}
+
+/// Records the inputs of a session as it runs, bots included
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct InputRecorder(pub InputReplay);
+
+/// Stores each slot's input of this tick. The intents are quantized the way
+/// a replay plays them back, so playing the recording takes the same course.
+pub fn record_inputs(
+    mut recorder: ResMut<InputRecorder>,
+    tick: Res<SimulationTick>,
+    mut players: Query<(&InputSlot, &mut MovementIntent, Option<&mut FireIntent>)>,
+) {
+    let mut players: Vec<_> = players.iter_mut().collect();
+    players.sort_by_key(|(slot, ..)| **slot);
+    for (slot, mut intent, fire) in players {
+        let input = NetInput::from_intent(&intent, fire.as_deref());
+        *intent = input.intent();
+        if let Some(mut fire) = fire {
+            *fire = input.fire();
+        }
+        recorder.0.record(tick.0, slot.0, input);
+    }
+}
+
+/// Bundle tick counting, replay playback and recording for easy
+/// registration. Inputs are played back while an `InputReplay` resource
+/// exists and recorded while an `InputRecorder` does.
+pub struct ReplaySystems;
+
+impl ReplaySystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.init_resource::<SimulationTick>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    assign_input_slots,
+                    apply_deferred,
+                    play_input_replay.run_if(resource_exists::<InputReplay>()),
+                )
+                    .chain()
+                    .in_set(PhysicsStep::Begin),
+            )
+            .add_systems(
+                FixedUpdate,
+                record_inputs
+                    .run_if(resource_exists::<InputRecorder>())
+                    .after(play_input_replay)
+                    .in_set(PhysicsStep::Begin),
+            )
+            .add_systems(FixedUpdate, count_ticks.in_set(PhysicsStep::End))
+            .add_systems(OnExit(GameState::MainMenu), reset_simulation_tick)
+            .add_systems(OnExit(GameState::GameOver), reset_simulation_tick)
+    }
+}
@@ -0,0 +1,1309 @@
+use crate::collectibles::Collectible;
+use crate::components::{
+    Acceleration, FireIntent, LinearVelocity, MovementIntent, Obstacle, Player,
+};
+use crate::enemy::Enemy;
+use crate::game_state::{GameState, GameplayEntity};
+use crate::health::Health;
+use crate::input::handle_input;
+use crate::level::{spawn_obstacle, spawn_player};
+use crate::modifiers::StatModifiers;
+use crate::particles::ParticleSet;
+use crate::power_ups::{PowerUp, PowerUpKind};
+use crate::projectiles::{handle_fire_input, Projectile, Weapon};
+use crate::rollback::NetInput;
+use crate::stamina::{update_stamina, Stamina};
+use crate::systems::{PhysicsSet, PhysicsStep};
+use crate::transport::{NetError, Transport};
+use bevy::ecs::system::CommandQueue;
+use bevy::prelude::*;
+use std::collections::{BTreeMap, VecDeque};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::transport::UdpTransport;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::SocketAddr;
+
+/// Server states kept as delta baselines
+pub const SNAPSHOT_HISTORY: usize = 64;
+
+/// Received states a client keeps for baselines and interpolation
+pub const CLIENT_STATE_BUFFER: usize = 32;
+
+/// Unacknowledged inputs a client keeps resending; older ones are dropped
+pub const MAX_PENDING_INPUTS: usize = 120;
+
+/// Id of a replicated entity, the same on the server and every client
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NetId(pub u32);
+
+/// Client side copy of the server entity with this id
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Replica(pub NetId);
+
+/// Server player driven by the inputs of the client with this index
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientPlayer(pub usize);
+
+/// What a replicated entity is, enough for a client to draw it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplicatedKind {
+    Player,
+    Enemy,
+    Projectile { radius: f32 },
+    Collectible { radius: f32 },
+    PowerUp(PowerUpKind),
+    Obstacle { half_size: Vec2 },
+}
+
+/// Replicated state of one entity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityState {
+    pub kind: ReplicatedKind,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    /// Zero for entities without health
+    pub health: f32,
+}
+
+/// Every replicated entity after the server simulated `tick`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorldState {
+    pub tick: u32,
+    pub entities: BTreeMap<NetId, EntityState>,
+}
+
+/// Changed fields of one entity. Entities new to the baseline carry every field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityDelta {
+    pub id: NetId,
+    pub kind: Option<ReplicatedKind>,
+    pub position: Option<Vec2>,
+    pub velocity: Option<Vec2>,
+    pub health: Option<f32>,
+}
+
+impl EntityDelta {
+    const KIND: u8 = 1;
+    const POSITION: u8 = 2;
+    const VELOCITY: u8 = 4;
+    const HEALTH: u8 = 8;
+
+    fn mask(&self) -> u8 {
+        let mut mask = 0;
+        if self.kind.is_some() {
+            mask |= Self::KIND;
+        }
+        if self.position.is_some() {
+            mask |= Self::POSITION;
+        }
+        if self.velocity.is_some() {
+            mask |= Self::VELOCITY;
+        }
+        if self.health.is_some() {
+            mask |= Self::HEALTH;
+        }
+        mask
+    }
+}
+
+/// World state the server sends a client every tick, as the difference from
+/// the newest state that client acknowledged
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotPacket {
+    pub tick: u32,
+    /// Tick of the state this packet is relative to, `None` for a full snapshot
+    pub baseline: Option<u32>,
+    /// Last input of the receiving client the server has simulated
+    pub last_input: Option<u32>,
+    /// The receiving client's own player
+    pub player: Option<NetId>,
+    pub changes: Vec<EntityDelta>,
+    /// Entities in the baseline that no longer exist
+    pub removed: Vec<NetId>,
+}
+
+impl SnapshotPacket {
+    /// Packet that turns `baseline` into `state`, or the whole state without a baseline
+    pub fn delta(state: &WorldState, baseline: Option<&WorldState>) -> Self {
+        let empty = BTreeMap::new();
+        let previous = baseline.map_or(&empty, |baseline| &baseline.entities);
+
+        let changes = state
+            .entities
+            .iter()
+            .filter_map(|(id, current)| {
+                let old = previous.get(id);
+                let changed = |same: fn(&EntityState, &EntityState) -> bool| {
+                    old.is_none_or(|old| !same(old, current))
+                };
+                let delta = EntityDelta {
+                    id: *id,
+                    kind: changed(|a, b| a.kind == b.kind).then_some(current.kind),
+                    position: changed(|a, b| a.position == b.position).then_some(current.position),
+                    velocity: changed(|a, b| a.velocity == b.velocity).then_some(current.velocity),
+                    health: changed(|a, b| a.health == b.health).then_some(current.health),
+                };
+                (delta.mask() != 0).then_some(delta)
+            })
+            .collect();
+        let removed = previous
+            .keys()
+            .filter(|id| !state.entities.contains_key(id))
+            .copied()
+            .collect();
+
+        Self {
+            tick: state.tick,
+            baseline: baseline.map(|baseline| baseline.tick),
+            last_input: None,
+            player: None,
+            changes,
+            removed,
+        }
+    }
+
+    /// Rebuild the server state from `baseline`, which must be the state this
+    /// packet was made against
+    pub fn apply(&self, baseline: Option<&WorldState>) -> Result<WorldState, NetError> {
+        if baseline.map(|baseline| baseline.tick) != self.baseline {
+            return Err(NetError::Malformed(format!(
+                "snapshot {} needs baseline {:?}",
+                self.tick, self.baseline
+            )));
+        }
+        let mut entities =
+            baseline.map_or_else(BTreeMap::new, |baseline| baseline.entities.clone());
+        for id in self.removed.iter() {
+            entities.remove(id);
+        }
+        for delta in self.changes.iter() {
+            match entities.get_mut(&delta.id) {
+                Some(state) => {
+                    state.kind = delta.kind.unwrap_or(state.kind);
+                    state.position = delta.position.unwrap_or(state.position);
+                    state.velocity = delta.velocity.unwrap_or(state.velocity);
+                    state.health = delta.health.unwrap_or(state.health);
+                }
+                None => {
+                    let (Some(kind), Some(position), Some(velocity), Some(health)) =
+                        (delta.kind, delta.position, delta.velocity, delta.health)
+                    else {
+                        return Err(NetError::Malformed(format!(
+                            "new entity {} is missing fields",
+                            delta.id.0
+                        )));
+                    };
+                    entities.insert(
+                        delta.id,
+                        EntityState {
+                            kind,
+                            position,
+                            velocity,
+                            health,
+                        },
+                    );
+                }
+            }
+        }
+        Ok(WorldState {
+            tick: self.tick,
+            entities,
+        })
+    }
+
+    /// Little endian header of tick, baseline, last input and player, with
+    /// `u32::MAX` for `None`. Then each change as id, field mask and the
+    /// fields present, then the removed ids.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = PacketWriter::default();
+        writer.u32(self.tick);
+        writer.optional(self.baseline);
+        writer.optional(self.last_input);
+        writer.optional(self.player.map(|id| id.0));
+
+        writer.u16(self.changes.len() as u16);
+        for delta in self.changes.iter() {
+            writer.u32(delta.id.0);
+            writer.u8(delta.mask());
+            if let Some(kind) = delta.kind {
+                writer.kind(kind);
+            }
+            if let Some(position) = delta.position {
+                writer.vec2(position);
+            }
+            if let Some(velocity) = delta.velocity {
+                writer.vec2(velocity);
+            }
+            if let Some(health) = delta.health {
+                writer.f32(health);
+            }
+        }
+
+        writer.u16(self.removed.len() as u16);
+        for id in self.removed.iter() {
+            writer.u32(id.0);
+        }
+        writer.0
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, NetError> {
+        let mut reader = PacketReader::new(bytes);
+        let tick = reader.u32()?;
+        let baseline = reader.optional()?;
+        let last_input = reader.optional()?;
+        let player = reader.optional()?.map(NetId);
+
+        let changes = (0..reader.u16()?)
+            .map(|_| {
+                let id = NetId(reader.u32()?);
+                let mask = reader.u8()?;
+                let has = |bit: u8| mask & bit != 0;
+                Ok(EntityDelta {
+                    id,
+                    kind: has(EntityDelta::KIND).then(|| reader.kind()).transpose()?,
+                    position: has(EntityDelta::POSITION)
+                        .then(|| reader.vec2())
+                        .transpose()?,
+                    velocity: has(EntityDelta::VELOCITY)
+                        .then(|| reader.vec2())
+                        .transpose()?,
+                    health: has(EntityDelta::HEALTH).then(|| reader.f32()).transpose()?,
+                })
+            })
+            .collect::<Result<Vec<_>, NetError>>()?;
+        let removed = (0..reader.u16()?)
+            .map(|_| reader.u32().map(NetId))
+            .collect::<Result<Vec<_>, NetError>>()?;
+        reader.finish()?;
+
+        Ok(Self {
+            tick,
+            baseline,
+            last_input,
+            player,
+            changes,
+            removed,
+        })
+    }
+}
+
+/// Inputs a client sends every tick: all the server has not simulated yet,
+/// so a lost packet is covered by the next one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientPacket {
+    /// Newest snapshot the client has, the server makes deltas against it
+    pub ack: Option<u32>,
+    /// Sequence number of the first input
+    pub first_input: u32,
+    pub inputs: Vec<NetInput>,
+}
+
+impl ClientPacket {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = PacketWriter::default();
+        writer.optional(self.ack);
+        writer.u32(self.first_input);
+        writer.u16(self.inputs.len() as u16);
+        for input in self.inputs.iter() {
+            writer.u8(input.x as u8);
+            writer.u8(input.y as u8);
+            writer.u8(input.buttons);
+        }
+        writer.0
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, NetError> {
+        let mut reader = PacketReader::new(bytes);
+        let ack = reader.optional()?;
+        let first_input = reader.u32()?;
+        let inputs = (0..reader.u16()?)
+            .map(|_| {
+                Ok(NetInput {
+                    x: reader.u8()? as i8,
+                    y: reader.u8()? as i8,
+                    buttons: reader.u8()?,
+                })
+            })
+            .collect::<Result<Vec<_>, NetError>>()?;
+        reader.finish()?;
+        Ok(Self {
+            ack,
+            first_input,
+            inputs,
+        })
+    }
+}
+
+#[derive(Default)]
+struct PacketWriter(Vec<u8>);
+
+impl PacketWriter {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn optional(&mut self, value: Option<u32>) {
+        self.u32(value.unwrap_or(u32::MAX));
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn vec2(&mut self, value: Vec2) {
+        self.f32(value.x);
+        self.f32(value.y);
+    }
+
+    fn kind(&mut self, kind: ReplicatedKind) {
+        match kind {
+            ReplicatedKind::Player => self.u8(0),
+            ReplicatedKind::Enemy => self.u8(1),
+            ReplicatedKind::Projectile { radius } => {
+                self.u8(2);
+                self.f32(radius);
+            }
+            ReplicatedKind::Collectible { radius } => {
+                self.u8(3);
+                self.f32(radius);
+            }
+            ReplicatedKind::PowerUp(kind) => {
+                self.u8(4);
+                self.u8(match kind {
+                    PowerUpKind::Speed => 0,
+                    PowerUpKind::LowFriction => 1,
+                    PowerUpKind::HeavyMass => 2,
+                });
+            }
+            ReplicatedKind::Obstacle { half_size } => {
+                self.u8(5);
+                self.vec2(half_size);
+            }
+        }
+    }
+}
+
+struct PacketReader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> PacketReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, at: 0 }
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], NetError> {
+        let bytes = self
+            .bytes
+            .get(self.at..self.at + N)
+            .ok_or_else(|| NetError::Malformed(format!("packet ends at byte {}", self.at)))?;
+        self.at += N;
+        Ok(bytes.try_into().expect("slice has N bytes"))
+    }
+
+    fn u8(&mut self) -> Result<u8, NetError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, NetError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, NetError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn optional(&mut self) -> Result<Option<u32>, NetError> {
+        Ok(Some(self.u32()?).filter(|value| *value != u32::MAX))
+    }
+
+    fn f32(&mut self) -> Result<f32, NetError> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+
+    fn vec2(&mut self) -> Result<Vec2, NetError> {
+        Ok(Vec2::new(self.f32()?, self.f32()?))
+    }
+
+    fn kind(&mut self) -> Result<ReplicatedKind, NetError> {
+        Ok(match self.u8()? {
+            0 => ReplicatedKind::Player,
+            1 => ReplicatedKind::Enemy,
+            2 => ReplicatedKind::Projectile {
+                radius: self.f32()?,
+            },
+            3 => ReplicatedKind::Collectible {
+                radius: self.f32()?,
+            },
+            4 => ReplicatedKind::PowerUp(match self.u8()? {
+                0 => PowerUpKind::Speed,
+                1 => PowerUpKind::LowFriction,
+                2 => PowerUpKind::HeavyMass,
+                other => return Err(NetError::Malformed(format!("unknown power-up {}", other))),
+            }),
+            5 => ReplicatedKind::Obstacle {
+                half_size: self.vec2()?,
+            },
+            other => {
+                return Err(NetError::Malformed(format!(
+                    "unknown entity kind {}",
+                    other
+                )))
+            }
+        })
+    }
+
+    fn finish(&self) -> Result<(), NetError> {
+        if self.at != self.bytes.len() {
+            return Err(NetError::Malformed(format!(
+                "{} bytes left over",
+                self.bytes.len() - self.at
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Position of entity `id` at a fractional `tick`, blended between the
+/// received states around it. Outside the buffered range the nearest state is used.
+pub fn interpolated_position(states: &VecDeque<WorldState>, id: NetId, tick: f32) -> Option<Vec2> {
+    let with_entity = || {
+        states
+            .iter()
+            .filter_map(move |state| Some((state.tick as f32, state.entities.get(&id)?.position)))
+    };
+    let before = with_entity().rev().find(|(at, _)| *at <= tick);
+    let after = with_entity().find(|(at, _)| *at > tick);
+    match (before, after) {
+        (Some((from_tick, from)), Some((to_tick, to))) => {
+            Some(from.lerp(to, (tick - from_tick) / (to_tick - from_tick)))
+        }
+        (Some((_, position)), None) | (None, Some((_, position))) => Some(position),
+        (None, None) => None,
+    }
+}
+
+/// Server side of one connected client
+struct RemoteClient {
+    transport: Box<dyn Transport>,
+    player: Option<Entity>,
+    /// Received inputs not simulated yet, by sequence number
+    inputs: BTreeMap<u32, NetInput>,
+    next_input: u32,
+    /// Newest snapshot the client has
+    acked: Option<u32>,
+}
+
+/// Authoritative side of a replicated session. The server runs the whole
+/// simulation; clients only send inputs, one applied per server tick to the
+/// player assigned to them, and receive snapshots of every replicated entity.
+#[derive(Resource, Default)]
+pub struct ReplicationServer {
+    clients: Vec<RemoteClient>,
+    /// Next tick to send
+    tick: u32,
+    history: VecDeque<WorldState>,
+    next_id: u32,
+}
+
+impl ReplicationServer {
+    /// Connect a client and return its index
+    pub fn add_client(&mut self, transport: impl Transport + 'static) -> usize {
+        self.clients.push(RemoteClient {
+            transport: Box::new(transport),
+            player: None,
+            inputs: BTreeMap::new(),
+            next_input: 0,
+            acked: None,
+        });
+        self.clients.len() - 1
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Player the inputs of `client` drive
+    pub fn client_player(&self, client: usize) -> Option<Entity> {
+        self.clients.get(client).and_then(|client| client.player)
+    }
+
+    /// Next tick to send
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Newest snapshot `client` acknowledged
+    pub fn acked_tick(&self, client: usize) -> Option<u32> {
+        self.clients.get(client).and_then(|client| client.acked)
+    }
+}
+
+/// Entities that are replicated but have no id yet
+pub type UnidentifiedQuery<'w, 's> = Query<
+    'w,
+    's,
+    Entity,
+    (
+        Without<NetId>,
+        Or<(
+            With<Player>,
+            With<Enemy>,
+            With<Projectile>,
+            With<Collectible>,
+            With<PowerUp>,
+            With<Obstacle>,
+        )>,
+    ),
+>;
+
+/// Gives every replicated entity a server wide unique id
+pub fn assign_net_ids(
+    mut commands: Commands,
+    mut server: ResMut<ReplicationServer>,
+    entities: UnidentifiedQuery,
+) {
+    for entity in entities.iter() {
+        commands.entity(entity).insert(NetId(server.next_id));
+        server.next_id += 1;
+    }
+}
+
+/// Replicated players
+pub type NetPlayerQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static Transform), (With<Player>, With<NetId>)>;
+
+/// Clients without a living player take the free players, sorted by
+/// position so the assignment does not depend on spawn order
+pub fn assign_client_players(
+    mut commands: Commands,
+    mut server: ResMut<ReplicationServer>,
+    players: NetPlayerQuery,
+) {
+    let mut free: Vec<(Entity, Vec2)> = players
+        .iter()
+        .filter(|(entity, _)| !server.clients.iter().any(|c| c.player == Some(*entity)))
+        .map(|(entity, transform)| (entity, transform.translation.truncate()))
+        .collect();
+    free.sort_by(|(_, a), (_, b)| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    let mut free = free.into_iter();
+
+    for (index, client) in server.clients.iter_mut().enumerate() {
+        if client.player.is_some_and(|player| players.contains(player)) {
+            continue;
+        }
+        client.player = None;
+        if let Some((entity, _)) = free.next() {
+            commands.entity(entity).insert(ClientPlayer(index));
+            client.player = Some(entity);
+        }
+    }
+}
+
+/// Reads client packets and writes each client's next input into its player's
+/// intents. A client whose input has not arrived keeps its last one.
+pub fn apply_client_inputs(
+    mut server: ResMut<ReplicationServer>,
+    mut players: Query<(&mut MovementIntent, Option<&mut FireIntent>)>,
+) {
+    for client in server.clients.iter_mut() {
+        for bytes in client.transport.receive() {
+            let packet = match ClientPacket::decode(&bytes) {
+                Ok(packet) => packet,
+                Err(error) => {
+                    warn!("Dropped client packet: {}", error);
+                    continue;
+                }
+            };
+            client.acked = client.acked.max(packet.ack);
+            for (offset, input) in packet.inputs.into_iter().enumerate() {
+                let sequence = packet.first_input + offset as u32;
+                if sequence >= client.next_input {
+                    client.inputs.insert(sequence, input);
+                }
+            }
+        }
+
+        let Some(input) = client.inputs.remove(&client.next_input) else {
+            continue;
+        };
+        client.next_input += 1;
+        let Some(Ok((mut intent, fire))) = client.player.map(|player| players.get_mut(player))
+        else {
+            continue;
+        };
+        *intent = input.intent();
+        if let Some(mut fire) = fire {
+            *fire = input.fire();
+        }
+    }
+}
+
+/// Components a snapshot reads from every replicated entity
+pub type ReplicatedQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static NetId,
+        &'static Transform,
+        Option<&'static LinearVelocity>,
+        Option<&'static Health>,
+        Option<&'static Player>,
+        Option<&'static Enemy>,
+        Option<&'static Projectile>,
+        Option<&'static Collectible>,
+        Option<&'static PowerUp>,
+        Option<&'static Obstacle>,
+    ),
+>;
+
+/// Records the state of this tick and sends every client the difference from
+/// the newest state it acknowledged
+pub fn send_snapshots(
+    mut server: ResMut<ReplicationServer>,
+    entities: ReplicatedQuery,
+    net_ids: Query<&NetId>,
+) {
+    let entities = entities
+        .iter()
+        .filter_map(
+            |(
+                id,
+                transform,
+                velocity,
+                health,
+                player,
+                enemy,
+                projectile,
+                collectible,
+                power_up,
+                obstacle,
+            )| {
+                let kind = if player.is_some() {
+                    ReplicatedKind::Player
+                } else if enemy.is_some() {
+                    ReplicatedKind::Enemy
+                } else if let Some(projectile) = projectile {
+                    ReplicatedKind::Projectile {
+                        radius: projectile.radius,
+                    }
+                } else if let Some(collectible) = collectible {
+                    ReplicatedKind::Collectible {
+                        radius: collectible.radius,
+                    }
+                } else if let Some(power_up) = power_up {
+                    ReplicatedKind::PowerUp(power_up.kind)
+                } else {
+                    ReplicatedKind::Obstacle {
+                        half_size: obstacle?.half_size,
+                    }
+                };
+                let velocity = velocity
+                    .map(|velocity| velocity.0)
+                    .or(projectile.map(|projectile| projectile.velocity))
+                    .unwrap_or_default();
+                Some((
+                    *id,
+                    EntityState {
+                        kind,
+                        position: transform.translation.truncate(),
+                        velocity,
+                        health: health.map_or(0.0, |health| health.current),
+                    },
+                ))
+            },
+        )
+        .collect();
+    let state = WorldState {
+        tick: server.tick,
+        entities,
+    };
+
+    let ReplicationServer {
+        clients, history, ..
+    } = &mut *server;
+    for client in clients.iter_mut() {
+        let baseline = client
+            .acked
+            .and_then(|tick| history.iter().find(|state| state.tick == tick));
+        let mut packet = SnapshotPacket::delta(&state, baseline);
+        packet.last_input = client.next_input.checked_sub(1);
+        packet.player = client
+            .player
+            .and_then(|player| net_ids.get(player).ok())
+            .copied();
+        if let Err(error) = client.transport.send(&packet.encode()) {
+            warn!("Could not send snapshot: {}", error);
+        }
+    }
+
+    history.push_back(state);
+    while history.len() > SNAPSHOT_HISTORY {
+        history.pop_front();
+    }
+    server.tick += 1;
+}
+
+/// Client side of a replicated session. The client simulates only its own
+/// player, ahead of the server, and when a snapshot arrives resets it to the
+/// server state and replays the inputs the server has not simulated yet.
+/// Every other entity is a plain replica drawn a few ticks in the past,
+/// interpolated between the two snapshots around that time.
+///
+/// Like `RollbackSession` it owns `FixedUpdate` while it exists. Stamina and
+/// modifiers are not replicated: they are predicted, and rewound with the
+/// player whenever the pending inputs are replayed.
+#[derive(Resource)]
+pub struct ReplicationClient {
+    transport: Box<dyn Transport>,
+    /// The player the server assigned to this client
+    player: Option<NetId>,
+    states: VecDeque<WorldState>,
+    replicas: BTreeMap<NetId, Entity>,
+    /// Inputs the server has not simulated yet, oldest first
+    pending: VecDeque<PendingInput>,
+    next_input: u32,
+    /// Ticks remote entities are drawn behind the newest snapshot
+    pub interpolation_delay: f32,
+    render_tick: f32,
+    /// Real time not simulated yet
+    accumulator: f32,
+    /// Snapshots that moved the own player away from where it was predicted
+    pub corrections: u32,
+    /// Distance the last reconciliation moved the own player
+    pub last_correction: f32,
+}
+
+impl ReplicationClient {
+    /// Reconciliations that move the player less than this are not counted
+    const CORRECTION_EPSILON: f32 = 0.01;
+
+    pub fn new(transport: impl Transport + 'static) -> Self {
+        Self {
+            transport: Box::new(transport),
+            player: None,
+            states: VecDeque::new(),
+            replicas: BTreeMap::new(),
+            pending: VecDeque::new(),
+            next_input: 0,
+            interpolation_delay: 6.0,
+            render_tick: 0.0,
+            accumulator: 0.0,
+            corrections: 0,
+            last_correction: 0.0,
+        }
+    }
+
+    pub fn player(&self) -> Option<NetId> {
+        self.player
+    }
+
+    /// Local entity standing in for server entity `id`
+    pub fn replica(&self, id: NetId) -> Option<Entity> {
+        self.replicas.get(&id).copied()
+    }
+
+    /// The predicted own player
+    pub fn player_entity(&self) -> Option<Entity> {
+        self.player.and_then(|id| self.replica(id))
+    }
+
+    pub fn latest_state(&self) -> Option<&WorldState> {
+        self.states.back()
+    }
+
+    /// Tick remote entities are drawn at
+    pub fn render_tick(&self) -> f32 {
+        self.render_tick
+    }
+
+    pub fn pending_inputs(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Apply snapshots that arrived: spawn and despawn replicas and reconcile the own player
+    pub fn receive(&mut self, world: &mut World) {
+        let mut received = false;
+        let mut last_input = None;
+
+        for bytes in self.transport.receive() {
+            let packet = match SnapshotPacket::decode(&bytes) {
+                Ok(packet) => packet,
+                Err(error) => {
+                    warn!("Dropped snapshot: {}", error);
+                    continue;
+                }
+            };
+            if self
+                .latest_state()
+                .is_some_and(|latest| latest.tick >= packet.tick)
+            {
+                continue;
+            }
+            let baseline = match packet.baseline {
+                Some(tick) => match self.states.iter().find(|state| state.tick == tick) {
+                    Some(baseline) => Some(baseline),
+                    None => {
+                        warn!(
+                            "Dropped snapshot {}: baseline {} is gone",
+                            packet.tick, tick
+                        );
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            let state = match packet.apply(baseline) {
+                Ok(state) => state,
+                Err(error) => {
+                    warn!("Dropped snapshot: {}", error);
+                    continue;
+                }
+            };
+
+            self.states.push_back(state);
+            while self.states.len() > CLIENT_STATE_BUFFER {
+                self.states.pop_front();
+            }
+            self.player = packet.player;
+            last_input = packet.last_input;
+            received = true;
+        }
+
+        if !received {
+            return;
+        }
+        if let Some(last_input) = last_input {
+            self.pending.retain(|pending| pending.sequence > last_input);
+        }
+        let spawned = self.sync_replicas(world);
+        self.reconcile(world, !spawned);
+    }
+
+    /// Predict one tick of the own player with `input` and send it to the server
+    pub fn advance(&mut self, world: &mut World, input: NetInput) {
+        self.receive(world);
+
+        let entity = self.player_entity();
+        let mut pending = PendingInput {
+            sequence: self.next_input,
+            input,
+            stamina: None,
+            modifiers: None,
+        };
+        if let Some(entity) = entity {
+            pending.save(world, entity);
+            simulate_input(world, entity, input);
+        }
+        self.pending.push_back(pending);
+        self.next_input += 1;
+        while self.pending.len() > MAX_PENDING_INPUTS {
+            self.pending.pop_front();
+        }
+
+        let packet = ClientPacket {
+            ack: self.latest_state().map(|state| state.tick),
+            first_input: self
+                .pending
+                .front()
+                .map_or(self.next_input, |pending| pending.sequence),
+            inputs: self.pending.iter().map(|pending| pending.input).collect(),
+        };
+        if let Err(error) = self.transport.send(&packet.encode()) {
+            warn!("Could not send inputs: {}", error);
+        }
+    }
+
+    /// Move remote replicas `seconds` further along the buffered snapshots
+    pub fn interpolate(&mut self, world: &mut World, seconds: f32) {
+        let Some(latest) = self.latest_state().map(|state| state.tick as f32) else {
+            return;
+        };
+        let period = world.resource::<FixedTime>().period.as_secs_f32();
+        let target = latest - self.interpolation_delay;
+        self.render_tick += seconds / period;
+        // Jump instead of drifting when far behind or ahead, such as after a stall
+        if (self.render_tick - target).abs() > self.interpolation_delay {
+            self.render_tick = target;
+        }
+        self.render_tick = self.render_tick.min(latest);
+
+        for (id, entity) in self.replicas.iter() {
+            if Some(*id) == self.player {
+                continue;
+            }
+            let Some(position) = interpolated_position(&self.states, *id, self.render_tick) else {
+                continue;
+            };
+            if let Some(mut transform) = world.get_mut::<Transform>(*entity) {
+                transform.translation.x = position.x;
+                transform.translation.y = position.y;
+            }
+        }
+    }
+
+    /// Make the replicas match the newest snapshot. Returns whether the own
+    /// player was spawned.
+    fn sync_replicas(&mut self, world: &mut World) -> bool {
+        let Some(state) = self.states.back() else {
+            return false;
+        };
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, world);
+
+        let player = self.player;
+        self.replicas.retain(|id, entity| {
+            let exists = world.get_entity(*entity).is_some();
+            // The own player is a predicted body, a plain replica of it is replaced
+            let wrong_kind =
+                exists && (Some(*id) == player) != world.get::<Player>(*entity).is_some();
+            let keep = exists && !wrong_kind && state.entities.contains_key(id);
+            if exists && !keep {
+                commands.entity(*entity).despawn();
+            }
+            keep
+        });
+
+        let mut spawned_player = false;
+        for (id, entity_state) in state.entities.iter() {
+            if self.replicas.contains_key(id) {
+                continue;
+            }
+            let entity = if Some(*id) == player {
+                spawned_player = true;
+                let entity = spawn_player(&mut commands, entity_state.position);
+                commands.entity(entity).remove::<Weapon>();
+                entity
+            } else {
+                spawn_replica(&mut commands, entity_state)
+            };
+            commands.entity(entity).insert(Replica(*id));
+            self.replicas.insert(*id, entity);
+        }
+        queue.apply(world);
+        spawned_player
+    }
+
+    /// Reset the own player to the server state and replay the pending inputs,
+    /// from the stamina and modifiers it had before the first of them.
+    /// Particles sit the replay out, see `Reconciling`.
+    /// A player that was just spawned had no prediction to count as corrected.
+    fn reconcile(&mut self, world: &mut World, predicted: bool) {
+        let (Some(entity), Some(state)) = (
+            self.player_entity(),
+            self.player
+                .and_then(|id| self.latest_state()?.entities.get(&id).copied()),
+        ) else {
+            return;
+        };
+        let predicted = world
+            .get::<Transform>(entity)
+            .map(|transform| transform.translation.truncate())
+            .filter(|_| predicted);
+
+        let mut body = world.entity_mut(entity);
+        if let Some(mut transform) = body.get_mut::<Transform>() {
+            transform.translation.x = state.position.x;
+            transform.translation.y = state.position.y;
+        }
+        if let Some(mut health) = body.get_mut::<Health>() {
+            health.current = state.health;
+        }
+        body.insert((LinearVelocity(state.velocity), Acceleration::zero()));
+
+        if let Some(first) = self.pending.front() {
+            first.restore(world, entity);
+        }
+        world.insert_resource(Reconciling);
+        for pending in self.pending.iter_mut() {
+            pending.save(world, entity);
+            simulate_input(world, entity, pending.input);
+        }
+        world.remove_resource::<Reconciling>();
+
+        if let (Some(predicted), Some(transform)) = (predicted, world.get::<Transform>(entity)) {
+            self.last_correction = predicted.distance(transform.translation.truncate());
+            if self.last_correction > Self::CORRECTION_EPSILON {
+                self.corrections += 1;
+            }
+        }
+    }
+}
+
+/// Present while a client replays its pending inputs. Those ticks were shown
+/// already, so nothing cosmetic runs in them.
+#[derive(Resource, Debug, Default)]
+pub struct Reconciling;
+
+/// Run condition for `ParticleSet`: particles age and emit once per predicted
+/// tick, not again for every replay of it
+pub fn not_reconciling(reconciling: Option<Res<Reconciling>>) -> bool {
+    reconciling.is_none()
+}
+
+/// An input the server has not simulated yet, with the predicted stats the
+/// own player had before it
+struct PendingInput {
+    sequence: u32,
+    input: NetInput,
+    stamina: Option<Stamina>,
+    modifiers: Option<StatModifiers>,
+}
+
+impl PendingInput {
+    fn save(&mut self, world: &World, entity: Entity) {
+        self.stamina = world.get::<Stamina>(entity).cloned();
+        self.modifiers = world.get::<StatModifiers>(entity).cloned();
+    }
+
+    fn restore(&self, world: &mut World, entity: Entity) {
+        let mut body = world.entity_mut(entity);
+        if let Some(stamina) = &self.stamina {
+            body.insert(stamina.clone());
+        }
+        if let Some(modifiers) = &self.modifiers {
+            body.insert(modifiers.clone());
+        }
+    }
+}
+
+/// Write `input` into the intents of `entity` and run one fixed tick
+fn simulate_input(world: &mut World, entity: Entity, input: NetInput) {
+    if let Some(mut intent) = world.get_mut::<MovementIntent>(entity) {
+        *intent = input.intent();
+    }
+    if let Some(mut fire) = world.get_mut::<FireIntent>(entity) {
+        *fire = input.fire();
+    }
+    world.run_schedule(FixedUpdate);
+}
+
+/// Spawn a plain sprite for a remote entity. Only obstacles keep their
+/// component, so the predicted player collides with them.
+fn spawn_replica(commands: &mut Commands, state: &EntityState) -> Entity {
+    let (color, size) = match state.kind {
+        ReplicatedKind::Obstacle { half_size } => {
+            return spawn_obstacle(commands, state.position, Obstacle { half_size });
+        }
+        ReplicatedKind::Player => (Color::rgb(0.25, 0.25, 0.75), Vec2::splat(50.0)),
+        ReplicatedKind::Enemy => (Color::rgb(0.8, 0.3, 0.2), Vec2::splat(40.0)),
+        ReplicatedKind::Projectile { radius } => {
+            (Color::rgb(1.0, 0.95, 0.6), Vec2::splat(radius * 2.0))
+        }
+        ReplicatedKind::Collectible { radius } => {
+            (Color::rgb(1.0, 0.85, 0.2), Vec2::splat(radius * 2.0))
+        }
+        ReplicatedKind::PowerUp(kind) => {
+            (kind.color(), Vec2::splat(PowerUp::new(kind).radius * 1.5))
+        }
+    };
+    commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform: Transform::from_translation(state.position.extend(0.0)),
+                ..default()
+            },
+            GameplayEntity,
+        ))
+        .id()
+}
+
+/// Predict one tick of the own player in `world`. Does nothing without a client.
+pub fn advance_replication_client(world: &mut World, input: NetInput) {
+    if let Some(mut client) = world.remove_resource::<ReplicationClient>() {
+        client.advance(world, input);
+        world.insert_resource(client);
+    }
+}
+
+/// Apply snapshots that arrived without predicting a new tick
+pub fn synchronize_replication_client(world: &mut World) {
+    if let Some(mut client) = world.remove_resource::<ReplicationClient>() {
+        client.receive(world);
+        world.insert_resource(client);
+    }
+}
+
+/// Move remote replicas `seconds` along in `world`
+pub fn interpolate_replicas(world: &mut World, seconds: f32) {
+    if let Some(mut client) = world.remove_resource::<ReplicationClient>() {
+        client.interpolate(world, seconds);
+        world.insert_resource(client);
+    }
+}
+
+/// Run condition for `PhysicsSet`. A client runs `FixedUpdate` itself, only
+/// for its own player.
+pub fn no_replication_client(client: Option<Res<ReplicationClient>>) -> bool {
+    client.is_none()
+}
+
+/// Steps the client in real time, one predicted tick per fixed period with the
+/// own player's current intent, then interpolates the replicas
+pub fn run_replication_client(world: &mut World) {
+    let Some(mut client) = world.remove_resource::<ReplicationClient>() else {
+        return;
+    };
+    let period = world.resource::<FixedTime>().period.as_secs_f32();
+    let delta = world.resource::<Time>().delta_seconds();
+    client.accumulator += delta;
+
+    let input = client
+        .player_entity()
+        .and_then(|entity| {
+            let body = world.get_entity(entity)?;
+            Some(NetInput::from_intent(
+                body.get::<MovementIntent>()?,
+                body.get::<FireIntent>(),
+            ))
+        })
+        .unwrap_or_default();
+
+    client.receive(world);
+    while client.accumulator >= period {
+        client.advance(world, input);
+        client.accumulator -= period;
+    }
+    client.interpolate(world, delta);
+    world.insert_resource(client);
+}
+
+/// Command line setup for UDP replication.
+/// `--serve <local addr> <client addr>...` hosts, talking to client `i` from
+/// the local port plus `i`; `--connect <local addr> <server addr>` joins.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplicationConfig {
+    Serve {
+        local: SocketAddr,
+        clients: Vec<SocketAddr>,
+    },
+    Connect {
+        local: SocketAddr,
+        server: SocketAddr,
+    },
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ReplicationConfig {
+    /// `None` when neither `--serve` nor `--connect` is among `args`
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, NetError> {
+        let address = |value: &String| {
+            value
+                .parse::<SocketAddr>()
+                .map_err(|_| NetError::Config(format!("'{}' is not an address", value)))
+        };
+
+        if let Some(flag) = args.iter().position(|arg| arg == "--serve") {
+            let values: Vec<&String> = args[flag + 1..]
+                .iter()
+                .take_while(|arg| !arg.starts_with("--"))
+                .collect();
+            let [local, clients @ ..] = values.as_slice() else {
+                return Err(NetError::Config(
+                    "usage: --serve <local addr> <client addr>...".into(),
+                ));
+            };
+            if clients.is_empty() {
+                return Err(NetError::Config("--serve needs at least one client".into()));
+            }
+            return Ok(Some(Self::Serve {
+                local: address(local)?,
+                clients: clients
+                    .iter()
+                    .map(|client| address(client))
+                    .collect::<Result<_, _>>()?,
+            }));
+        }
+
+        if let Some(flag) = args.iter().position(|arg| arg == "--connect") {
+            let Some([local, server]) = args.get(flag + 1..flag + 3) else {
+                return Err(NetError::Config(
+                    "usage: --connect <local addr> <server addr>".into(),
+                ));
+            };
+            return Ok(Some(Self::Connect {
+                local: address(local)?,
+                server: address(server)?,
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Open the sockets and insert the server or client resource
+    pub fn start(&self, world: &mut World) -> Result<(), NetError> {
+        match self {
+            ReplicationConfig::Serve { local, clients } => {
+                let mut server = ReplicationServer::default();
+                for (index, client) in clients.iter().enumerate() {
+                    let mut address = *local;
+                    address.set_port(local.port() + index as u16);
+                    let transport = UdpTransport::bind(address)?;
+                    transport.connect(client)?;
+                    server.add_client(transport);
+                }
+                world.insert_resource(server);
+            }
+            ReplicationConfig::Connect { local, server } => {
+                let transport = UdpTransport::bind(local)?;
+                transport.connect(server)?;
+                world.insert_resource(ReplicationClient::new(transport));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bundle snapshot replication for easy registration. The server systems run
+/// while a `ReplicationServer` exists, the client while a `ReplicationClient` does.
+pub struct ReplicationSystems;
+
+impl ReplicationSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.configure_set(FixedUpdate, PhysicsSet.run_if(no_replication_client))
+            .configure_set(FixedUpdate, ParticleSet.run_if(not_reconciling))
+            .add_systems(
+                FixedUpdate,
+                (assign_net_ids, assign_client_players)
+                    .chain()
+                    .in_set(PhysicsStep::Begin)
+                    .run_if(resource_exists::<ReplicationServer>()),
+            )
+            .add_systems(
+                FixedUpdate,
+                apply_client_inputs
+                    .before(update_stamina)
+                    .in_set(PhysicsStep::Simulate)
+                    .run_if(resource_exists::<ReplicationServer>()),
+            )
+            .add_systems(
+                FixedUpdate,
+                send_snapshots
+                    .in_set(PhysicsStep::End)
+                    .run_if(resource_exists::<ReplicationServer>()),
+            )
+            .add_systems(
+                Update,
+                run_replication_client
+                    .after(handle_input)
+                    .after(handle_fire_input)
+                    .run_if(resource_exists::<ReplicationClient>())
+                    .run_if(in_state(GameState::Playing)),
+            )
+    }
+}
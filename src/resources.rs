@@ -1,15 +1,19 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 /// World boundaries resource defining the playable area
-#[derive(Resource, Debug)]
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WorldBounds {
     pub min: Vec2,
     pub max: Vec2,
     pub friction: f32,
+    /// Fraction of speed kept when bouncing off the bounds. At most 1.0, a
+    /// bounce never adds speed.
     pub bounce_factor: f32,
 }
 
 const DEFAULT_FRICTION: f32 = 0.1;
+const DEFAULT_BOUNCE_FACTOR: f32 = 0.8;
 impl WorldBounds {
     /// Create world bounds from window dimensions with optional margin
     pub fn from_window_size(width: f32, height: f32, margin: f32) -> Self {
@@ -20,7 +24,7 @@ impl WorldBounds {
             min: Vec2::new(-half_width, -half_height),
             max: Vec2::new(half_width, half_height),
             friction: DEFAULT_FRICTION,
-            bounce_factor: 2.0,
+            bounce_factor: DEFAULT_BOUNCE_FACTOR,
         }
     }
 
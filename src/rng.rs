@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+
+/// Small seeded random number generator (SplitMix64).
+/// Only integer arithmetic, so a seed produces the same sequence on every platform.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Uniform float in `0.0..1.0` built from the top 24 bits
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniform float in `min..max`
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Uniform integer in `0..bound`, `bound` must be non-zero
+    pub fn below(&mut self, bound: u32) -> u32 {
+        ((self.next_u32() as u64 * bound as u64) >> 32) as u32
+    }
+
+    /// Uniform point inside the rectangle `min..max`
+    pub fn point_in(&mut self, min: Vec2, max: Vec2) -> Vec2 {
+        Vec2::new(self.range_f32(min.x, max.x), self.range_f32(min.y, max.y))
+    }
+
+    /// `true` with the given probability
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+}
+
+/// Seed of the gameplay generator when none is given
+pub const DEFAULT_SEED: u64 = 0x5EED;
+
+/// Shared gameplay random number generator
+#[derive(Resource, Debug, Clone)]
+pub struct GameRng(pub SeededRng);
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self(SeededRng::new(seed))
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::from_seed(DEFAULT_SEED)
+    }
+}
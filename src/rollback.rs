@@ -0,0 +1,901 @@
+use crate::collectibles::{collectible_bundle, Collectible, Score};
+use crate::components::{
+    Acceleration, FireIntent, Friction, LinearVelocity, Mass, MovementIntent, Player,
+};
+use crate::enemy::{enemy_bundle, Enemy, Steering};
+use crate::entity_relations::{despawn_related, Owner, Target};
+use crate::game_state::GameState;
+use crate::health::{Health, PendingRespawns};
+use crate::input::{handle_input, ControlledBy};
+use crate::level::player_bundle;
+use crate::modifiers::StatModifiers;
+use crate::particles::Particle;
+use crate::power_ups::{power_up_bundle, PowerUp};
+use crate::projectiles::{handle_fire_input, projectile_bundle, Projectile, Weapon};
+use crate::race::RaceProgress;
+use crate::rng::GameRng;
+use crate::stamina::Stamina;
+use crate::systems::PhysicsSet;
+use crate::transport::{NetError, Transport};
+use bevy::ecs::world::EntityMut;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::transport::UdpTransport;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::SocketAddr;
+
+/// Peers in a rollback session, one player each
+pub const ROLLBACK_PLAYERS: u8 = 2;
+
+/// Ticks between reading a local input and simulating it
+pub const DEFAULT_INPUT_DELAY: u32 = 2;
+
+/// Which peer's input drives this player
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RollbackPlayer(pub u8);
+
+/// One player's input for one tick, quantized so every peer simulates exactly
+/// the same values. Aim points are not sent, networked players fire the way
+/// they are heading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, Serialize, Deserialize)]
+pub struct NetInput {
+    pub x: i8,
+    pub y: i8,
+    pub buttons: u8,
+}
+
+impl NetInput {
+    pub const BOOST: u8 = 1;
+    pub const MOUSE_BOOST: u8 = 2;
+    pub const FIRE: u8 = 4;
+
+    pub fn from_intent(intent: &MovementIntent, fire: Option<&FireIntent>) -> Self {
+        let direction = intent.direction.clamp_length_max(1.0);
+        let quantize = |value: f32| (value * 127.0).round() as i8;
+        let mut buttons = 0;
+        if intent.boost {
+            buttons |= Self::BOOST;
+        }
+        if intent.mouse_boost {
+            buttons |= Self::MOUSE_BOOST;
+        }
+        if fire.is_some_and(|fire| fire.firing) {
+            buttons |= Self::FIRE;
+        }
+        Self {
+            x: quantize(direction.x),
+            y: quantize(direction.y),
+            buttons,
+        }
+    }
+
+    pub fn intent(self) -> MovementIntent {
+        MovementIntent {
+            direction: (Vec2::new(self.x as f32, self.y as f32) / 127.0).clamp_length_max(1.0),
+            boost: self.buttons & Self::BOOST != 0,
+            mouse_boost: self.buttons & Self::MOUSE_BOOST != 0,
+        }
+    }
+
+    pub fn fire(self) -> FireIntent {
+        FireIntent {
+            firing: self.buttons & Self::FIRE != 0,
+            aim_at: None,
+        }
+    }
+}
+
+/// Inputs a peer sends every tick: all local inputs the other side has not
+/// acknowledged yet, so a lost packet is covered by the next one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputPacket {
+    /// Frame of the first input
+    pub start_frame: u32,
+    /// The sender has every input of the receiver before this frame
+    pub ack: u32,
+    pub inputs: Vec<NetInput>,
+}
+
+impl InputPacket {
+    const HEADER: usize = 10;
+
+    /// Little endian `start_frame`, `ack` and input count, then three bytes per input
+    pub fn encode(&self) -> Vec<u8> {
+        let count = self.inputs.len().min(u16::MAX as usize);
+        let mut bytes = Vec::with_capacity(Self::HEADER + count * 3);
+        bytes.extend_from_slice(&self.start_frame.to_le_bytes());
+        bytes.extend_from_slice(&self.ack.to_le_bytes());
+        bytes.extend_from_slice(&(count as u16).to_le_bytes());
+        for input in &self.inputs[..count] {
+            bytes.extend_from_slice(&[input.x as u8, input.y as u8, input.buttons]);
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, NetError> {
+        if bytes.len() < Self::HEADER {
+            return Err(NetError::Malformed(format!(
+                "{} bytes is shorter than the header",
+                bytes.len()
+            )));
+        }
+        let word = |at: usize| {
+            u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+        };
+        let count = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        let body = &bytes[Self::HEADER..];
+        if body.len() != count * 3 {
+            return Err(NetError::Malformed(format!(
+                "expected {} inputs, got {} bytes",
+                count,
+                body.len()
+            )));
+        }
+        Ok(Self {
+            start_frame: word(0),
+            ack: word(4),
+            inputs: body
+                .chunks_exact(3)
+                .map(|chunk| NetInput {
+                    x: chunk[0] as i8,
+                    y: chunk[1] as i8,
+                    buttons: chunk[2],
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Simulation state of one body
+#[derive(Debug, Clone)]
+pub struct RollbackBody {
+    pub entity: Entity,
+    pub transform: Transform,
+    pub velocity: LinearVelocity,
+    pub acceleration: Option<Acceleration>,
+    pub friction: Option<Friction>,
+    pub mass: Option<Mass>,
+    pub intent: Option<MovementIntent>,
+    pub fire: Option<FireIntent>,
+    pub stamina: Option<Stamina>,
+    pub health: Option<Health>,
+    pub weapon: Option<Weapon>,
+    pub modifiers: Option<StatModifiers>,
+    pub steering: Option<Steering>,
+    pub race: Option<RaceProgress>,
+    pub target: Option<Target>,
+    pub player: Option<RollbackPlayer>,
+}
+
+/// What an entity the simulation can despawn is rebuilt as
+#[derive(Debug, Clone, Copy)]
+pub enum RollbackKind {
+    Player,
+    Enemy,
+    Collectible(Collectible),
+    PowerUp(PowerUp),
+}
+
+/// An entity the simulation can despawn, with enough to rebuild it under the
+/// same id. Bodies get the rest of their state back from `RollbackBody`.
+#[derive(Debug, Clone)]
+pub struct RollbackEntity {
+    pub entity: Entity,
+    pub kind: RollbackKind,
+    pub transform: Transform,
+}
+
+#[derive(Debug, Clone)]
+pub struct RollbackProjectile {
+    /// `None` for shots whose shooter is gone
+    pub owner: Option<Entity>,
+    pub position: Vec2,
+    pub projectile: Projectile,
+}
+
+/// Everything the fixed timestep changes, saved before simulating `frame`.
+/// Bodies are restored in place. Players, enemies, collectibles and power-ups
+/// spawned inside the rollback window are despawned and those it despawned
+/// are rebuilt under their old ids. Projectiles are rebuilt.
+#[derive(Debug, Clone)]
+pub struct RollbackSnapshot {
+    pub frame: u32,
+    pub bodies: Vec<RollbackBody>,
+    pub entities: Vec<RollbackEntity>,
+    pub projectiles: Vec<RollbackProjectile>,
+    pub rng: Option<GameRng>,
+    pub score: Option<Score>,
+    pub respawns: Option<PendingRespawns>,
+}
+
+/// Components saved for every body, see `RollbackBody`
+pub type RollbackBodyQuery = (
+    Entity,
+    &'static Transform,
+    &'static LinearVelocity,
+    Option<&'static Acceleration>,
+    Option<&'static Friction>,
+    Option<&'static Mass>,
+    Option<&'static MovementIntent>,
+    Option<&'static FireIntent>,
+    Option<&'static Stamina>,
+    Option<&'static Health>,
+    Option<&'static Weapon>,
+    Option<&'static StatModifiers>,
+    Option<&'static Steering>,
+    (
+        Option<&'static RaceProgress>,
+        Option<&'static Target>,
+        Option<&'static RollbackPlayer>,
+    ),
+);
+
+/// Entities the simulation despawns and rollback rebuilds
+type RollbackKindFilter = Or<(With<Player>, With<Enemy>, With<Collectible>, With<PowerUp>)>;
+
+impl RollbackSnapshot {
+    pub fn capture(world: &mut World, frame: u32) -> Self {
+        let bodies = world
+            .query::<RollbackBodyQuery>()
+            .iter(world)
+            .map(
+                |(
+                    entity,
+                    transform,
+                    velocity,
+                    acceleration,
+                    friction,
+                    mass,
+                    intent,
+                    fire,
+                    stamina,
+                    health,
+                    weapon,
+                    modifiers,
+                    steering,
+                    (race, target, player),
+                )| RollbackBody {
+                    entity,
+                    transform: *transform,
+                    velocity: *velocity,
+                    acceleration: acceleration.copied(),
+                    friction: friction.copied(),
+                    mass: mass.copied(),
+                    intent: intent.copied(),
+                    fire: fire.copied(),
+                    stamina: stamina.cloned(),
+                    health: health.cloned(),
+                    weapon: weapon.cloned(),
+                    modifiers: modifiers.cloned(),
+                    steering: steering.cloned(),
+                    race: race.cloned(),
+                    target: target.copied(),
+                    player: player.copied(),
+                },
+            )
+            .collect();
+        let entities = world
+            .query_filtered::<(
+                Entity,
+                &Transform,
+                Option<&Player>,
+                Option<&Collectible>,
+                Option<&PowerUp>,
+            ), RollbackKindFilter>()
+            .iter(world)
+            .map(|(entity, transform, player, collectible, power_up)| {
+                let kind = match (player, collectible, power_up) {
+                    (Some(_), ..) => RollbackKind::Player,
+                    (_, Some(collectible), _) => RollbackKind::Collectible(*collectible),
+                    (.., Some(power_up)) => RollbackKind::PowerUp(*power_up),
+                    _ => RollbackKind::Enemy,
+                };
+                RollbackEntity {
+                    entity,
+                    kind,
+                    transform: *transform,
+                }
+            })
+            .collect();
+        let projectiles = world
+            .query::<(&Transform, &Projectile, Option<&Owner>)>()
+            .iter(world)
+            .map(|(transform, projectile, owner)| RollbackProjectile {
+                owner: owner.map(|owner| owner.0),
+                position: transform.translation.truncate(),
+                projectile: projectile.clone(),
+            })
+            .collect();
+
+        Self {
+            frame,
+            bodies,
+            entities,
+            projectiles,
+            rng: world.get_resource::<GameRng>().cloned(),
+            score: world.get_resource::<Score>().cloned(),
+            respawns: world.get_resource::<PendingRespawns>().cloned(),
+        }
+    }
+
+    pub fn restore(&self, world: &mut World) {
+        // Whatever the window spawned goes, projectiles are all rebuilt below
+        let saved: HashSet<Entity> = self.entities.iter().map(|saved| saved.entity).collect();
+        let stale: Vec<Entity> = world
+            .query_filtered::<Entity, Or<(RollbackKindFilter, With<Projectile>)>>()
+            .iter(world)
+            .filter(|entity| !saved.contains(entity))
+            .collect();
+        for entity in stale {
+            despawn_related(world, entity);
+        }
+        for saved in &self.entities {
+            if world.get_entity(saved.entity).is_none() {
+                rebuild_entity(world, saved);
+            }
+        }
+
+        for body in &self.bodies {
+            let Some(mut entity) = world.get_entity_mut(body.entity) else {
+                continue;
+            };
+            entity.insert((body.transform, body.velocity));
+            restore_component(&mut entity, &body.acceleration);
+            restore_component(&mut entity, &body.friction);
+            restore_component(&mut entity, &body.mass);
+            restore_component(&mut entity, &body.intent);
+            restore_component(&mut entity, &body.fire);
+            restore_component(&mut entity, &body.stamina);
+            restore_component(&mut entity, &body.health);
+            restore_component(&mut entity, &body.weapon);
+            restore_component(&mut entity, &body.modifiers);
+            restore_component(&mut entity, &body.steering);
+            restore_component(&mut entity, &body.player);
+            // A finished run or a retargeted chase is undone too
+            match &body.race {
+                Some(race) => entity.insert(race.clone()),
+                None => entity.remove::<RaceProgress>(),
+            };
+            match body.target {
+                Some(target) => entity.insert(target),
+                None => entity.remove::<Target>(),
+            };
+        }
+
+        for state in &self.projectiles {
+            let mut shot = world.spawn(projectile_bundle(state.position, state.projectile.clone()));
+            if let Some(owner) = state.owner {
+                shot.insert(Owner(owner));
+            }
+        }
+
+        if let Some(rng) = &self.rng {
+            world.insert_resource(rng.clone());
+        }
+        if let Some(score) = &self.score {
+            world.insert_resource(score.clone());
+        }
+        if let Some(respawns) = &self.respawns {
+            world.insert_resource(respawns.clone());
+        }
+    }
+
+    /// Hash of positions, velocities, health, race runs, pickups,
+    /// projectiles, the score and pending respawns that does not depend on
+    /// entity ids or order, so two peers can compare their worlds
+    pub fn checksum(&self) -> u64 {
+        let race_values = |race: &Option<RaceProgress>| {
+            race.as_ref().map_or([0.0; 4], |race| {
+                [
+                    race.ticks as f32,
+                    race.next as f32,
+                    race.lap as f32,
+                    race.finished as u8 as f32,
+                ]
+            })
+        };
+        let bodies = self.bodies.iter().map(|body| {
+            let translation = body.transform.translation;
+            let health = body.health.as_ref().map_or(0.0, |health| health.current);
+            let [ticks, next, lap, finished] = race_values(&body.race);
+            fnv1a(&[
+                translation.x,
+                translation.y,
+                body.velocity.0.x,
+                body.velocity.0.y,
+                health,
+                ticks,
+                next,
+                lap,
+                finished,
+            ])
+        });
+        let pickups = self.entities.iter().filter_map(|saved| {
+            let (kind, value) = match saved.kind {
+                RollbackKind::Collectible(collectible) => (1.0, collectible.value as f32),
+                RollbackKind::PowerUp(power_up) => (2.0, power_up.kind as u8 as f32),
+                RollbackKind::Player | RollbackKind::Enemy => return None,
+            };
+            let translation = saved.transform.translation;
+            Some(fnv1a(&[kind, value, translation.x, translation.y]))
+        });
+        let score = self.score.iter().map(|score| {
+            fnv1a(&[
+                score.points as f32,
+                score.combo as f32,
+                score.best_combo as f32,
+                score.combo_time_left,
+            ])
+        });
+        let respawns = self.respawns.iter().flat_map(|respawns| {
+            respawns.entries.iter().map(|entry| {
+                let [ticks, next, lap, finished] = race_values(&entry.race);
+                fnv1a(&[entry.time_left, ticks, next, lap, finished])
+            })
+        });
+        let projectiles = self.projectiles.iter().map(|state| {
+            fnv1a(&[
+                state.position.x,
+                state.position.y,
+                state.projectile.velocity.x,
+                state.projectile.velocity.y,
+            ])
+        });
+        bodies
+            .chain(pickups)
+            .chain(projectiles)
+            .chain(score)
+            .chain(respawns)
+            .fold(0u64, |sum, hash| sum.wrapping_add(hash))
+    }
+}
+
+/// Spawn `saved` again under its old id. A particle may have taken the id in
+/// the meantime, it is cosmetic and makes room.
+fn rebuild_entity(world: &mut World, saved: &RollbackEntity) {
+    let occupant = world.entities().resolve_from_id(saved.entity.index());
+    if let Some(occupant) = occupant.filter(|occupant| *occupant != saved.entity) {
+        if world.get::<Particle>(occupant).is_some() {
+            world.despawn(occupant);
+        }
+    }
+    let Some(mut entity) = world.get_or_spawn(saved.entity) else {
+        warn!(
+            "Cannot rebuild {:?} for rollback, its id is taken",
+            saved.entity
+        );
+        return;
+    };
+
+    let position = saved.transform.translation.truncate();
+    match saved.kind {
+        RollbackKind::Player => entity.insert(player_bundle(position)),
+        RollbackKind::Enemy => entity.insert(enemy_bundle(position, Steering::default())),
+        RollbackKind::Collectible(collectible) => {
+            entity.insert(collectible_bundle(position, collectible))
+        }
+        RollbackKind::PowerUp(power_up) => entity.insert(power_up_bundle(position, power_up)),
+    };
+    entity.insert(saved.transform);
+}
+
+fn restore_component<T: Component + Clone>(entity: &mut EntityMut, value: &Option<T>) {
+    if let Some(value) = value {
+        entity.insert(value.clone());
+    }
+}
+
+fn fnv1a(values: &[f32]) -> u64 {
+    values
+        .iter()
+        .flat_map(|value| value.to_bits().to_le_bytes())
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+/// Peer-to-peer session that only exchanges inputs. Every peer runs the whole
+/// simulation: remote inputs that have not arrived are predicted by repeating
+/// the last one, and when the real input differs the world is restored to the
+/// snapshot of that tick and resimulated with the corrected inputs.
+///
+/// While a session exists it owns `FixedUpdate`: it runs the schedule itself,
+/// once per tick and again for every resimulated tick.
+#[derive(Resource)]
+pub struct RollbackSession {
+    pub local_player: u8,
+    /// Local inputs are simulated this many ticks after they are read, which
+    /// hides that much latency without any rollback. Both peers must agree.
+    input_delay: u32,
+    /// Furthest the simulation may run ahead of the last confirmed remote input
+    pub max_prediction: u32,
+    /// Next tick to simulate
+    frame: u32,
+    local_inputs: BTreeMap<u32, NetInput>,
+    remote_inputs: BTreeMap<u32, NetInput>,
+    /// Every remote input before this frame has arrived
+    confirmed: u32,
+    /// The remote peer has every local input before this frame
+    remote_ack: u32,
+    /// Remote inputs guessed for ticks already simulated
+    predictions: BTreeMap<u32, NetInput>,
+    snapshots: VecDeque<RollbackSnapshot>,
+    /// Real time not simulated yet
+    accumulator: f32,
+    pub rollbacks: u32,
+    pub resimulated_frames: u32,
+    transport: Box<dyn Transport>,
+}
+
+impl RollbackSession {
+    pub fn new(local_player: u8, input_delay: u32, transport: impl Transport + 'static) -> Self {
+        // Nobody has input for the first ticks, both peers simulate them idle
+        let idle: BTreeMap<u32, NetInput> = (0..input_delay)
+            .map(|frame| (frame, NetInput::default()))
+            .collect();
+        Self {
+            local_player,
+            input_delay,
+            max_prediction: 8,
+            frame: 0,
+            local_inputs: idle.clone(),
+            remote_inputs: idle,
+            confirmed: input_delay,
+            remote_ack: 0,
+            predictions: BTreeMap::new(),
+            snapshots: VecDeque::new(),
+            accumulator: 0.0,
+            rollbacks: 0,
+            resimulated_frames: 0,
+            transport: Box::new(transport),
+        }
+    }
+
+    pub fn remote_player(&self) -> u8 {
+        (self.local_player + 1) % ROLLBACK_PLAYERS
+    }
+
+    /// Next tick to simulate
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Every remote input before this frame has arrived
+    pub fn confirmed_frame(&self) -> u32 {
+        self.confirmed
+    }
+
+    pub fn input_delay(&self) -> u32 {
+        self.input_delay
+    }
+
+    /// Whether the next tick stays inside the prediction window
+    pub fn can_advance(&self) -> bool {
+        self.frame < self.confirmed + self.max_prediction
+    }
+
+    /// Input of `player` on `frame` and whether it is a prediction
+    pub fn input(&self, player: u8, frame: u32) -> (NetInput, bool) {
+        if player == self.local_player {
+            return (
+                self.local_inputs.get(&frame).copied().unwrap_or_default(),
+                false,
+            );
+        }
+        match self.remote_inputs.get(&frame) {
+            Some(input) => (*input, false),
+            None => (
+                self.remote_inputs
+                    .range(..frame)
+                    .next_back()
+                    .map(|(_, input)| *input)
+                    .unwrap_or_default(),
+                true,
+            ),
+        }
+    }
+
+    /// Read remote inputs and resimulate from the first tick that was predicted wrong
+    pub fn synchronize(&mut self, world: &mut World) {
+        if let Some(frame) = self.receive() {
+            self.rollback(world, frame);
+        }
+    }
+
+    /// Simulate one tick with `local_input` read now. Returns false without
+    /// simulating when the remote peer is too far behind.
+    pub fn advance(&mut self, world: &mut World, local_input: NetInput) -> bool {
+        self.synchronize(world);
+        if !self.can_advance() {
+            self.send();
+            return false;
+        }
+
+        self.local_inputs
+            .insert(self.frame + self.input_delay, local_input);
+        self.send();
+        self.simulate(world, self.frame);
+        self.frame += 1;
+        self.prune();
+        true
+    }
+
+    /// Store remote inputs and return the earliest tick whose prediction was wrong
+    fn receive(&mut self) -> Option<u32> {
+        let mut mispredicted: Option<u32> = None;
+
+        for bytes in self.transport.receive() {
+            let packet = match InputPacket::decode(&bytes) {
+                Ok(packet) => packet,
+                Err(error) => {
+                    warn!("Dropped rollback packet: {}", error);
+                    continue;
+                }
+            };
+            self.remote_ack = self.remote_ack.max(packet.ack);
+
+            for (offset, input) in packet.inputs.iter().enumerate() {
+                let frame = packet.start_frame + offset as u32;
+                if frame < self.confirmed || self.remote_inputs.contains_key(&frame) {
+                    continue;
+                }
+                self.remote_inputs.insert(frame, *input);
+                if self
+                    .predictions
+                    .remove(&frame)
+                    .is_some_and(|predicted| predicted != *input)
+                {
+                    mispredicted = Some(mispredicted.map_or(frame, |first| first.min(frame)));
+                }
+            }
+        }
+
+        while self.remote_inputs.contains_key(&self.confirmed) {
+            self.confirmed += 1;
+        }
+        mispredicted
+    }
+
+    fn send(&mut self) {
+        let inputs: Vec<NetInput> = self
+            .local_inputs
+            .range(self.remote_ack..)
+            .map(|(_, input)| *input)
+            .collect();
+        let packet = InputPacket {
+            start_frame: self.remote_ack,
+            ack: self.confirmed,
+            inputs,
+        };
+        if let Err(error) = self.transport.send(&packet.encode()) {
+            warn!("Could not send rollback inputs: {}", error);
+        }
+    }
+
+    fn simulate(&mut self, world: &mut World, frame: u32) {
+        self.snapshots.retain(|snapshot| snapshot.frame < frame);
+        self.snapshots
+            .push_back(RollbackSnapshot::capture(world, frame));
+
+        let (local, _) = self.input(self.local_player, frame);
+        let (remote, predicted) = self.input(self.remote_player(), frame);
+        if predicted {
+            self.predictions.insert(frame, remote);
+        } else {
+            self.predictions.remove(&frame);
+        }
+
+        let mut players = world.query::<(
+            &RollbackPlayer,
+            &mut MovementIntent,
+            Option<&mut FireIntent>,
+        )>();
+        for (player, mut intent, fire) in players.iter_mut(world) {
+            let input = if player.0 == self.local_player {
+                local
+            } else {
+                remote
+            };
+            *intent = input.intent();
+            if let Some(mut fire) = fire {
+                *fire = input.fire();
+            }
+        }
+
+        world.run_schedule(FixedUpdate);
+    }
+
+    fn rollback(&mut self, world: &mut World, from: u32) {
+        let Some(snapshot) = self
+            .snapshots
+            .iter()
+            .find(|snapshot| snapshot.frame == from)
+            .cloned()
+        else {
+            error!(
+                "No rollback snapshot for tick {}, peers have desynced",
+                from
+            );
+            return;
+        };
+
+        snapshot.restore(world);
+        for frame in from..self.frame {
+            self.simulate(world, frame);
+        }
+        self.rollbacks += 1;
+        self.resimulated_frames += self.frame - from;
+    }
+
+    /// Forget what no rollback or resend can need any more
+    fn prune(&mut self) {
+        let oldest = self.confirmed.min(self.frame);
+        self.snapshots.retain(|snapshot| snapshot.frame >= oldest);
+        let resend = self.remote_ack.min(oldest);
+        self.local_inputs = self.local_inputs.split_off(&resend);
+        // Keep the input before the oldest tick that may still be simulated,
+        // predictions repeat it
+        self.remote_inputs = self.remote_inputs.split_off(&oldest.saturating_sub(1));
+    }
+}
+
+/// Simulate one tick of the session in `world`. Returns false when stalled
+/// waiting for the remote peer or when there is no session.
+pub fn advance_rollback(world: &mut World, local_input: NetInput) -> bool {
+    let Some(mut session) = world.remove_resource::<RollbackSession>() else {
+        return false;
+    };
+    let advanced = session.advance(world, local_input);
+    world.insert_resource(session);
+    advanced
+}
+
+/// Apply remote inputs that arrived without simulating a new tick
+pub fn synchronize_rollback(world: &mut World) {
+    if let Some(mut session) = world.remove_resource::<RollbackSession>() {
+        session.synchronize(world);
+        world.insert_resource(session);
+    }
+}
+
+/// Run condition for `PhysicsSet`. The session leaves the world while it runs
+/// `FixedUpdate`, so the physics only runs on its own when there is no session.
+pub fn no_rollback_session(session: Option<Res<RollbackSession>>) -> bool {
+    session.is_none()
+}
+
+/// Players that have no rollback handle yet
+pub type UnassignedPlayerQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static Transform), (With<Player>, Without<RollbackPlayer>)>;
+
+/// Gives players without a handle the handles nobody holds yet, sorted by
+/// position so both peers agree. A respawned player already carries its old
+/// handle, see `PendingRespawn`.
+/// Each peer drives its player from the whole keyboard, so local bindings are dropped.
+pub fn assign_rollback_players(
+    mut commands: Commands,
+    assigned: Query<&RollbackPlayer>,
+    unassigned: UnassignedPlayerQuery,
+) {
+    let mut free = (0..ROLLBACK_PLAYERS).filter(|handle| !assigned.iter().any(|p| p.0 == *handle));
+    let mut players: Vec<(Entity, Vec2)> = unassigned
+        .iter()
+        .map(|(entity, transform)| (entity, transform.translation.truncate()))
+        .collect();
+    players.sort_by(|(_, a), (_, b)| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+
+    for (entity, _) in players {
+        let Some(handle) = free.next() else {
+            break;
+        };
+        commands
+            .entity(entity)
+            .insert(RollbackPlayer(handle))
+            .remove::<ControlledBy>();
+    }
+}
+
+/// Steps the session in real time, one tick per fixed period, with the local
+/// player's current intent as input
+pub fn run_rollback_session(world: &mut World) {
+    let Some(mut session) = world.remove_resource::<RollbackSession>() else {
+        return;
+    };
+    let period = world.resource::<FixedTime>().period.as_secs_f32();
+    session.accumulator += world.resource::<Time>().delta_seconds();
+
+    let local_input = world
+        .query::<(&RollbackPlayer, &MovementIntent, Option<&FireIntent>)>()
+        .iter(world)
+        .find(|(player, ..)| player.0 == session.local_player)
+        .map(|(_, intent, fire)| NetInput::from_intent(intent, fire))
+        .unwrap_or_default();
+
+    session.synchronize(world);
+    while session.accumulator >= period {
+        if !session.advance(world, local_input) {
+            // Stalled, do not build up a burst of ticks to catch up with
+            session.accumulator = session.accumulator.min(period);
+            break;
+        }
+        session.accumulator -= period;
+    }
+    world.insert_resource(session);
+}
+
+/// Command line setup for a UDP session: `--netplay <local addr> <peer addr> <player>`
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetplayConfig {
+    pub local: SocketAddr,
+    pub peer: SocketAddr,
+    pub player: u8,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl NetplayConfig {
+    /// `None` when `--netplay` is not among `args`
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, NetError> {
+        let Some(flag) = args.iter().position(|arg| arg == "--netplay") else {
+            return Ok(None);
+        };
+        let usage =
+            || NetError::Config("usage: --netplay <local addr> <peer addr> <player>".into());
+        let [local, peer, player] = args.get(flag + 1..flag + 4).ok_or_else(usage)? else {
+            return Err(usage());
+        };
+
+        let address = |value: &String| {
+            value
+                .parse::<SocketAddr>()
+                .map_err(|_| NetError::Config(format!("'{}' is not an address", value)))
+        };
+        let player = player
+            .parse::<u8>()
+            .ok()
+            .filter(|player| *player < ROLLBACK_PLAYERS)
+            .ok_or_else(|| NetError::Config(format!("player must be 0 or 1, got '{}'", player)))?;
+
+        Ok(Some(Self {
+            local: address(local)?,
+            peer: address(peer)?,
+            player,
+        }))
+    }
+
+    pub fn start(&self) -> Result<RollbackSession, NetError> {
+        let transport = UdpTransport::bind(self.local)?;
+        transport.connect(self.peer)?;
+        Ok(RollbackSession::new(
+            self.player,
+            DEFAULT_INPUT_DELAY,
+            transport,
+        ))
+    }
+}
+
+/// Bundle rollback netplay for easy registration. Nothing runs until a
+/// `RollbackSession` is inserted.
+pub struct RollbackSystems;
+
+impl RollbackSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.configure_set(FixedUpdate, PhysicsSet.run_if(no_rollback_session))
+            .add_systems(
+                Update,
+                (
+                    assign_rollback_players,
+                    apply_deferred,
+                    run_rollback_session,
+                )
+                    .chain()
+                    .after(handle_input)
+                    .after(handle_fire_input)
+                    .run_if(resource_exists::<RollbackSession>())
+                    .run_if(in_state(GameState::Playing)),
+            )
+    }
+}
@@ -0,0 +1,559 @@
+use crate::collectibles::{spawn_collectible, Collectible, Score};
+use crate::components::{Acceleration, Friction, LinearVelocity, Obstacle, Player};
+use crate::enemy::{spawn_enemy, Enemy, Steering, SteeringBehavior};
+use crate::entity_relations::{
+    despawn_related, ChildOf, Children, Group, GroupMember, Owner, Parent, Target,
+};
+use crate::game_state::{GameState, GameplayEntity, GameplayReset};
+use crate::health::Health;
+use crate::input::ControlledBy;
+use crate::level::{spawn_obstacle, spawn_player};
+use crate::power_ups::{spawn_power_up, PowerUp, PowerUpKind};
+use crate::projectiles::Projectile;
+use crate::race::{spawn_checkpoint, Checkpoint};
+use crate::resources::WorldBounds;
+use bevy::ecs::system::CommandQueue;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Format version written by this build. Bump it whenever `SaveFile` changes
+/// shape and register a migration for the previous version.
+pub const SAVE_VERSION: u32 = 2;
+
+/// Where F6 saves and F7 loads
+pub const QUICKSAVE_PATH: &str = "saves/quicksave.ron";
+
+/// What an entity was, so loading can rebuild it with its sprite and behavior
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SavedKind {
+    Player,
+    Enemy(SteeringBehavior),
+    Collectible {
+        value: u32,
+        radius: f32,
+    },
+    Obstacle {
+        half_size: Vec2,
+    },
+    PowerUp(PowerUpKind),
+    Checkpoint {
+        index: usize,
+        radius: f32,
+    },
+    /// Only the saved components are restored
+    Other,
+}
+
+/// One gameplay entity. Entity references hold the saving world's entity bits
+/// and are remapped to the new entities on load.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntitySnapshot {
+    pub id: u64,
+    pub kind: SavedKind,
+    pub transform: Transform,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub velocity: Option<Vec2>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acceleration: Option<Vec2>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub friction: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<Health>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub controlled_by: Option<ControlledBy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub child_of: Option<u64>,
+    /// Loading marks an entity with children as a `Parent`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<u64>,
+    /// Groups are referenced by id rather than entity, so membership is stored as is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_member: Option<GroupMember>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<Group>,
+}
+
+/// Versioned snapshot of a play session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub bounds: WorldBounds,
+    pub score: Score,
+    pub entities: Vec<EntitySnapshot>,
+}
+
+/// Only the version, read first to decide whether a migration is needed
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+/// Why a save could not be written or read
+#[derive(Debug)]
+pub enum SaveError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Serialize(String),
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// Written by a newer build, or an older version without a registered migration
+    UnsupportedVersion {
+        path: PathBuf,
+        found: u32,
+    },
+    Migration {
+        path: PathBuf,
+        from: u32,
+        message: String,
+    },
+    Invalid {
+        path: PathBuf,
+        /// Path to the offending field, such as `bounds.bounce_factor`
+        field: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            SaveError::Serialize(message) => write!(f, "could not serialize save: {}", message),
+            SaveError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            SaveError::UnsupportedVersion { path, found } => write!(
+                f,
+                "{}: save version {} is not supported (current is {})",
+                path.display(),
+                found,
+                SAVE_VERSION
+            ),
+            SaveError::Migration {
+                path,
+                from,
+                message,
+            } => write!(
+                f,
+                "{}: migrating from version {} failed: {}",
+                path.display(),
+                from,
+                message
+            ),
+            SaveError::Invalid {
+                path,
+                field,
+                message,
+            } => write!(f, "{}: `{}` {}", path.display(), field, message),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SaveError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the source of a save written by an older version and returns it in the current format
+pub type MigrationFn = fn(&str) -> Result<SaveFile, String>;
+
+/// Migration hooks for older save versions, keyed by the version they read.
+/// Migrations for this build's own older versions are registered by default.
+#[derive(Resource, Debug)]
+pub struct SaveMigrations {
+    migrations: HashMap<u32, MigrationFn>,
+}
+
+impl Default for SaveMigrations {
+    fn default() -> Self {
+        let mut migrations = Self {
+            migrations: HashMap::new(),
+        };
+        migrations.register(1, migrate_v1);
+        migrations
+    }
+}
+
+/// Version 1 had no power-up or checkpoint kinds and did not save health or
+/// input bindings. Its entities read as they are, the rebuilt ones start with
+/// full health and no binding. The `parent` reference it kept on children is
+/// ignored, loading marks parents from their `children`.
+pub fn migrate_v1(source: &str) -> Result<SaveFile, String> {
+    ron::from_str(source).map_err(|error| error.to_string())
+}
+
+impl SaveMigrations {
+    pub fn register(&mut self, from_version: u32, migration: MigrationFn) -> &mut Self {
+        self.migrations.insert(from_version, migration);
+        self
+    }
+}
+
+fn parse_error(path: &Path, error: ron::error::SpannedError) -> SaveError {
+    SaveError::Parse {
+        path: path.to_path_buf(),
+        line: error.position.line,
+        column: error.position.col,
+        message: error.code.to_string(),
+    }
+}
+
+impl SaveFile {
+    pub fn to_ron(&self) -> Result<String, SaveError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| SaveError::Serialize(error.to_string()))
+    }
+
+    /// Parse a save, running the registered migration when it was written by an older version.
+    /// `path` is only used in error messages.
+    pub fn from_ron(
+        source: &str,
+        path: impl AsRef<Path>,
+        migrations: &SaveMigrations,
+    ) -> Result<Self, SaveError> {
+        let path = path.as_ref();
+        let save = Self::parse(source, path, migrations)?;
+        save.validate(path)?;
+        Ok(save)
+    }
+
+    fn parse(source: &str, path: &Path, migrations: &SaveMigrations) -> Result<Self, SaveError> {
+        let header: SaveHeader = ron::from_str(source).map_err(|error| parse_error(path, error))?;
+
+        if header.version == SAVE_VERSION {
+            return ron::from_str(source).map_err(|error| parse_error(path, error));
+        }
+
+        let unsupported = SaveError::UnsupportedVersion {
+            path: path.to_path_buf(),
+            found: header.version,
+        };
+        if header.version > SAVE_VERSION {
+            return Err(unsupported);
+        }
+        let Some(migration) = migrations.migrations.get(&header.version) else {
+            return Err(unsupported);
+        };
+
+        let mut save = migration(source).map_err(|message| SaveError::Migration {
+            path: path.to_path_buf(),
+            from: header.version,
+            message,
+        })?;
+        save.version = SAVE_VERSION;
+        Ok(save)
+    }
+
+    /// Reject values the game would misbehave with, the same way levels are checked
+    fn validate(&self, path: &Path) -> Result<(), SaveError> {
+        if !(0.0..=1.0).contains(&self.bounds.bounce_factor) {
+            return Err(SaveError::Invalid {
+                path: path.to_path_buf(),
+                field: "bounds.bounce_factor".into(),
+                message: "must be in 0.0..=1.0".into(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        let io_error = |source| SaveError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        std::fs::write(path, self.to_ron()?).map_err(io_error)
+    }
+
+    pub fn read(path: impl AsRef<Path>, migrations: &SaveMigrations) -> Result<Self, SaveError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| SaveError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_ron(&source, path, migrations)
+    }
+}
+
+/// Capture every `GameplayEntity` plus the score and world bounds. Shots in
+/// flight are left out, they would be gone a moment after loading anyway.
+pub fn capture_snapshot(world: &mut World) -> SaveFile {
+    let mut entities: Vec<Entity> = world
+        .query_filtered::<Entity, (With<GameplayEntity>, Without<Projectile>)>()
+        .iter(world)
+        .collect();
+    entities.sort();
+
+    let snapshots = entities
+        .into_iter()
+        .map(|entity| {
+            let entity_ref = world.entity(entity);
+            let kind = if entity_ref.contains::<Player>() {
+                SavedKind::Player
+            } else if let (true, Some(steering)) =
+                (entity_ref.contains::<Enemy>(), entity_ref.get::<Steering>())
+            {
+                SavedKind::Enemy(steering.behavior)
+            } else if let Some(collectible) = entity_ref.get::<Collectible>() {
+                SavedKind::Collectible {
+                    value: collectible.value,
+                    radius: collectible.radius,
+                }
+            } else if let Some(obstacle) = entity_ref.get::<Obstacle>() {
+                SavedKind::Obstacle {
+                    half_size: obstacle.half_size,
+                }
+            } else if let Some(power_up) = entity_ref.get::<PowerUp>() {
+                SavedKind::PowerUp(power_up.kind)
+            } else if let Some(checkpoint) = entity_ref.get::<Checkpoint>() {
+                SavedKind::Checkpoint {
+                    index: checkpoint.index,
+                    radius: checkpoint.radius,
+                }
+            } else {
+                SavedKind::Other
+            };
+
+            EntitySnapshot {
+                id: entity.to_bits(),
+                kind,
+                transform: entity_ref.get::<Transform>().copied().unwrap_or_default(),
+                velocity: entity_ref.get::<LinearVelocity>().map(|v| v.0),
+                acceleration: entity_ref.get::<Acceleration>().map(|a| a.0),
+                friction: entity_ref.get::<Friction>().map(|f| f.0),
+                health: entity_ref.get::<Health>().cloned(),
+                controlled_by: entity_ref.get::<ControlledBy>().copied(),
+                child_of: entity_ref.get::<ChildOf>().map(|r| r.0.to_bits()),
+                children: entity_ref
+                    .get::<Children>()
+                    .map(|children| children.0.iter().map(|e| e.to_bits()).collect())
+                    .unwrap_or_default(),
+                target: entity_ref.get::<Target>().map(|r| r.0.to_bits()),
+                owner: entity_ref.get::<Owner>().map(|r| r.0.to_bits()),
+                group_member: entity_ref.get::<GroupMember>().cloned(),
+                group: entity_ref.get::<Group>().cloned(),
+            }
+        })
+        .collect();
+
+    SaveFile {
+        version: SAVE_VERSION,
+        bounds: world
+            .get_resource::<WorldBounds>()
+            .cloned()
+            .unwrap_or_default(),
+        score: world.get_resource::<Score>().cloned().unwrap_or_default(),
+        entities: snapshots,
+    }
+}
+
+/// Result of restoring a save
+#[derive(Debug, Default)]
+pub struct RestoredSnapshot {
+    /// Saved entity id to the entity spawned for it
+    pub entities: HashMap<u64, Entity>,
+    /// `(saved entity, saved reference)` pairs pointing at entities that were not in the save
+    pub dropped_references: Vec<(u64, u64)>,
+}
+
+/// Spawn the saved entities into `world`, remapping every entity reference,
+/// and restore the score and world bounds
+pub fn restore_snapshot(world: &mut World, save: &SaveFile) -> RestoredSnapshot {
+    let mut restored = RestoredSnapshot::default();
+
+    // Rebuild each entity with the same helpers the level uses, so it gets its sprite
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, world);
+    for snapshot in save.entities.iter() {
+        let position = snapshot.transform.translation.truncate();
+        let entity = match &snapshot.kind {
+            SavedKind::Player => spawn_player(&mut commands, position),
+            SavedKind::Enemy(behavior) => {
+                spawn_enemy(&mut commands, position, Steering::new(*behavior), None)
+            }
+            SavedKind::Collectible { value, radius } => spawn_collectible(
+                &mut commands,
+                position,
+                Collectible {
+                    value: *value,
+                    radius: *radius,
+                },
+            ),
+            SavedKind::Obstacle { half_size } => spawn_obstacle(
+                &mut commands,
+                position,
+                Obstacle {
+                    half_size: *half_size,
+                },
+            ),
+            SavedKind::PowerUp(kind) => {
+                spawn_power_up(&mut commands, position, PowerUp::new(*kind))
+            }
+            SavedKind::Checkpoint { index, radius } => spawn_checkpoint(
+                &mut commands,
+                position,
+                Checkpoint {
+                    index: *index,
+                    radius: *radius,
+                },
+            ),
+            SavedKind::Other => commands.spawn(GameplayEntity).id(),
+        };
+        restored.entities.insert(snapshot.id, entity);
+    }
+    queue.apply(world);
+
+    for snapshot in save.entities.iter() {
+        let entity = restored.entities[&snapshot.id];
+        let mut remap = |reference: u64| {
+            let mapped = restored.entities.get(&reference).copied();
+            if mapped.is_none() {
+                restored.dropped_references.push((snapshot.id, reference));
+            }
+            mapped
+        };
+
+        let child_of = snapshot.child_of.and_then(&mut remap);
+        let target = snapshot.target.and_then(&mut remap);
+        let owner = snapshot.owner.and_then(&mut remap);
+        let children: Vec<Entity> = snapshot
+            .children
+            .iter()
+            .filter_map(|&child| remap(child))
+            .collect();
+
+        let mut entity_mut = world.entity_mut(entity);
+        entity_mut.insert(snapshot.transform);
+        if let Some(velocity) = snapshot.velocity {
+            entity_mut.insert(LinearVelocity(velocity));
+        }
+        if let Some(acceleration) = snapshot.acceleration {
+            entity_mut.insert(Acceleration(acceleration));
+        }
+        if let Some(friction) = snapshot.friction {
+            entity_mut.insert(Friction(friction));
+        }
+        if let Some(health) = snapshot.health.clone() {
+            entity_mut.insert(health);
+        }
+        if let Some(controlled_by) = snapshot.controlled_by {
+            entity_mut.insert(controlled_by);
+        }
+        if let Some(child_of) = child_of {
+            entity_mut.insert(ChildOf(child_of));
+        }
+        if !children.is_empty() {
+            entity_mut.insert((Children(children), Parent));
+        }
+        if let Some(target) = target {
+            entity_mut.insert(Target(target));
+        }
+        if let Some(owner) = owner {
+            entity_mut.insert(Owner(owner));
+        }
+        if let Some(group_member) = snapshot.group_member.clone() {
+            entity_mut.insert(group_member);
+        }
+        if let Some(group) = snapshot.group.clone() {
+            entity_mut.insert(group);
+        }
+    }
+
+    for (entity, reference) in restored.dropped_references.iter() {
+        warn!(
+            "Save entity {} referenced {} which is not in the save, reference dropped",
+            entity, reference
+        );
+    }
+
+    world.insert_resource(save.score.clone());
+    world.insert_resource(save.bounds.clone());
+    restored
+}
+
+/// F6 writes the session to the quicksave file
+pub fn quick_save(world: &mut World) {
+    if !world.resource::<Input<KeyCode>>().just_pressed(KeyCode::F6) {
+        return;
+    }
+
+    let save = capture_snapshot(world);
+    match save.write(QUICKSAVE_PATH) {
+        Ok(()) => info!(
+            "Saved {} entities to {}",
+            save.entities.len(),
+            QUICKSAVE_PATH
+        ),
+        Err(error) => error!("{}", error),
+    }
+}
+
+/// F7 replaces the session with the quicksave file
+pub fn quick_load(world: &mut World) {
+    if !world.resource::<Input<KeyCode>>().just_pressed(KeyCode::F7) {
+        return;
+    }
+
+    let save = match SaveFile::read(QUICKSAVE_PATH, world.resource::<SaveMigrations>()) {
+        Ok(save) => save,
+        Err(error) => {
+            error!("{}", error);
+            return;
+        }
+    };
+
+    let session: Vec<Entity> = world
+        .query_filtered::<Entity, With<GameplayEntity>>()
+        .iter(world)
+        .collect();
+    for entity in session {
+        despawn_related(world, entity);
+    }
+    world.send_event(GameplayReset);
+
+    let restored = restore_snapshot(world, &save);
+    info!(
+        "Loaded {} entities from {}",
+        restored.entities.len(),
+        QUICKSAVE_PATH
+    );
+}
+
+/// Bundle save and load for easy registration
+pub struct SaveSystems;
+
+impl SaveSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.init_resource::<SaveMigrations>()
+            .add_event::<GameplayReset>()
+            .add_systems(
+                Update,
+                (quick_save, quick_load)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+    }
+}
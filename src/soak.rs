@@ -0,0 +1,301 @@
+use crate::bots::PickupQuery;
+use crate::components::LinearVelocity;
+use crate::enemy::Enemy;
+use crate::projectiles::Projectile;
+use crate::replay::{count_ticks, InputRecorder, InputReplay, ReplayError, SimulationTick};
+use crate::resources::WorldBounds;
+use crate::systems::PhysicsStep;
+use bevy::ecs::entity::Entities;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Violations kept in a report, later ones are only counted
+pub const MAX_REPORTED_VIOLATIONS: usize = 100;
+
+/// What a soak run checks after every tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Invariant {
+    /// Positions and velocities are finite numbers
+    FiniteMotion,
+    /// Bodies stay inside `WorldBounds`
+    InsideBounds,
+    /// Entity counts stay within `SoakBudgets`
+    EntityBudget,
+}
+
+/// One broken invariant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    pub tick: u32,
+    pub invariant: Invariant,
+    /// `Entity::to_bits` of the offending entity, if there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity: Option<u64>,
+    pub detail: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tick {}: {:?}", self.tick, self.invariant)?;
+        if let Some(entity) = self.entity {
+            write!(f, " ({:?})", Entity::from_bits(entity))?;
+        }
+        write!(f, ": {}", self.detail)
+    }
+}
+
+/// Most entities of each kind a session should ever hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoakBudgets {
+    pub entities: usize,
+    pub enemies: usize,
+    pub projectiles: usize,
+    /// Collectibles and power-ups
+    pub pickups: usize,
+}
+
+impl Default for SoakBudgets {
+    fn default() -> Self {
+        Self {
+            entities: 1000,
+            enemies: 64,
+            projectiles: 256,
+            pickups: 128,
+        }
+    }
+}
+
+/// Checks the simulation's invariants every tick while it exists
+#[derive(Resource, Debug, Clone, Default)]
+pub struct SoakMonitor {
+    pub budgets: SoakBudgets,
+    /// How far outside the bounds a body may be before it counts
+    pub bounds_tolerance: f32,
+    /// The first `MAX_REPORTED_VIOLATIONS` violations
+    pub violations: Vec<Violation>,
+    /// All violations, including those not kept
+    pub total: usize,
+    /// Set once the report was written
+    pub reported: bool,
+}
+
+impl SoakMonitor {
+    pub fn new(budgets: SoakBudgets) -> Self {
+        Self {
+            budgets,
+            bounds_tolerance: 1.0,
+            ..default()
+        }
+    }
+
+    pub fn failed(&self) -> bool {
+        self.total > 0
+    }
+
+    fn report(&mut self, violation: Violation) {
+        self.total += 1;
+        if self.violations.len() < MAX_REPORTED_VIOLATIONS {
+            self.violations.push(violation);
+        }
+    }
+
+    fn check_budget(&mut self, tick: u32, name: &str, count: usize, budget: usize) {
+        if count > budget {
+            self.report(Violation {
+                tick,
+                invariant: Invariant::EntityBudget,
+                entity: None,
+                detail: format!("{} {} over a budget of {}", count, name, budget),
+            });
+        }
+    }
+}
+
+/// Bodies moved by the physics step
+pub type SoakBodyQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static Transform, &'static LinearVelocity)>;
+
+/// Checks the tick that just ran, before the tick counter advances
+pub fn check_invariants(
+    mut monitor: ResMut<SoakMonitor>,
+    tick: Res<SimulationTick>,
+    bounds: Res<WorldBounds>,
+    entities: &Entities,
+    bodies: SoakBodyQuery,
+    projectiles: Query<(Entity, &Transform, &Projectile)>,
+    (enemies, pickups): (Query<(), With<Enemy>>, PickupQuery),
+) {
+    let tick = tick.0;
+    let tolerance = Vec2::splat(monitor.bounds_tolerance);
+    let motions = bodies
+        .iter()
+        .map(|(entity, transform, velocity)| (entity, transform, velocity.0, true))
+        .chain(projectiles.iter().map(|(entity, transform, projectile)| {
+            (entity, transform, projectile.velocity, false)
+        }));
+    for (entity, transform, velocity, bounded) in motions {
+        let position = transform.translation.truncate();
+        if !position.is_finite() || !velocity.is_finite() {
+            monitor.report(Violation {
+                tick,
+                invariant: Invariant::FiniteMotion,
+                entity: Some(entity.to_bits()),
+                detail: format!("position {} velocity {}", position, velocity),
+            });
+        } else if bounded
+            && (position.cmplt(bounds.min - tolerance).any()
+                || position.cmpgt(bounds.max + tolerance).any())
+        {
+            monitor.report(Violation {
+                tick,
+                invariant: Invariant::InsideBounds,
+                entity: Some(entity.to_bits()),
+                detail: format!(
+                    "position {} outside {} to {}",
+                    position, bounds.min, bounds.max
+                ),
+            });
+        }
+    }
+
+    let budgets = monitor.budgets;
+    monitor.check_budget(tick, "entities", entities.len() as usize, budgets.entities);
+    monitor.check_budget(tick, "enemies", enemies.iter().count(), budgets.enemies);
+    monitor.check_budget(
+        tick,
+        "projectiles",
+        projectiles.iter().count(),
+        budgets.projectiles,
+    );
+    monitor.check_budget(tick, "pickups", pickups.iter().count(), budgets.pickups);
+}
+
+/// Result of a soak run: what broke and the recorded inputs that lead there.
+/// Played back, the replay reproduces the violations.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SoakReport {
+    /// Ticks simulated before the run stopped
+    pub ticks: u32,
+    pub total: usize,
+    pub violations: Vec<Violation>,
+    pub replay: InputReplay,
+}
+
+impl SoakReport {
+    pub fn capture(world: &World) -> Self {
+        let monitor = world.resource::<SoakMonitor>();
+        Self {
+            ticks: world.resource::<SimulationTick>().0,
+            total: monitor.total,
+            violations: monitor.violations.clone(),
+            replay: world
+                .get_resource::<InputRecorder>()
+                .map(|recorder| &recorder.0)
+                .or(world.get_resource::<InputReplay>())
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String, ReplayError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| ReplayError::Serialize(error.to_string()))
+    }
+
+    /// `path` is only used in error messages
+    pub fn from_ron(source: &str, path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let mut report: Self = ron::from_str(source).map_err(|error| ReplayError::Parse {
+            path: path.as_ref().to_path_buf(),
+            line: error.position.line,
+            column: error.position.col,
+            message: error.code.to_string(),
+        })?;
+        report.replay.inputs.sort_by_key(|entry| entry.tick);
+        Ok(report)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        let path = path.as_ref();
+        let io_error = |source| ReplayError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        std::fs::write(path, self.to_ron()?).map_err(io_error)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| ReplayError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_ron(&source, path)
+    }
+}
+
+/// Reads a replay file, or the replay inside a soak report
+pub fn read_replay(path: impl AsRef<Path>) -> Result<InputReplay, ReplayError> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path).map_err(|source| ReplayError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    InputReplay::from_ron(&source, path).or_else(|error| {
+        SoakReport::from_ron(&source, path)
+            .map(|report| report.replay)
+            .map_err(|_| error)
+    })
+}
+
+/// Where a failed soak run writes its report
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct SoakReportPath(pub PathBuf);
+
+/// Logs the violations once there are any and writes the report
+pub fn write_soak_report(world: &mut World) {
+    let monitor = world.resource::<SoakMonitor>();
+    if monitor.reported || !monitor.failed() {
+        return;
+    }
+    let report = SoakReport::capture(world);
+    world.resource_mut::<SoakMonitor>().reported = true;
+
+    error!(
+        "{} invariant violations after {} ticks",
+        report.total, report.ticks
+    );
+    for violation in report.violations.iter().take(10) {
+        error!("  {}", violation);
+    }
+    if let Some(SoakReportPath(path)) = world.get_resource::<SoakReportPath>() {
+        match report.write(path) {
+            Ok(()) => info!("Replayable report written to {}", path.display()),
+            Err(error) => error!("{}", error),
+        }
+    }
+}
+
+/// Bundle invariant checks for easy registration. They run while a
+/// `SoakMonitor` resource exists.
+pub struct SoakSystems;
+
+impl SoakSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.add_systems(
+            FixedUpdate,
+            check_invariants
+                .run_if(resource_exists::<SoakMonitor>())
+                .before(count_ticks)
+                .in_set(PhysicsStep::End),
+        )
+        .add_systems(
+            Last,
+            write_soak_report.run_if(resource_exists::<SoakMonitor>()),
+        )
+    }
+}
@@ -0,0 +1,172 @@
+use crate::components::MovementIntent;
+use bevy::prelude::*;
+
+/// How several boost sources held at once combine into one force multiplier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BoostStacking {
+    /// Multipliers multiply: two 3x boosts give 9x, as boosting always did
+    #[default]
+    Multiply,
+    /// Bonuses add up: two 3x boosts give 1 + 2 + 2 = 5x
+    Add,
+    /// Only the strongest held boost applies
+    Strongest,
+}
+
+/// Boost multipliers and stacking rule shared by all players
+#[derive(Resource, Debug, Clone)]
+pub struct BoostSettings {
+    /// Multiplier for the keyboard / gamepad boost button
+    pub button_multiplier: f32,
+    /// Multiplier for the left mouse button
+    pub mouse_multiplier: f32,
+    pub stacking: BoostStacking,
+}
+
+impl Default for BoostSettings {
+    fn default() -> Self {
+        Self {
+            button_multiplier: 3.0,
+            mouse_multiplier: 3.0,
+            stacking: BoostStacking::default(),
+        }
+    }
+}
+
+impl BoostSettings {
+    /// Combined force multiplier for the boost sources held in `intent`
+    pub fn multiplier(&self, intent: &MovementIntent) -> f32 {
+        let held = [
+            (intent.boost, self.button_multiplier),
+            (intent.mouse_boost, self.mouse_multiplier),
+        ];
+        let active = held.iter().filter(|(pressed, _)| *pressed).map(|(_, m)| *m);
+
+        match self.stacking {
+            BoostStacking::Multiply => active.product(),
+            BoostStacking::Add => 1.0 + active.map(|m| m - 1.0).sum::<f32>(),
+            BoostStacking::Strongest => active.fold(1.0, f32::max),
+        }
+    }
+}
+
+/// Per-player boost stamina, drained while boosting and recharged after a delay
+#[derive(Component, Debug, Clone)]
+pub struct Stamina {
+    pub current: f32,
+    pub max: f32,
+    /// Stamina spent per second for each boost source held
+    pub drain_per_second: f32,
+    pub recharge_per_second: f32,
+    /// Seconds without boosting before recharge starts
+    pub recharge_delay: f32,
+    /// Once depleted, boost stays locked until stamina is back above this fraction of max
+    pub reboost_fraction: f32,
+    /// Seconds since boost was last used
+    pub idle_time: f32,
+    /// Set when stamina ran out, cleared once it recovers past `reboost_fraction`
+    pub exhausted: bool,
+    /// Whether the boost was applied on the last fixed tick
+    pub boosting: bool,
+}
+
+impl Default for Stamina {
+    fn default() -> Self {
+        Self {
+            current: 100.0,
+            max: 100.0,
+            drain_per_second: 40.0,
+            recharge_per_second: 25.0,
+            recharge_delay: 1.0,
+            reboost_fraction: 0.25,
+            idle_time: 0.0,
+            exhausted: false,
+            boosting: false,
+        }
+    }
+}
+
+/// Outcome of a stamina update that other systems may want to hear about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaminaChange {
+    Depleted,
+    Full,
+}
+
+impl Stamina {
+    pub fn can_boost(&self) -> bool {
+        !self.exhausted && self.current > 0.0
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.max <= 0.0 {
+            0.0
+        } else {
+            self.current / self.max
+        }
+    }
+
+    /// Advance stamina by one step. `sources` is the number of boost sources held.
+    /// Returns the change crossing a threshold this step, if any.
+    pub fn tick(&mut self, sources: u32, delta: f32) -> Option<StaminaChange> {
+        self.boosting = sources > 0 && self.can_boost();
+
+        if self.boosting {
+            self.idle_time = 0.0;
+            self.current = (self.current - self.drain_per_second * sources as f32 * delta).max(0.0);
+            if self.current <= 0.0 {
+                self.exhausted = true;
+                return Some(StaminaChange::Depleted);
+            }
+            return None;
+        }
+
+        self.idle_time += delta;
+        if self.idle_time < self.recharge_delay || self.current >= self.max {
+            return None;
+        }
+
+        self.current = (self.current + self.recharge_per_second * delta).min(self.max);
+        if self.exhausted && self.fraction() >= self.reboost_fraction {
+            self.exhausted = false;
+        }
+        (self.current >= self.max).then_some(StaminaChange::Full)
+    }
+}
+
+/// Whether the boost is applied for this intent: gated by stamina when the
+/// player has it, free otherwise
+pub fn is_boosting(intent: &MovementIntent, stamina: Option<&Stamina>) -> bool {
+    stamina.map_or(intent.boost_sources() > 0, |s| s.boosting)
+}
+
+/// Fired on the tick a player's stamina hits zero
+#[derive(Event, Debug, Clone, Copy)]
+pub struct StaminaDepleted {
+    pub entity: Entity,
+}
+
+/// Fired on the tick a player's stamina finishes recharging
+#[derive(Event, Debug, Clone, Copy)]
+pub struct StaminaFull {
+    pub entity: Entity,
+}
+
+/// Drains stamina for held boosts and recharges it otherwise, runs on the fixed timestep
+/// before `apply_movement_intent` so an empty bar blocks the boost on the same tick
+pub fn update_stamina(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(Entity, &MovementIntent, &mut Stamina)>,
+    mut depleted_events: EventWriter<StaminaDepleted>,
+    mut full_events: EventWriter<StaminaFull>,
+) {
+    let delta = fixed_time.period.as_secs_f32();
+
+    for (entity, intent, mut stamina) in query.iter_mut() {
+        match stamina.tick(intent.boost_sources(), delta) {
+            Some(StaminaChange::Depleted) => depleted_events.send(StaminaDepleted { entity }),
+            Some(StaminaChange::Full) => full_events.send(StaminaFull { entity }),
+            None => {}
+        }
+    }
+}
@@ -1,64 +1,229 @@
-use crate::components::Player;
+use crate::components::{Acceleration, CollisionRadius, Friction, LinearVelocity, Mass, Obstacle};
+use crate::input::apply_movement_intent;
 use crate::resources::WorldBounds;
+use crate::stamina::update_stamina;
 use bevy::prelude::*;
 
+/// System set for the fixed timestep simulation, so run conditions such as
+/// game state gating apply to every physics system at once
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PhysicsSet;
+
+/// Ordered phases inside `PhysicsSet`. Debug tooling hooks into `Begin` and `End`,
+/// the simulation itself runs in `Simulate`.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhysicsStep {
+    Begin,
+    Simulate,
+    End,
+}
+
+/// Length of one fixed physics tick in seconds
+pub fn fixed_delta(fixed_time: &FixedTime) -> f32 {
+    fixed_time.period.as_secs_f32()
+}
+
 /// World friction system that applies global friction to all entities
-pub fn world_friction(time: Res<Time>, bounds: Res<WorldBounds>, mut query: Query<&mut Player>) {
-    let delta = time.delta_seconds();
+pub fn world_friction(
+    fixed_time: Res<FixedTime>,
+    bounds: Res<WorldBounds>,
+    mut query: Query<&mut LinearVelocity>,
+) {
+    let delta = fixed_delta(&fixed_time);
 
-    for mut player in query.iter_mut() {
+    for mut velocity in query.iter_mut() {
         // Apply world friction
-        player.velocity *= (1.0 - bounds.friction).powf(delta);
+        velocity.0 *= (1.0 - bounds.friction).powf(delta);
     }
 }
 
+/// Bodies moved by the physics integration
+pub type IntegrationQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Transform,
+        &'static mut LinearVelocity,
+        &'static mut Acceleration,
+        &'static Friction,
+        Option<&'static Mass>,
+    ),
+>;
+
 /// Physics integration system that applies acceleration and entity friction to velocity,
 /// then applies velocity to transform position
-pub fn player_physics_integration(
-    time: Res<Time>,
-    mut query: Query<(&mut Transform, &mut Player)>,
-) {
-    let delta = time.delta_seconds();
+pub fn player_physics_integration(fixed_time: Res<FixedTime>, mut query: IntegrationQuery) {
+    let delta = fixed_delta(&fixed_time);
 
-    for (mut transform, mut player) in query.iter_mut() {
-        // Apply acceleration to velocity
-        let player_acceleration = player.acceleration;
-        player.velocity += player_acceleration * delta;
+    for (mut transform, mut velocity, mut acceleration, friction, mass) in query.iter_mut() {
+        // Apply acceleration to velocity, heavier bodies respond less to the same force
+        let mass = mass.copied().unwrap_or_default().0;
+        velocity.0 += acceleration.0 / mass * delta;
 
         // Apply entity-specific friction to velocity
-        let player_friction = player.friction;
-        player.velocity *= (1.0 - player_friction).powf(delta);
+        velocity.0 *= (1.0 - friction.0).powf(delta);
 
         // Apply velocity to position
-        transform.translation.x += player.velocity.x * delta;
-        transform.translation.y += player.velocity.y * delta;
+        transform.translation.x += velocity.0.x * delta;
+        transform.translation.y += velocity.0.y * delta;
 
         // Reset acceleration (will be set by input system next frame)
-        player.acceleration = Vec2::ZERO;
+        acceleration.0 = Vec2::ZERO;
     }
 }
 
+/// Fired when a body hits the world bounds or an obstacle
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WallImpact {
+    pub entity: Entity,
+    /// Body position after it was pushed back out
+    pub position: Vec2,
+    /// Surface normal pointing away from the wall
+    pub normal: Vec2,
+    /// Speed into the wall just before the bounce
+    pub speed: f32,
+}
+
 /// Boundary collision system that handles collisions with world bounds
 pub fn boundary_collision(
     bounds: Res<WorldBounds>,
-    mut query: Query<(&mut Transform, &mut Player)>,
+    mut query: Query<(Entity, &mut Transform, &mut LinearVelocity)>,
+    mut impacts: EventWriter<WallImpact>,
 ) {
-    for (mut transform, mut player) in query.iter_mut() {
+    for (entity, mut transform, mut velocity) in query.iter_mut() {
         let position = Vec2::new(transform.translation.x, transform.translation.y);
 
         // Check X boundaries
         if position.x <= bounds.min.x || position.x >= bounds.max.x {
-            player.velocity.x = player.velocity.x * bounds.bounce_factor;
-            player.velocity.x = -player.velocity.x; // Reverse X velocity
+            let normal = if position.x <= bounds.min.x {
+                Vec2::X
+            } else {
+                Vec2::NEG_X
+            };
+            let speed = velocity.0.x.abs();
+            velocity.0.x *= bounds.bounce_factor;
+            velocity.0.x = -velocity.0.x; // Reverse X velocity
 
             transform.translation.x = transform.translation.x.clamp(bounds.min.x, bounds.max.x);
+            impacts.send(WallImpact {
+                entity,
+                position: transform.translation.truncate(),
+                normal,
+                speed,
+            });
         }
 
         // Check Y boundaries
         if position.y <= bounds.min.y || position.y >= bounds.max.y {
-            player.velocity.y = player.velocity.y * bounds.bounce_factor;
-            player.velocity.y = -player.velocity.y; // Reverse Y velocity
+            let normal = if position.y <= bounds.min.y {
+                Vec2::Y
+            } else {
+                Vec2::NEG_Y
+            };
+            let speed = velocity.0.y.abs();
+            velocity.0.y *= bounds.bounce_factor;
+            velocity.0.y = -velocity.0.y; // Reverse Y velocity
             transform.translation.y = transform.translation.y.clamp(bounds.min.y, bounds.max.y);
+            impacts.send(WallImpact {
+                entity,
+                position: transform.translation.truncate(),
+                normal,
+                speed,
+            });
+        }
+    }
+}
+
+/// Direction and depth to push a circle out of a box, `None` when they do not overlap
+pub fn obstacle_penetration(
+    position: Vec2,
+    radius: f32,
+    center: Vec2,
+    half_size: Vec2,
+) -> Option<(Vec2, f32)> {
+    let local = position - center;
+    let closest = local.clamp(-half_size, half_size);
+    let offset = local - closest;
+    let distance = offset.length();
+
+    if distance > f32::EPSILON {
+        return (distance < radius).then(|| (offset / distance, radius - distance));
+    }
+
+    // Center inside the box, leave through the nearest face
+    let depth = half_size - local.abs();
+    if depth.x < depth.y {
+        Some((Vec2::new(local.x.signum(), 0.0), depth.x + radius))
+    } else {
+        Some((Vec2::new(0.0, local.y.signum()), depth.y + radius))
+    }
+}
+
+/// Pushes bodies out of obstacles and reflects the velocity component into the obstacle
+pub fn obstacle_collision(
+    obstacles: Query<(&Transform, &Obstacle)>,
+    mut bodies: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut LinearVelocity,
+            Option<&CollisionRadius>,
+        ),
+        Without<Obstacle>,
+    >,
+    mut impacts: EventWriter<WallImpact>,
+) {
+    for (entity, mut transform, mut velocity, radius) in bodies.iter_mut() {
+        let radius = radius.map_or(0.0, |radius| radius.0);
+
+        for (obstacle_transform, obstacle) in obstacles.iter() {
+            let position = transform.translation.truncate();
+            let center = obstacle_transform.translation.truncate();
+            let Some((normal, depth)) =
+                obstacle_penetration(position, radius, center, obstacle.half_size)
+            else {
+                continue;
+            };
+
+            transform.translation += (normal * depth).extend(0.0);
+            let into_obstacle = velocity.0.dot(normal);
+            if into_obstacle < 0.0 {
+                velocity.0 -= 2.0 * into_obstacle * normal;
+                impacts.send(WallImpact {
+                    entity,
+                    position: transform.translation.truncate(),
+                    normal,
+                    speed: -into_obstacle,
+                });
+            }
         }
     }
 }
+
+/// Bundle the fixed timestep physics pipeline for easy registration
+pub struct PhysicsSystems;
+
+impl PhysicsSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.add_event::<WallImpact>()
+            .configure_sets(
+                FixedUpdate,
+                (PhysicsStep::Begin, PhysicsStep::Simulate, PhysicsStep::End)
+                    .chain()
+                    .in_set(PhysicsSet),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    update_stamina,
+                    apply_movement_intent,
+                    world_friction,
+                    player_physics_integration,
+                    boundary_collision,
+                    obstacle_collision,
+                )
+                    .chain()
+                    .in_set(PhysicsStep::Simulate),
+            )
+    }
+}
@@ -0,0 +1,83 @@
+use crate::entity_relations::{ChildOf, Children};
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+/// Transform relative to the parent named by `ChildOf`. Each frame the
+/// entity's `Transform` is set to the parent's transform combined with this
+/// one, so the entity follows the parent's position, rotation and scale.
+/// Entities attached without it keep moving on their own.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default)]
+pub struct LocalTransform(pub Transform);
+
+impl LocalTransform {
+    /// Offset that keeps `child` where it is under `parent`
+    pub fn between(parent: &Transform, child: &Transform) -> Self {
+        let local = parent.compute_affine().inverse() * child.compute_affine();
+        Self(Transform::from_matrix(Mat4::from(local)))
+    }
+
+    /// Where the entity ends up under a parent at `parent`
+    pub fn world(&self, parent: &Transform) -> Transform {
+        parent.mul_transform(self.0)
+    }
+}
+
+/// Top of a hierarchy: has children but is nobody's child
+pub type HierarchyRootQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static Transform, &'static Children), Without<ChildOf>>;
+
+/// Attached entities, placed by their parents
+pub type AttachedQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static ChildOf,
+        &'static mut Transform,
+        Option<&'static LocalTransform>,
+        Option<&'static Children>,
+    ),
+>;
+
+/// Walks every hierarchy from its root down and places children with a
+/// `LocalTransform` relative to their parent. Runs after physics moved the
+/// roots. A child only counts when its `ChildOf` agrees with the parent's
+/// `Children`, which also keeps broken links from looping forever.
+pub fn propagate_local_transforms(roots: HierarchyRootQuery, mut attached: AttachedQuery) {
+    let mut stack: Vec<(Entity, Transform, Entity)> = Vec::new();
+    for (root, transform, children) in roots.iter() {
+        stack.extend(children.0.iter().map(|child| (root, *transform, *child)));
+        while let Some((parent, parent_transform, entity)) = stack.pop() {
+            let Ok((child_of, mut transform, local, children)) = attached.get_mut(entity) else {
+                continue;
+            };
+            if child_of.0 != parent {
+                continue;
+            }
+            if let Some(local) = local {
+                let world = local.world(&parent_transform);
+                // Only write when it moved, so change detection stays useful
+                if *transform != world {
+                    *transform = world;
+                }
+            }
+            let transform = *transform;
+            if let Some(children) = children {
+                stack.extend(children.0.iter().map(|child| (entity, transform, *child)));
+            }
+        }
+    }
+}
+
+/// Bundle transform propagation for easy registration. It runs every frame
+/// after the fixed physics ticks and before Bevy computes global transforms
+/// for rendering.
+pub struct TransformPropagationSystems;
+
+impl TransformPropagationSystems {
+    pub fn add_to_app(app: &mut App) -> &mut App {
+        app.add_systems(
+            PostUpdate,
+            propagate_local_transforms.before(TransformSystem::TransformPropagate),
+        )
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// Why a packet could not be sent or read, or a network session could not start
+#[derive(Debug)]
+pub enum NetError {
+    Io(std::io::Error),
+    /// A received packet did not have the expected layout
+    Malformed(String),
+    /// Invalid network setup, such as a bad command line address
+    Config(String),
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetError::Io(source) => write!(f, "network error: {}", source),
+            NetError::Malformed(message) => write!(f, "malformed packet: {}", message),
+            NetError::Config(message) => write!(f, "invalid network setup: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for NetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NetError::Io(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for NetError {
+    fn from(source: std::io::Error) -> Self {
+        NetError::Io(source)
+    }
+}
+
+/// Unreliable datagram link to one peer. Packets may be lost, so protocols on
+/// top resend what has not been acknowledged. Neither call blocks.
+pub trait Transport: Send + Sync {
+    fn send(&mut self, packet: &[u8]) -> Result<(), NetError>;
+
+    /// Every packet that arrived since the last call, oldest first
+    fn receive(&mut self) -> Vec<Vec<u8>>;
+}
+
+type PacketQueue = Arc<Mutex<VecDeque<Vec<u8>>>>;
+
+/// In-memory link between two endpoints in the same process, for tests and
+/// loopback play. Packets arrive in order and are never lost.
+pub struct ChannelTransport {
+    outgoing: PacketQueue,
+    incoming: PacketQueue,
+}
+
+impl ChannelTransport {
+    /// Two endpoints connected to each other
+    pub fn pair() -> (Self, Self) {
+        let a_to_b = PacketQueue::default();
+        let b_to_a = PacketQueue::default();
+        (
+            Self {
+                outgoing: a_to_b.clone(),
+                incoming: b_to_a.clone(),
+            },
+            Self {
+                outgoing: b_to_a,
+                incoming: a_to_b,
+            },
+        )
+    }
+
+    /// Number of packets sent to this endpoint and not yet received
+    pub fn pending(&self) -> usize {
+        self.incoming.lock().map_or(0, |queue| queue.len())
+    }
+}
+
+impl Transport for ChannelTransport {
+    fn send(&mut self, packet: &[u8]) -> Result<(), NetError> {
+        if let Ok(mut queue) = self.outgoing.lock() {
+            queue.push_back(packet.to_vec());
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Vec<Vec<u8>> {
+        self.incoming
+            .lock()
+            .map(|mut queue| queue.drain(..).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Largest packet `UdpTransport` reads, the UDP payload limit. Full world
+/// snapshots can exceed a typical MTU and rely on IP fragmentation.
+#[cfg(not(target_arch = "wasm32"))]
+pub const MAX_DATAGRAM: usize = 65507;
+
+/// Non-blocking UDP socket talking to a single peer. Not available on WASM.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct UdpTransport {
+    socket: UdpSocket,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl UdpTransport {
+    /// Bind a socket on `local`. Call `connect` before sending.
+    pub fn bind(local: impl ToSocketAddrs) -> Result<Self, NetError> {
+        let socket = UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket })
+    }
+
+    /// Send to `peer` and ignore datagrams from anyone else
+    pub fn connect(&self, peer: impl ToSocketAddrs) -> Result<(), NetError> {
+        self.socket.connect(peer)?;
+        Ok(())
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, NetError> {
+        Ok(self.socket.local_addr()?)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Transport for UdpTransport {
+    fn send(&mut self, packet: &[u8]) -> Result<(), NetError> {
+        match self.socket.send(packet) {
+            Ok(_) => Ok(()),
+            // The peer is not listening yet, the packet is simply lost
+            Err(error) if error.kind() == std::io::ErrorKind::ConnectionRefused => Ok(()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn receive(&mut self) -> Vec<Vec<u8>> {
+        let mut packets = Vec::new();
+        let mut buffer = [0; MAX_DATAGRAM];
+        loop {
+            match self.socket.recv(&mut buffer) {
+                Ok(length) => packets.push(buffer[..length].to_vec()),
+                Err(error) if error.kind() == std::io::ErrorKind::ConnectionRefused => continue,
+                Err(_) => break,
+            }
+        }
+        packets
+    }
+}
@@ -0,0 +1,219 @@
+use crate::components::MovementIntent;
+use crate::stamina::{is_boosting, Stamina};
+use bevy::prelude::*;
+
+/// Gameplay states that change how an entity is drawn.
+/// Declared in increasing priority: when several are active the last one wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum VisualState {
+    Idle,
+    Boosting,
+    Invulnerable,
+    Hurt,
+}
+
+/// Tint and uniform scale for one visual state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VisualStyle {
+    pub color: Color,
+    pub scale: f32,
+}
+
+impl VisualStyle {
+    pub fn new(color: Color, scale: f32) -> Self {
+        Self { color, scale }
+    }
+
+    /// Linear blend toward `target`, `t` in 0..=1
+    pub fn lerp(self, target: VisualStyle, t: f32) -> VisualStyle {
+        let t = t.clamp(0.0, 1.0);
+        let from = self.color.as_rgba_f32();
+        let to = target.color.as_rgba_f32();
+        let mix = |i: usize| from[i] + (to[i] - from[i]) * t;
+
+        VisualStyle {
+            color: Color::rgba(mix(0), mix(1), mix(2), mix(3)),
+            scale: self.scale + (target.scale - self.scale) * t,
+        }
+    }
+}
+
+/// Look of every non-idle state, the idle look comes from each entity's `VisualFeedback::base`
+#[derive(Resource, Debug, Clone)]
+pub struct VisualPalette {
+    pub boosting: VisualStyle,
+    pub invulnerable: VisualStyle,
+    pub hurt: VisualStyle,
+    /// Blinks per second while invulnerable
+    pub invulnerable_blink_rate: f32,
+}
+
+impl Default for VisualPalette {
+    fn default() -> Self {
+        Self {
+            boosting: VisualStyle::new(Color::rgb(0.9, 0.25, 0.75), 1.1),
+            invulnerable: VisualStyle::new(Color::rgba(0.9, 0.9, 1.0, 0.6), 1.0),
+            hurt: VisualStyle::new(Color::rgb(1.0, 0.2, 0.2), 1.2),
+            invulnerable_blink_rate: 8.0,
+        }
+    }
+}
+
+impl VisualPalette {
+    pub fn style(&self, state: VisualState, base: VisualStyle) -> VisualStyle {
+        match state {
+            VisualState::Idle => base,
+            VisualState::Boosting => self.boosting,
+            VisualState::Invulnerable => self.invulnerable,
+            VisualState::Hurt => self.hurt,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ActiveVisual {
+    state: VisualState,
+    /// `None` for states held until cleared, otherwise seconds left
+    remaining: Option<f32>,
+}
+
+/// Visual state layer for one sprite. Gameplay systems raise or clear states here
+/// and `apply_visual_feedback` is the only system that writes the sprite tint and scale.
+#[derive(Component, Debug, Clone)]
+pub struct VisualFeedback {
+    /// Idle look
+    pub base: VisualStyle,
+    /// Seconds to blend from the current look to a new target look
+    pub blend_time: f32,
+    current: VisualStyle,
+    /// Look the blend in progress started from, and the look it ends on
+    blend_from: VisualStyle,
+    blend_to: VisualStyle,
+    /// Seconds since the blend toward `blend_to` started
+    blend_elapsed: f32,
+    active: Vec<ActiveVisual>,
+}
+
+impl VisualFeedback {
+    pub fn new(base_color: Color) -> Self {
+        let base = VisualStyle::new(base_color, 1.0);
+        Self {
+            base,
+            blend_time: 0.1,
+            current: base,
+            blend_from: base,
+            blend_to: base,
+            blend_elapsed: 0.0,
+            active: Vec::new(),
+        }
+    }
+
+    /// Hold or release a state that is derived every frame, such as boosting
+    pub fn set(&mut self, state: VisualState, active: bool) {
+        self.active
+            .retain(|visual| visual.state != state || visual.remaining.is_some());
+        if active {
+            self.active.push(ActiveVisual {
+                state,
+                remaining: None,
+            });
+        }
+    }
+
+    /// Raise a state for `duration` seconds, such as a damage flash.
+    /// Triggering a state that is already timed extends it to the longer duration.
+    pub fn trigger(&mut self, state: VisualState, duration: f32) {
+        if let Some(visual) = self
+            .active
+            .iter_mut()
+            .find(|visual| visual.state == state && visual.remaining.is_some())
+        {
+            visual.remaining = visual.remaining.map(|left| left.max(duration));
+            return;
+        }
+        self.active.push(ActiveVisual {
+            state,
+            remaining: Some(duration),
+        });
+    }
+
+    pub fn is_active(&self, state: VisualState) -> bool {
+        self.active.iter().any(|visual| visual.state == state)
+    }
+
+    /// Highest priority active state
+    pub fn dominant(&self) -> VisualState {
+        self.active
+            .iter()
+            .map(|visual| visual.state)
+            .max()
+            .unwrap_or(VisualState::Idle)
+    }
+
+    /// Look currently shown, including any blend in progress
+    pub fn current(&self) -> VisualStyle {
+        self.current
+    }
+
+    /// Count down timed states and blend the current look toward the dominant state
+    pub fn advance(&mut self, palette: &VisualPalette, delta: f32) -> VisualStyle {
+        for visual in self.active.iter_mut() {
+            if let Some(remaining) = visual.remaining.as_mut() {
+                *remaining -= delta;
+            }
+        }
+        self.active
+            .retain(|visual| visual.remaining.is_none_or(|left| left > 0.0));
+
+        // A new target restarts the blend from whatever is shown right now
+        let target = palette.style(self.dominant(), self.base);
+        if target != self.blend_to {
+            self.blend_from = self.current;
+            self.blend_to = target;
+            self.blend_elapsed = 0.0;
+        }
+        self.blend_elapsed += delta;
+
+        let t = if self.blend_time <= 0.0 {
+            1.0
+        } else {
+            self.blend_elapsed / self.blend_time
+        };
+        self.current = self.blend_from.lerp(target, t);
+        self.current
+    }
+}
+
+/// Raises the boosting state from the same rule `apply_movement_intent` uses
+pub fn derive_boost_visuals(
+    mut query: Query<(&MovementIntent, Option<&Stamina>, &mut VisualFeedback)>,
+) {
+    for (intent, stamina, mut feedback) in query.iter_mut() {
+        feedback.set(VisualState::Boosting, is_boosting(intent, stamina));
+    }
+}
+
+/// Writes the blended look to the sprite color and transform scale
+pub fn apply_visual_feedback(
+    time: Res<Time>,
+    palette: Res<VisualPalette>,
+    mut query: Query<(&mut VisualFeedback, &mut Sprite, &mut Transform)>,
+) {
+    let delta = time.delta_seconds();
+
+    for (mut feedback, mut sprite, mut transform) in query.iter_mut() {
+        let mut style = feedback.advance(&palette, delta);
+
+        // Invulnerability blinks on top of whatever is blended in
+        if feedback.dominant() == VisualState::Invulnerable {
+            let phase = (time.elapsed_seconds() * palette.invulnerable_blink_rate).fract();
+            if phase < 0.5 {
+                style.color.set_a(style.color.a() * 0.3);
+            }
+        }
+
+        sprite.color = style.color;
+        transform.scale.x = style.scale;
+        transform.scale.y = style.scale;
+    }
+}
@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+use bevy_wasm_game::camera::*;
+use bevy_wasm_game::resources::*;
+use bevy_wasm_game::systems::*;
+use std::time::{Duration, Instant};
+
+/// Tests for the camera controller and screen shake
+
+#[cfg(test)]
+mod camera_tests {
+    use super::*;
+
+    #[test]
+    fn test_dead_zone_only_moves_when_target_leaves_it() {
+        let dead_zone = Vec2::new(80.0, 60.0);
+        assert_eq!(
+            apply_dead_zone(Vec2::ZERO, Vec2::new(50.0, -30.0), dead_zone),
+            Vec2::ZERO
+        );
+        assert_eq!(
+            apply_dead_zone(Vec2::ZERO, Vec2::new(100.0, -90.0), dead_zone),
+            Vec2::new(20.0, -30.0)
+        );
+    }
+
+    #[test]
+    fn test_zoom_fits_targets() {
+        let follow = CameraFollow::default();
+        let view = Vec2::new(1280.0, 720.0);
+        assert_eq!(fit_zoom(Vec2::ZERO, view, &follow), follow.min_zoom);
+
+        let zoom = fit_zoom(Vec2::new(1800.0, 100.0), view, &follow);
+        assert!(zoom > 1.0 && zoom < follow.max_zoom);
+        assert!(1800.0 + follow.padding * 2.0 <= view.x * zoom + 0.01);
+
+        assert_eq!(
+            fit_zoom(Vec2::splat(100_000.0), view, &follow),
+            follow.max_zoom
+        );
+    }
+
+    #[test]
+    fn test_focus_clamps_to_bounds() {
+        let bounds = WorldBounds::from_window_size(4000.0, 2000.0, 0.0);
+        let half_view = Vec2::new(640.0, 360.0);
+
+        let clamped = clamp_focus(Vec2::new(5000.0, -5000.0), half_view, &bounds, 0.0);
+        assert_eq!(clamped, Vec2::new(2000.0 - 640.0, -1000.0 + 360.0));
+
+        // The default arena is no bigger than the window, so the camera stays centered
+        let arena = WorldBounds::default_bounds();
+        let centered = clamp_focus(Vec2::new(300.0, 200.0), half_view, &arena, 50.0);
+        assert_eq!(centered, Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_trauma_decays_and_scales_shake() {
+        let mut shake = CameraShake::default();
+        assert_eq!(shake.advance(0.016), (Vec2::ZERO, 0.0));
+
+        shake.add_trauma(2.0);
+        assert_eq!(shake.trauma, 1.0, "Trauma is capped");
+        let (offset, _) = shake.advance(0.1);
+        assert!(offset.length() <= shake.max_offset * std::f32::consts::SQRT_2);
+
+        for _ in 0..100 {
+            shake.advance(0.1);
+        }
+        assert_eq!(shake.trauma, 0.0);
+    }
+
+    #[test]
+    fn test_hard_wall_hits_shake_and_camera_follows() {
+        let mut world = World::new();
+        let mut time = Time::default();
+        let start = Instant::now();
+        time.update_with_instant(start);
+        time.update_with_instant(start + Duration::from_millis(100));
+        world.insert_resource(time);
+        world.insert_resource(WorldBounds::from_window_size(6000.0, 6000.0, 0.0));
+        world.init_resource::<ImpactShakeSettings>();
+        world.init_resource::<Events<WallImpact>>();
+        world.init_resource::<Events<ShakeEvent>>();
+
+        let camera = world
+            .spawn((
+                Transform::default(),
+                OrthographicProjection::default(),
+                CameraFollow::default(),
+                CameraShake::default(),
+            ))
+            .id();
+        let target = world
+            .spawn((Transform::from_xyz(1000.0, 0.0, 0.0), CameraTarget))
+            .id();
+
+        let settings = ImpactShakeSettings::default();
+        let mut impacts = world.resource_mut::<Events<WallImpact>>();
+        impacts.send(WallImpact {
+            entity: target,
+            position: Vec2::ZERO,
+            normal: Vec2::X,
+            speed: settings.min_speed - 1.0,
+        });
+
+        let mut schedule = Schedule::new();
+        schedule
+            .add_systems((camera_follow, collect_camera_trauma, apply_camera_transform).chain());
+        schedule.run(&mut world);
+        assert_eq!(world.get::<CameraShake>(camera).unwrap().trauma, 0.0);
+
+        world.resource_mut::<Events<WallImpact>>().send(WallImpact {
+            entity: target,
+            position: Vec2::ZERO,
+            normal: Vec2::X,
+            speed: settings.full_speed,
+        });
+        schedule.run(&mut world);
+        assert!(world.get::<CameraShake>(camera).unwrap().trauma > 0.0);
+
+        let focus = world.get::<CameraFollow>(camera).unwrap().focus;
+        assert!(focus.x > 0.0, "Camera moves toward the target");
+        assert!(focus.x < 1000.0, "Camera movement is smoothed");
+    }
+}
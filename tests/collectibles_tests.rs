@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use bevy_wasm_game::collectibles::*;
+use bevy_wasm_game::components::*;
+use bevy_wasm_game::resources::*;
+use bevy_wasm_game::rng::*;
+
+/// Tests for collectibles, pickups and combo scoring
+
+#[cfg(test)]
+mod collectibles_tests {
+    use super::*;
+
+    #[test]
+    fn test_combo_multiplier_grows_and_breaks() {
+        let settings = ScoreSettings::default();
+        let mut score = Score::default();
+
+        assert_eq!(score.register_pickup(10, &settings), 10);
+        score.tick(0.5);
+        assert_eq!(
+            score.register_pickup(10, &settings),
+            15,
+            "Second in chain is x1.5"
+        );
+        score.tick(0.5);
+        assert_eq!(score.register_pickup(10, &settings), 20);
+        assert_eq!(score.combo, 3);
+
+        // Let the window run out
+        score.tick(settings.combo_window + 0.1);
+        assert_eq!(score.combo, 0);
+        assert_eq!(
+            score.register_pickup(10, &settings),
+            10,
+            "Chain restarts at x1"
+        );
+
+        assert_eq!(score.points, 55);
+        assert_eq!(score.best_combo, 3);
+    }
+
+    #[test]
+    fn test_multiplier_is_capped() {
+        let settings = ScoreSettings::default();
+        let mut score = Score::default();
+        for _ in 0..20 {
+            score.register_pickup(10, &settings);
+        }
+        assert_eq!(score.multiplier(&settings), settings.max_multiplier);
+    }
+
+    #[test]
+    fn test_spawn_positions_respect_rules() {
+        let bounds = WorldBounds::default_bounds();
+        let spawner = CollectibleSpawner::default();
+        let mut rng = GameRng::from_seed(42);
+        let players = vec![Vec2::ZERO];
+        let mut placed = Vec::new();
+
+        for _ in 0..10 {
+            let position =
+                find_spawn_position(&mut rng, &bounds, &spawner, &players, &placed, &[]).unwrap();
+            assert!(bounds.contains(position));
+            assert!(position.x >= bounds.min.x + spawner.margin);
+            assert!(position.y <= bounds.max.y - spawner.margin);
+            assert!(position.length() >= spawner.min_player_distance);
+            assert!(placed
+                .iter()
+                .all(|other: &Vec2| other.distance(position) >= spawner.min_spacing));
+            placed.push(position);
+        }
+    }
+
+    #[test]
+    fn test_spawn_positions_avoid_blocked_areas() {
+        let bounds = WorldBounds::default_bounds();
+        let spawner = CollectibleSpawner::default();
+        let mut rng = GameRng::from_seed(7);
+        // Block the whole left half of the arena
+        let blocked = [Rect::new(bounds.min.x, bounds.min.y, 0.0, bounds.max.y)];
+
+        for _ in 0..20 {
+            if let Some(position) =
+                find_spawn_position(&mut rng, &bounds, &spawner, &[], &[], &blocked)
+            {
+                assert!(position.x > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pickup_scores_and_fires_event() {
+        let mut world = World::new();
+        world.insert_resource(FixedTime::new_from_secs(1.0 / 60.0));
+        world.insert_resource(WorldBounds::default_bounds());
+        world.init_resource::<GameRng>();
+        world.init_resource::<CollectibleSpawner>();
+        world.init_resource::<ScoreSettings>();
+        world.init_resource::<Score>();
+        world.init_resource::<Events<PickupEvent>>();
+
+        let player = world
+            .spawn((Transform::default(), Player, CollisionRadius::default()))
+            .id();
+        let near = world
+            .spawn((Transform::from_xyz(30.0, 0.0, 0.0), Collectible::default()))
+            .id();
+        world.spawn((Transform::from_xyz(300.0, 0.0, 0.0), Collectible::default()));
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems((tick_combo, detect_pickups).chain());
+        schedule.run(&mut world);
+
+        assert!(
+            world.get_entity(near).is_none(),
+            "Touched collectible is removed"
+        );
+        assert_eq!(world.resource::<Score>().points, 10);
+
+        let events = world.resource::<Events<PickupEvent>>();
+        let picked: Vec<_> = events.get_reader().iter(events).copied().collect();
+        assert_eq!(picked.len(), 1);
+        assert_eq!(picked[0].collector, player);
+        assert_eq!(picked[0].collectible, near);
+
+        // The spawner refills the arena
+        let mut spawn_schedule = Schedule::new();
+        spawn_schedule.add_systems(spawn_collectibles);
+        spawn_schedule.run(&mut world);
+        let count = world.query::<&Collectible>().iter(&world).count();
+        assert_eq!(count, CollectibleSpawner::default().target_count);
+    }
+
+    #[test]
+    fn test_seeded_rng_is_reproducible() {
+        let mut a = SeededRng::new(7);
+        let mut b = SeededRng::new(7);
+        let sequence_a: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+        let sequence_b: Vec<u64> = (0..8).map(|_| b.next_u64()).collect();
+        assert_eq!(sequence_a, sequence_b);
+
+        for _ in 0..1000 {
+            let value = a.next_f32();
+            assert!((0.0..1.0).contains(&value));
+            assert!(a.below(5) < 5);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use bevy_wasm_game::components::*;
+use bevy_wasm_game::enemy::*;
+use bevy_wasm_game::entity_relations::*;
+use bevy_wasm_game::resources::*;
+use bevy_wasm_game::stamina::*;
+use bevy_wasm_game::systems::*;
+
+/// Tests for enemy steering behaviors
+
+#[cfg(test)]
+mod enemy_tests {
+    use super::*;
+
+    fn create_enemy_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<BoostSettings>()
+            .insert_resource(WorldBounds::default_bounds())
+            .add_event::<StaminaDepleted>()
+            .add_event::<StaminaFull>();
+        PhysicsSystems::add_to_app(&mut app);
+        EnemySystems::add_to_app(&mut app);
+        app
+    }
+
+    fn spawn_body(app: &mut App, position: Vec2, velocity: Vec2) -> Entity {
+        app.world
+            .spawn((
+                Transform::from_translation(position.extend(0.0)),
+                PhysicsBundle::new(velocity, 0.95),
+            ))
+            .id()
+    }
+
+    fn position(app: &App, entity: Entity) -> Vec2 {
+        app.world
+            .get::<Transform>(entity)
+            .unwrap()
+            .translation
+            .truncate()
+    }
+
+    #[test]
+    fn test_basic_behaviors_point_the_right_way() {
+        let steering = Steering::default();
+        let target = Vec2::new(100.0, 0.0);
+
+        assert!(seek(Vec2::ZERO, Vec2::ZERO, target, &steering).x > 0.0);
+        assert!(flee(Vec2::ZERO, Vec2::ZERO, target, &steering).x < 0.0);
+
+        let far_threat = Vec2::new(steering.panic_radius + 1.0, 0.0);
+        assert_eq!(
+            flee(Vec2::ZERO, Vec2::ZERO, far_threat, &steering),
+            Vec2::ZERO,
+            "Threats outside the panic radius are ignored"
+        );
+
+        // Arriving at speed on the target brakes
+        let braking = arrive(target, Vec2::new(200.0, 0.0), target, &steering);
+        assert!(braking.x < 0.0);
+        assert!(braking.length() <= steering.max_force + 0.01);
+    }
+
+    #[test]
+    fn test_pursue_and_evade_lead_the_target() {
+        let steering = Steering::default();
+        let target = Vec2::new(200.0, 0.0);
+        let target_velocity = Vec2::new(0.0, 300.0);
+
+        let predicted = predict_position(
+            Vec2::ZERO,
+            target,
+            target_velocity,
+            steering.max_speed,
+            steering.max_prediction,
+        );
+        assert!(
+            predicted.y > 0.0,
+            "Prediction moves along the target's velocity"
+        );
+
+        let chase = pursue(Vec2::ZERO, Vec2::ZERO, target, target_velocity, &steering);
+        assert!(chase.y > 0.0, "Pursuit aims ahead of the target");
+
+        let escape = evade(Vec2::ZERO, Vec2::ZERO, target, target_velocity, &steering);
+        assert!(escape.x < 0.0 && escape.y < 0.0);
+    }
+
+    #[test]
+    fn test_separation_pushes_neighbours_apart() {
+        let push = separation(Vec2::ZERO, [Vec2::new(10.0, 0.0)], 60.0, 100.0);
+        assert!(push.x < 0.0);
+
+        let none = separation(Vec2::ZERO, [Vec2::new(100.0, 0.0)], 60.0, 100.0);
+        assert_eq!(none, Vec2::ZERO);
+
+        // Overlapping neighbours still get pushed
+        let stacked = separation(Vec2::ZERO, [Vec2::ZERO], 60.0, 100.0);
+        assert!(stacked.length() > 0.0);
+    }
+
+    #[test]
+    fn test_enemy_chases_target_through_physics() {
+        let mut app = create_enemy_app();
+        let player = spawn_body(&mut app, Vec2::new(300.0, 0.0), Vec2::ZERO);
+        app.world.entity_mut(player).insert(Player);
+        let enemy = spawn_body(&mut app, Vec2::ZERO, Vec2::ZERO);
+        app.world.entity_mut(enemy).insert((
+            Enemy,
+            Steering::new(SteeringBehavior::Seek),
+            Target(player),
+        ));
+
+        for _ in 0..30 {
+            app.world.run_schedule(FixedUpdate);
+        }
+
+        assert!(
+            position(&app, enemy).x > 0.0,
+            "Enemy moved toward its target"
+        );
+        assert_eq!(position(&app, player), Vec2::new(300.0, 0.0));
+        assert_eq!(
+            app.world.get::<Acceleration>(enemy).unwrap().0,
+            Vec2::ZERO,
+            "Integration consumed the steering force"
+        );
+    }
+
+    #[test]
+    fn test_missing_target_and_wander() {
+        let mut app = create_enemy_app();
+        let gone = app.world.spawn_empty().id();
+        app.world.despawn(gone);
+
+        let orphan = spawn_body(&mut app, Vec2::new(-100.0, 0.0), Vec2::ZERO);
+        app.world.entity_mut(orphan).insert((
+            Enemy,
+            Steering::new(SteeringBehavior::Pursue),
+            Target(gone),
+        ));
+        let wanderer = spawn_body(&mut app, Vec2::new(100.0, 0.0), Vec2::ZERO);
+        app.world
+            .entity_mut(wanderer)
+            .insert((Enemy, Steering::new(SteeringBehavior::Wander)));
+
+        for _ in 0..30 {
+            app.world.run_schedule(FixedUpdate);
+        }
+
+        assert_eq!(position(&app, orphan), Vec2::new(-100.0, 0.0));
+        let moved = position(&app, wanderer);
+        assert!(moved.is_finite());
+        assert_ne!(
+            moved,
+            Vec2::new(100.0, 0.0),
+            "Wandering enemies keep moving"
+        );
+    }
+}
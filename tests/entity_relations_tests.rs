@@ -0,0 +1,236 @@
+use bevy::ecs::system::CommandQueue;
+use bevy::prelude::*;
+use bevy_wasm_game::entity_relations::{
+    attach_child, create_parent_child_relationship, despawn_related, despawn_with_policies,
+    hierarchy_of, is_ancestor, ChildOf, Children, HierarchyCommandsExt, OnDelete, Owner, Parent,
+    RelationCommandsExt, RelationPolicies, Target,
+};
+
+/// Tests for keeping the ChildOf / Parent / Children hierarchy consistent and
+/// for the on-delete policies of relations
+
+#[cfg(test)]
+mod entity_relations_tests {
+    use super::*;
+
+    fn apply(world: &mut World, edit: impl FnOnce(&mut Commands)) {
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, world);
+        edit(&mut commands);
+        queue.apply(world);
+    }
+
+    fn children(world: &World, parent: Entity) -> Vec<Entity> {
+        world
+            .get::<Children>(parent)
+            .map_or(Vec::new(), |children| children.0.clone())
+    }
+
+    fn parent_of(world: &World, child: Entity) -> Option<Entity> {
+        let child_of = world.get::<ChildOf>(child).map(|c| c.0);
+        if let Some(parent) = child_of {
+            assert!(
+                world.get::<Parent>(parent).is_some(),
+                "The parent is marked"
+            );
+        }
+        child_of
+    }
+
+    #[test]
+    fn test_attaching_keeps_both_sides() {
+        let mut world = World::new();
+        let ship = world.spawn_empty().id();
+        let turret = world.spawn_empty().id();
+        let shield = world.spawn_empty().id();
+
+        apply(&mut world, |commands| {
+            commands.entity(ship).attach_child(turret);
+            commands.entity(shield).attach_to(ship);
+            // Attaching twice does not list the child twice
+            commands.entity(ship).attach_child(turret);
+        });
+        assert_eq!(children(&world, ship), vec![turret, shield]);
+        assert_eq!(parent_of(&world, turret), Some(ship));
+        assert_eq!(parent_of(&world, shield), Some(ship));
+
+        let mut parents = world.query_filtered::<Entity, With<Parent>>();
+        assert_eq!(parents.iter(&world).collect::<Vec<_>>(), vec![ship]);
+    }
+
+    #[test]
+    fn test_reparenting_and_detaching() {
+        let mut world = World::new();
+        let [ship, carrier, turret, stranger] = [(); 4].map(|_| world.spawn_empty().id());
+        apply(&mut world, |commands| {
+            commands.entity(ship).attach_child(turret);
+        });
+
+        apply(&mut world, |commands| {
+            commands.entity(turret).attach_to(carrier);
+        });
+        assert!(
+            world.get::<Children>(ship).is_none(),
+            "The old parent lets go"
+        );
+        assert!(world.get::<Parent>(ship).is_none(), "Without children");
+        assert_eq!(children(&world, carrier), vec![turret]);
+        assert_eq!(parent_of(&world, turret), Some(carrier));
+
+        // Only the actual parent can let go of a child
+        apply(&mut world, |commands| {
+            commands.entity(stranger).detach_child(turret);
+        });
+        assert_eq!(parent_of(&world, turret), Some(carrier));
+
+        apply(&mut world, |commands| {
+            commands.entity(carrier).detach_child(turret);
+        });
+        assert_eq!(parent_of(&world, turret), None);
+        assert!(world.get::<Children>(carrier).is_none());
+        assert!(world.get::<Parent>(carrier).is_none());
+
+        let mut queue = CommandQueue::default();
+        create_parent_child_relationship(Commands::new(&mut queue, &world), ship, turret);
+        queue.apply(&mut world);
+        assert_eq!(children(&world, ship), vec![turret]);
+        apply(&mut world, |commands| {
+            commands.entity(turret).detach_from_parent();
+        });
+        assert_eq!(parent_of(&world, turret), None);
+        assert!(children(&world, ship).is_empty());
+    }
+
+    #[test]
+    fn test_cycles_are_refused() {
+        let mut world = World::new();
+        let [root, middle, leaf] = [(); 3].map(|_| world.spawn_empty().id());
+        assert!(attach_child(&mut world, root, middle));
+        assert!(attach_child(&mut world, middle, leaf));
+
+        assert!(!attach_child(&mut world, leaf, root));
+        assert!(!attach_child(&mut world, leaf, leaf));
+        assert_eq!(parent_of(&world, root), None);
+        assert!(is_ancestor(&world, root, leaf));
+        assert!(!is_ancestor(&world, leaf, root));
+        assert_eq!(hierarchy_of(&world, root), vec![root, middle, leaf]);
+    }
+
+    #[test]
+    fn test_despawning_a_hierarchy() {
+        let mut world = World::new();
+        let [ship, turret, barrel, shield] = [(); 4].map(|_| world.spawn_empty().id());
+        attach_child(&mut world, ship, turret);
+        attach_child(&mut world, turret, barrel);
+        attach_child(&mut world, ship, shield);
+
+        // A despawned child leaves its parent's list
+        apply(&mut world, |commands| {
+            commands.entity(shield).despawn_hierarchy();
+        });
+        assert!(world.get_entity(shield).is_none());
+        assert_eq!(children(&world, ship), vec![turret]);
+
+        // A despawned parent takes its descendants along
+        apply(&mut world, |commands| {
+            commands.entity(ship).despawn_hierarchy();
+        });
+        assert_eq!(world.entities().len(), 0);
+    }
+
+    fn alive(world: &World, entity: Entity) -> bool {
+        world.get_entity(entity).is_some()
+    }
+
+    #[test]
+    fn test_default_policies() {
+        let mut world = World::new();
+        let [player, shield, rocket, enemy] = [(); 4].map(|_| world.spawn_empty().id());
+        attach_child(&mut world, player, shield);
+        world.entity_mut(rocket).insert(Owner(player));
+        world.entity_mut(enemy).insert(Target(player));
+
+        let despawned = despawn_related(&mut world, player);
+        assert_eq!(despawned, vec![player, shield], "Attachments go along");
+        assert!(alive(&world, rocket) && world.get::<Owner>(rocket).is_none());
+        assert!(alive(&world, enemy) && world.get::<Target>(enemy).is_none());
+    }
+
+    #[test]
+    fn test_reassigning_to_the_grandparent() {
+        let mut world = World::new();
+        let [ship, turret, barrel, captain, crew, cargo] =
+            [(); 6].map(|_| world.spawn_empty().id());
+        attach_child(&mut world, ship, turret);
+        attach_child(&mut world, turret, barrel);
+        world.entity_mut(crew).insert(Owner(captain));
+        world.entity_mut(cargo).insert(Owner(crew));
+        let policies = RelationPolicies {
+            child_of: OnDelete::Reassign,
+            owner: OnDelete::Reassign,
+            ..default()
+        };
+
+        despawn_with_policies(&mut world, turret, &policies);
+        assert_eq!(parent_of(&world, barrel), Some(ship));
+        assert_eq!(children(&world, ship), vec![barrel]);
+
+        despawn_with_policies(&mut world, crew, &policies);
+        assert_eq!(world.get::<Owner>(cargo), Some(&Owner(captain)));
+
+        // Nothing above: the link is dropped
+        despawn_with_policies(&mut world, ship, &policies);
+        assert_eq!(parent_of(&world, barrel), None);
+    }
+
+    #[test]
+    fn test_default_owner_and_cascades() {
+        let mut world = World::new();
+        let [house, player, sword, shield, gem, enemy] = [(); 6].map(|_| world.spawn_empty().id());
+        world.entity_mut(sword).insert(Owner(player));
+        world.entity_mut(enemy).insert(Target(player));
+        attach_child(&mut world, player, shield);
+        world.entity_mut(gem).insert(Owner(shield));
+        world.insert_resource(RelationPolicies {
+            owner: OnDelete::DefaultOwner,
+            target: OnDelete::Cascade,
+            default_owner: Some(house),
+            ..default()
+        });
+
+        apply(&mut world, |commands| {
+            commands.entity(player).despawn_related();
+        });
+        assert_eq!(world.get::<Owner>(sword), Some(&Owner(house)));
+        assert!(!alive(&world, enemy), "Targeting entities cascade");
+        assert!(!alive(&world, shield));
+        assert_eq!(world.get::<Owner>(gem), Some(&Owner(house)));
+
+        // The default owner itself cannot take over its own relations
+        let dagger = world.spawn(Owner(house)).id();
+        despawn_related(&mut world, house);
+        assert!(world.get::<Owner>(dagger).is_none());
+        assert!(world.get::<Owner>(sword).is_none());
+    }
+
+    #[test]
+    fn test_despawning_a_hierarchy_applies_the_other_policies() {
+        let mut world = World::new();
+        let [ship, turret, enemy] = [(); 3].map(|_| world.spawn_empty().id());
+        attach_child(&mut world, ship, turret);
+        world.entity_mut(enemy).insert(Target(turret));
+        world.insert_resource(RelationPolicies {
+            child_of: OnDelete::Detach,
+            ..default()
+        });
+
+        apply(&mut world, |commands| {
+            commands.entity(ship).despawn_hierarchy();
+        });
+        assert!(
+            !alive(&world, turret),
+            "The hierarchy goes whatever the policy"
+        );
+        assert!(world.get::<Target>(enemy).is_none());
+    }
+}
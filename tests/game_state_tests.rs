@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+use bevy_wasm_game::game_state::*;
+
+/// Tests for the game state machine: pausing and session cleanup
+
+#[cfg(test)]
+mod game_state_tests {
+    use super::*;
+
+    fn create_test_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<Input<KeyCode>>();
+        GameStateSystems::add_to_app(&mut app);
+        app.update();
+        app
+    }
+
+    fn set_state(app: &mut App, state: GameState) {
+        app.world.resource_mut::<NextState<GameState>>().set(state);
+        app.update();
+        assert_eq!(*app.world.resource::<State<GameState>>().get(), state);
+    }
+
+    #[test]
+    fn test_pause_freezes_time() {
+        let mut app = create_test_app();
+        set_state(&mut app, GameState::Playing);
+        set_state(&mut app, GameState::Paused);
+
+        // One more frame so the paused clock has ticked
+        app.update();
+        let accumulated = app.world.resource::<FixedTime>().accumulated();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        app.update();
+
+        assert!(app.world.resource::<Time>().is_paused());
+        assert_eq!(
+            app.world.resource::<FixedTime>().accumulated(),
+            accumulated,
+            "No fixed time may accumulate while paused"
+        );
+
+        set_state(&mut app, GameState::Playing);
+        assert!(!app.world.resource::<Time>().is_paused());
+    }
+
+    #[test]
+    fn test_session_survives_pause_and_ends_on_menu() {
+        let mut app = create_test_app();
+        set_state(&mut app, GameState::Playing);
+        let player = app.world.spawn(GameplayEntity).id();
+
+        set_state(&mut app, GameState::Paused);
+        set_state(&mut app, GameState::Playing);
+        assert!(
+            app.world.get_entity(player).is_some(),
+            "Pausing keeps the session"
+        );
+
+        set_state(&mut app, GameState::Paused);
+        set_state(&mut app, GameState::MainMenu);
+        assert!(
+            app.world.get_entity(player).is_none(),
+            "Quitting ends the session"
+        );
+    }
+
+    #[test]
+    fn test_overlays_are_scoped_to_their_state() {
+        let mut app = create_test_app();
+        let mut overlays = app.world.query::<&DespawnOnExit>();
+        assert_eq!(overlays.iter(&app.world).count(), 1, "Main menu is shown");
+
+        set_state(&mut app, GameState::Playing);
+        assert_eq!(overlays.iter(&app.world).count(), 0);
+
+        set_state(&mut app, GameState::Paused);
+        assert_eq!(
+            overlays.iter(&app.world).count(),
+            1,
+            "Pause overlay is shown"
+        );
+
+        set_state(&mut app, GameState::Playing);
+        assert_eq!(overlays.iter(&app.world).count(), 0);
+    }
+}
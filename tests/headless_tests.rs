@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+use bevy_wasm_game::bots::*;
+use bevy_wasm_game::components::*;
+use bevy_wasm_game::headless::*;
+use bevy_wasm_game::replay::*;
+use bevy_wasm_game::rollback::NetInput;
+use bevy_wasm_game::save::*;
+
+/// Tests for the headless simulation, input replays and bots
+
+#[cfg(test)]
+mod headless_tests {
+    use super::*;
+
+    const RIGHT: NetInput = NetInput {
+        x: 127,
+        y: 0,
+        buttons: 0,
+    };
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn run(config: &HeadlessConfig) -> (App, HeadlessSummary) {
+        let mut app = headless_app(config).unwrap();
+        let summary = run_headless(&mut app).unwrap();
+        (app, summary)
+    }
+
+    #[test]
+    fn test_headless_run_simulates_the_requested_ticks() {
+        let config = HeadlessConfig {
+            ticks: Some(90),
+            ..default()
+        };
+        let (mut app, summary) = run(&config);
+        assert_eq!(summary.ticks, 90);
+        assert_eq!(app.world.resource::<SimulationTick>().0, 90);
+        assert_eq!(
+            summary.players.len(),
+            1,
+            "The default arena has one spawn point"
+        );
+        assert!(summary.to_string().starts_with("90 ticks"));
+
+        let players = app
+            .world
+            .query_filtered::<&InputSlot, With<Player>>()
+            .iter(&app.world)
+            .count();
+        assert_eq!(players, 1);
+    }
+
+    #[test]
+    fn test_bot_runs_are_deterministic() {
+        let config = HeadlessConfig {
+            level: Some("assets/levels/duel.ron".into()),
+            ticks: Some(120),
+            seed: Some(11),
+            bot: Some(BotBehavior::RandomWalk),
+            ..default()
+        };
+        let (_, first) = run(&config);
+        let (_, second) = run(&config);
+        assert_eq!(first, second);
+        assert!(first
+            .players
+            .iter()
+            .all(|player| player.velocity != Vec2::ZERO));
+
+        let other_seed = HeadlessConfig {
+            seed: Some(12),
+            ..config
+        };
+        assert_ne!(run(&other_seed).1, first);
+    }
+
+    #[test]
+    fn test_replays_drive_players_by_slot() {
+        let mut replay = InputReplay::new(3, Some("assets/levels/duel.ron".into()));
+        replay.record(0, 1, RIGHT);
+        replay.record(5, 1, RIGHT);
+        replay.record(30, 1, NetInput::default());
+        assert_eq!(replay.inputs.len(), 2, "Only changes are stored");
+        assert_eq!(replay.input(10, 1), RIGHT);
+        assert_eq!(replay.input(10, 0), NetInput::default());
+        assert_eq!(replay.input(40, 1), NetInput::default());
+        assert_eq!(replay.last_tick(), 30);
+
+        let source = replay.to_ron().unwrap();
+        assert_eq!(InputReplay::from_ron(&source, "memory").unwrap(), replay);
+        assert!(InputReplay::from_ron("(seed: ", "memory").is_err());
+
+        let path = std::env::temp_dir().join("headless_tests_replay.ron");
+        replay.write(&path).unwrap();
+        let config =
+            HeadlessConfig::from_args(&args(&format!("--ticks 60 --replay {}", path.display())))
+                .unwrap();
+        let (_, summary) = run(&config);
+        std::fs::remove_file(&path).ok();
+
+        // The replay names the duel level, slot 1 is the player on the right
+        let [left, right] = &summary.players[..] else {
+            panic!("expected two players, got {:?}", summary.players);
+        };
+        assert_eq!(left.position, Vec2::new(-400.0, 0.0));
+        assert_ne!(right.position, Vec2::new(400.0, 0.0));
+        assert_eq!(right.position.y, 0.0, "Pushed along x only");
+    }
+
+    #[test]
+    fn test_final_state_export() {
+        let path = std::env::temp_dir().join("headless_tests_export.ron");
+        let config = HeadlessConfig {
+            ticks: Some(10),
+            export: Some(path.clone()),
+            ..default()
+        };
+        run(&config);
+        let save = SaveFile::read(&path, &SaveMigrations::default()).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!(save
+            .entities
+            .iter()
+            .any(|entity| entity.kind == SavedKind::Player));
+    }
+
+    #[test]
+    fn test_headless_arguments() {
+        let config = HeadlessConfig::from_args(&args(
+            "level.ron --ticks 500 --seed 9 --bot random-walk --export out.ron",
+        ))
+        .unwrap();
+        assert_eq!(config.level, Some("level.ron".into()));
+        assert_eq!(config.ticks, Some(500));
+        assert_eq!(config.seed, Some(9));
+        assert_eq!(config.bot, Some(BotBehavior::RandomWalk));
+        assert!(!config.realtime && !config.soak);
+
+        let soak = HeadlessConfig::from_args(&args("--bot wall-hug --report failure.ron")).unwrap();
+        assert!(soak.soak);
+        assert_eq!(soak.report, Some("failure.ron".into()));
+
+        let server =
+            HeadlessConfig::from_args(&args("--realtime --serve 0.0.0.0:7000 127.0.0.1:7100"))
+                .unwrap();
+        assert!(server.realtime && server.ticks.is_none());
+
+        // Options after the network addresses still count
+        let server = HeadlessConfig::from_args(&args(
+            "duel.ron --serve 0.0.0.0:7000 127.0.0.1:7100 127.0.0.1:7101 --seed 4 --soak",
+        ))
+        .unwrap();
+        assert_eq!(server.level, Some("duel.ron".into()));
+        assert_eq!(server.seed, Some(4));
+        assert!(server.soak);
+        assert!(server.realtime, "A server runs in real time");
+        let client =
+            HeadlessConfig::from_args(&args("--connect 127.0.0.1:7100 127.0.0.1:7000 --ticks 30"))
+                .unwrap();
+        assert_eq!(client.ticks, Some(30));
+        assert!(client.realtime, "A client runs in real time");
+
+        assert!(HeadlessConfig::from_args(&args("--ticks")).is_err());
+        assert!(HeadlessConfig::from_args(&args("--ticks many")).is_err());
+        assert!(HeadlessConfig::from_args(&args("--bot dancer")).is_err());
+        assert!(HeadlessConfig::from_args(&args("--fly")).is_err());
+        assert!(HeadlessConfig::from_args(&args("a.ron b.ron")).is_err());
+    }
+}
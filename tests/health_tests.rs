@@ -0,0 +1,258 @@
+use bevy::prelude::*;
+use bevy_wasm_game::components::*;
+use bevy_wasm_game::entity_relations::{attach_child, Target};
+use bevy_wasm_game::game_state::GameplayReset;
+use bevy_wasm_game::health::*;
+use bevy_wasm_game::input::{ControlledBy, KeyboardScheme};
+use bevy_wasm_game::level::*;
+use bevy_wasm_game::projectiles::*;
+use bevy_wasm_game::race::RaceProgress;
+use bevy_wasm_game::systems::*;
+
+/// Tests for health, damage, death and respawning
+
+#[cfg(test)]
+mod health_tests {
+    use super::*;
+
+    const TICK: f32 = 1.0 / 60.0;
+
+    fn health_world() -> (World, Schedule) {
+        let mut world = World::new();
+        world.insert_resource(FixedTime::new_from_secs(TICK));
+        world.init_resource::<CurrentLevel>();
+        world.init_resource::<DamageSettings>();
+        world.init_resource::<PendingRespawns>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<DeathEvent>>();
+        world.init_resource::<Events<ProjectileHit>>();
+        world.init_resource::<Events<WallImpact>>();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems(
+            (
+                tick_invulnerability,
+                projectile_damage,
+                wall_impact_damage,
+                apply_damage,
+                respawn_players,
+            )
+                .chain(),
+        );
+        (world, schedule)
+    }
+
+    fn deaths(world: &World) -> Vec<DeathEvent> {
+        let events = world.resource::<Events<DeathEvent>>();
+        events.get_reader().iter(events).copied().collect()
+    }
+
+    #[test]
+    fn test_invulnerability_ignores_damage_until_it_runs_out() {
+        let mut health = Health::new(50.0);
+        assert_eq!(health.damage(20.0), 20.0);
+        assert!(health.is_invulnerable());
+        assert_eq!(health.damage(20.0), 0.0, "Ignored during i-frames");
+
+        health.invulnerable_left = 0.0;
+        assert_eq!(health.damage(100.0), 30.0, "Never loses more than it has");
+        assert!(health.is_dead());
+        health.invulnerable_left = 0.0;
+        assert_eq!(health.damage(10.0), 0.0, "The dead take no damage");
+    }
+
+    #[test]
+    fn test_projectile_hits_damage_and_knock_back() {
+        let (mut world, mut schedule) = health_world();
+        let shooter = world.spawn(Transform::default()).id();
+        let target = world
+            .spawn((
+                Transform::from_xyz(100.0, 0.0, 0.0),
+                LinearVelocity::default(),
+                Health::new(30.0),
+            ))
+            .id();
+
+        world.send_event(ProjectileHit {
+            projectile: Entity::PLACEHOLDER,
+            owner: Some(shooter),
+            target: Some(target),
+            position: Vec2::new(90.0, 0.0),
+            damage: 10.0,
+        });
+        schedule.run(&mut world);
+
+        assert_eq!(world.get::<Health>(target).unwrap().current, 20.0);
+        let velocity = world.get::<LinearVelocity>(target).unwrap().0;
+        assert!(
+            velocity.x > 0.0 && velocity.y == 0.0,
+            "Pushed away from the hit"
+        );
+        assert!(deaths(&world).is_empty());
+    }
+
+    #[test]
+    fn test_death_despawns_and_reports_the_killer() {
+        let (mut world, mut schedule) = health_world();
+        let killer = world.spawn(Transform::default()).id();
+        let victim = world
+            .spawn((Transform::from_xyz(5.0, 5.0, 0.0), Health::new(10.0)))
+            .id();
+
+        world.send_event(DamageEvent {
+            target: victim,
+            source: Some(killer),
+            amount: 25.0,
+            knockback: Vec2::ZERO,
+        });
+        schedule.run(&mut world);
+
+        assert!(world.get_entity(victim).is_none());
+        let deaths = deaths(&world);
+        assert_eq!(deaths.len(), 1);
+        assert_eq!(deaths[0].entity, victim);
+        assert_eq!(deaths[0].killer, Some(killer));
+        assert_eq!(deaths[0].position, Vec2::new(5.0, 5.0));
+        assert!(world.resource::<PendingRespawns>().entries.is_empty());
+    }
+
+    #[test]
+    fn test_players_respawn_after_delay_and_keep_their_pursuers() {
+        let (mut world, mut schedule) = health_world();
+        let player = world
+            .spawn((
+                Transform::from_xyz(300.0, 0.0, 0.0),
+                Player,
+                Health::new(10.0),
+                DeathBehavior::Respawn { delay: 0.5 },
+            ))
+            .id();
+        let hunter = world.spawn(Target(player)).id();
+        let shield = world.spawn_empty().id();
+        attach_child(&mut world, player, shield);
+
+        world.send_event(DamageEvent {
+            target: player,
+            source: None,
+            amount: 10.0,
+            knockback: Vec2::ZERO,
+        });
+        schedule.run(&mut world);
+        assert!(world.get_entity(player).is_none());
+        assert!(world.get_entity(shield).is_none(), "Attachments go with it");
+        assert!(world.get::<Target>(hunter).is_none(), "Let go of the dead");
+        assert_eq!(world.resource::<PendingRespawns>().entries.len(), 1);
+
+        for _ in 0..=(0.5 / TICK).ceil() as usize {
+            schedule.run(&mut world);
+        }
+        let respawned: Vec<(Entity, Vec2, f32)> = world
+            .query_filtered::<(Entity, &Transform, &Health), With<Player>>()
+            .iter(&world)
+            .map(|(entity, transform, health)| {
+                (entity, transform.translation.truncate(), health.current)
+            })
+            .collect();
+        assert_eq!(respawned.len(), 1);
+        let (new_player, position, health) = respawned[0];
+        let spawn_points = &world.resource::<CurrentLevel>().definition.spawn_points;
+        assert!(spawn_points.contains(&position));
+        assert_eq!(health, 100.0, "Respawned at full health");
+        assert_eq!(world.get::<Target>(hunter).unwrap().0, new_player);
+        assert!(world.resource::<PendingRespawns>().entries.is_empty());
+    }
+
+    #[test]
+    fn test_respawned_players_keep_their_binding_and_race() {
+        let (mut world, mut schedule) = health_world();
+        let mut race = RaceProgress::new(3, 1);
+        race.pass_checkpoint();
+        race.ticks = 100;
+        let player = world
+            .spawn((
+                Transform::default(),
+                Player,
+                Health::new(10.0),
+                DeathBehavior::Respawn { delay: 0.5 },
+                ControlledBy::keyboard(KeyboardScheme::B),
+                race,
+            ))
+            .id();
+
+        world.send_event(DamageEvent {
+            target: player,
+            source: None,
+            amount: 10.0,
+            knockback: Vec2::ZERO,
+        });
+        let waited = (0.5 / TICK).ceil() as u32 + 1;
+        for _ in 0..waited {
+            schedule.run(&mut world);
+        }
+        let (binding, race) = world
+            .query_filtered::<(&ControlledBy, &RaceProgress), With<Player>>()
+            .single(&world);
+        assert_eq!(*binding, ControlledBy::keyboard(KeyboardScheme::B));
+        assert_eq!(race.next, 1, "The run carries on where it was");
+        assert_eq!(race.ticks, 100 + waited - 1, "The clock ran while dead");
+    }
+
+    #[test]
+    fn test_resets_cancel_pending_respawns() {
+        let mut world = World::new();
+        world.init_resource::<Events<GameplayReset>>();
+        world.insert_resource(PendingRespawns {
+            entries: vec![PendingRespawn {
+                previous: Entity::PLACEHOLDER,
+                time_left: 1.0,
+                controlled_by: None,
+                rollback_player: None,
+                race: None,
+                chasers: Vec::new(),
+            }],
+        });
+        let mut schedule = Schedule::new();
+        schedule.add_systems(clear_pending_respawns);
+
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<PendingRespawns>().entries.len(), 1);
+        world.send_event(GameplayReset);
+        schedule.run(&mut world);
+        assert!(world.resource::<PendingRespawns>().entries.is_empty());
+    }
+
+    #[test]
+    fn test_wall_impact_damage_is_optional() {
+        let (mut world, mut schedule) = health_world();
+        let body = world.spawn((Transform::default(), Health::new(100.0))).id();
+        let impact = WallImpact {
+            entity: body,
+            position: Vec2::ZERO,
+            normal: Vec2::X,
+            speed: 2000.0,
+        };
+
+        world.send_event(impact);
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Health>(body).unwrap().current, 100.0);
+
+        world.resource_mut::<DamageSettings>().wall_damage = true;
+        world.send_event(impact);
+        schedule.run(&mut world);
+        let settings = world.resource::<DamageSettings>();
+        let expected = 100.0 - settings.wall_damage_for(2000.0);
+        assert!(settings.wall_damage_for(settings.wall_damage_min_speed) == 0.0);
+        assert_eq!(world.get::<Health>(body).unwrap().current, expected);
+    }
+
+    #[test]
+    fn test_safest_spawn_point_avoids_threats() {
+        let points = [Vec2::new(-100.0, 0.0), Vec2::new(100.0, 0.0)];
+        assert_eq!(safest_spawn_point(&points, &[]), Some(points[0]));
+        assert_eq!(
+            safest_spawn_point(&points, &[Vec2::new(-90.0, 0.0)]),
+            Some(points[1])
+        );
+        assert_eq!(safest_spawn_point(&[], &[Vec2::ZERO]), None);
+    }
+}
@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use bevy_wasm_game::components::*;
+use bevy_wasm_game::input::*;
+use bevy_wasm_game::mouse_control::ControlMode;
+
+/// Tests for per-player input bindings
+/// Each player must only react to the input source it is bound to
+
+#[cfg(test)]
+mod input_tests {
+    use super::*;
+
+    fn create_input_world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Input<KeyCode>>();
+        world.init_resource::<Input<MouseButton>>();
+        world.init_resource::<Input<GamepadButton>>();
+        world.init_resource::<Axis<GamepadAxis>>();
+        world.init_resource::<ControlMode>();
+        world
+    }
+
+    fn spawn_player(world: &mut World, controlled_by: ControlledBy) -> Entity {
+        world
+            .spawn((
+                Sprite::default(),
+                Player,
+                MovementIntent::default(),
+                controlled_by,
+            ))
+            .id()
+    }
+
+    fn run_input(world: &mut World) {
+        let mut schedule = Schedule::new();
+        schedule.add_systems(handle_input);
+        schedule.run(world);
+    }
+
+    #[test]
+    fn test_keyboard_schemes_are_independent() {
+        let mut world = create_input_world();
+        let player_a = spawn_player(&mut world, ControlledBy::keyboard(KeyboardScheme::A));
+        let player_b = spawn_player(&mut world, ControlledBy::keyboard(KeyboardScheme::B));
+
+        world.resource_mut::<Input<KeyCode>>().press(KeyCode::W);
+        run_input(&mut world);
+
+        let intent_a = world.get::<MovementIntent>(player_a).unwrap();
+        let intent_b = world.get::<MovementIntent>(player_b).unwrap();
+        assert!(intent_a.direction.y > 0.0, "Scheme A should move up on W");
+        assert_eq!(intent_b.direction, Vec2::ZERO, "Scheme B should ignore W");
+    }
+
+    #[test]
+    fn test_boost_only_affects_bound_player() {
+        let mut world = create_input_world();
+        let player_a = spawn_player(&mut world, ControlledBy::keyboard(KeyboardScheme::A));
+        let player_b = spawn_player(&mut world, ControlledBy::keyboard(KeyboardScheme::B));
+
+        {
+            let mut keyboard = world.resource_mut::<Input<KeyCode>>();
+            keyboard.press(KeyCode::D);
+            keyboard.press(KeyCode::Right);
+            keyboard.press(KeyCode::ShiftRight);
+        }
+        run_input(&mut world);
+
+        let intent_a = world.get::<MovementIntent>(player_a).unwrap();
+        let intent_b = world.get::<MovementIntent>(player_b).unwrap();
+        assert_eq!(intent_a.direction, intent_b.direction);
+        assert!(!intent_a.boost, "Right shift belongs to scheme B");
+        assert!(intent_b.boost, "Only scheme B boosts");
+    }
+
+    #[test]
+    fn test_gamepad_analog_stick() {
+        let mut world = create_input_world();
+        let pad_player = spawn_player(&mut world, ControlledBy::gamepad(1));
+        let other_pad = spawn_player(&mut world, ControlledBy::gamepad(0));
+
+        world.resource_mut::<Axis<GamepadAxis>>().set(
+            GamepadAxis::new(Gamepad::new(1), GamepadAxisType::LeftStickX),
+            0.5,
+        );
+        run_input(&mut world);
+
+        let intent = world.get::<MovementIntent>(pad_player).unwrap();
+        assert_eq!(
+            intent.direction,
+            Vec2::new(0.5, 0.0),
+            "Half stick gives half input"
+        );
+        assert_eq!(
+            world.get::<MovementIntent>(other_pad).unwrap().direction,
+            Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn test_read_keyboard_normalizes_diagonals() {
+        let mut keyboard = Input::<KeyCode>::default();
+        keyboard.press(KeyCode::Up);
+        keyboard.press(KeyCode::Left);
+
+        let input = read_keyboard(&keyboard, &KeyboardScheme::B.bindings());
+        assert!((input.direction.length() - 1.0).abs() < 1e-5);
+        assert!(!input.boost);
+    }
+}
@@ -0,0 +1,229 @@
+use bevy::prelude::*;
+use bevy_wasm_game::collectibles::*;
+use bevy_wasm_game::components::*;
+use bevy_wasm_game::enemy::*;
+use bevy_wasm_game::game_state::*;
+use bevy_wasm_game::input::{ControlledBy, KeyboardScheme};
+use bevy_wasm_game::level::*;
+use bevy_wasm_game::resources::*;
+use bevy_wasm_game::systems::*;
+use std::path::PathBuf;
+
+/// Tests for the level format, validation and loading
+
+#[cfg(test)]
+mod level_tests {
+    use super::*;
+
+    const SMALL_LEVEL: &str = r#"(
+        name: "Small",
+        bounds: (min: (-100.0, -100.0), max: (100.0, 100.0), friction: 0.2),
+        spawn_points: [(0.0, 0.0)],
+        obstacles: [(position: (50.0, 50.0), size: (20.0, 20.0))],
+        pickups: [(position: (-50.0, 0.0)), (position: (0.0, -50.0), value: Some(30))],
+        enemies: [(position: (80.0, -80.0), behavior: Evade)],
+    )"#;
+
+    fn write_level(name: &str, source: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("level_tests_{}_{}.ron", std::process::id(), name));
+        std::fs::write(&path, source).unwrap();
+        path
+    }
+
+    fn count<T: Component>(world: &mut World) -> usize {
+        world.query::<&T>().iter(world).count()
+    }
+
+    #[test]
+    fn test_builtin_level_matches_default_bounds() {
+        let level = LevelDefinition::builtin();
+        let bounds = level.world_bounds();
+        let default_bounds = WorldBounds::default_bounds();
+        assert_eq!(bounds.min, default_bounds.min);
+        assert_eq!(bounds.max, default_bounds.max);
+        assert_eq!(bounds.friction, default_bounds.friction);
+        assert_eq!(bounds.bounce_factor, default_bounds.bounce_factor);
+        assert!(!level.spawn_points.is_empty());
+    }
+
+    #[test]
+    fn test_parse_level() {
+        let level = LevelDefinition::from_ron(SMALL_LEVEL, "small.ron").unwrap();
+        assert_eq!(level.name, "Small");
+        assert_eq!(
+            level.bounds.bounce_factor, 0.8,
+            "Bounce factor has a default"
+        );
+        assert_eq!(level.obstacles.len(), 1);
+        assert_eq!(level.pickups[1].value, Some(30));
+        assert_eq!(level.enemies[0].behavior, SteeringBehavior::Evade);
+    }
+
+    #[test]
+    fn test_errors_name_file_and_field() {
+        let outside = SMALL_LEVEL.replace("(80.0, -80.0)", "(500.0, -80.0)");
+        let error = LevelDefinition::from_ron(&outside, "levels/small.ron").unwrap_err();
+        match &error {
+            LevelError::Invalid { path, field, .. } => {
+                assert_eq!(path, &PathBuf::from("levels/small.ron"));
+                assert_eq!(field, "enemies[0].position");
+            }
+            other => panic!("Expected a validation error, got {other:?}"),
+        }
+        let message = error.to_string();
+        assert!(message.contains("levels/small.ron") && message.contains("enemies[0].position"));
+
+        let missing = SMALL_LEVEL.replace("spawn_points: [(0.0, 0.0)],", "");
+        let error = LevelDefinition::from_ron(&missing, "broken.ron").unwrap_err();
+        assert!(matches!(error, LevelError::Parse { .. }));
+        let message = error.to_string();
+        assert!(message.starts_with("broken.ron:"));
+        assert!(message.contains("spawn_points"), "{message}");
+
+        let error = LevelDefinition::load("does/not/exist.ron").unwrap_err();
+        assert!(matches!(error, LevelError::Io { .. }));
+    }
+
+    #[test]
+    fn test_rejects_gaining_bounces_and_blocked_spawns() {
+        let invalid_field = |source: &str| match LevelDefinition::from_ron(source, "small.ron") {
+            Err(LevelError::Invalid { field, .. }) => field,
+            other => panic!("Expected a validation error, got {other:?}"),
+        };
+
+        let bouncy = SMALL_LEVEL.replace("friction: 0.2)", "friction: 0.2, bounce_factor: 2.0)");
+        assert_eq!(invalid_field(&bouncy), "bounds.bounce_factor");
+        let still = SMALL_LEVEL.replace("friction: 0.2)", "friction: 0.2, bounce_factor: 1.0)");
+        assert!(LevelDefinition::from_ron(&still, "small.ron").is_ok());
+
+        let blocked = SMALL_LEVEL.replace("(position: (50.0, 50.0)", "(position: (5.0, 5.0)");
+        assert_eq!(invalid_field(&blocked), "spawn_points[0]");
+    }
+
+    #[test]
+    fn test_each_local_player_gets_its_own_input() {
+        let mut world = World::new();
+        let duel = LevelDefinition::load("assets/levels/duel.ron").unwrap();
+        let mut queue = bevy::ecs::system::CommandQueue::default();
+        let players = spawn_level(&mut Commands::new(&mut queue, &world), &duel);
+        queue.apply(&mut world);
+
+        let bindings: Vec<Option<ControlledBy>> = players
+            .iter()
+            .map(|player| world.get::<ControlledBy>(*player).copied())
+            .collect();
+        assert_eq!(
+            bindings,
+            vec![
+                Some(ControlledBy::keyboard(KeyboardScheme::A)),
+                Some(ControlledBy::keyboard(KeyboardScheme::B)),
+            ]
+        );
+        assert_eq!(ControlledBy::for_player(3), ControlledBy::gamepad(1));
+
+        // A lone player keeps reading every binding
+        let mut queue = bevy::ecs::system::CommandQueue::default();
+        let level = LevelDefinition::builtin();
+        let players = spawn_level(&mut Commands::new(&mut queue, &world), &level);
+        queue.apply(&mut world);
+        assert_eq!(world.get::<ControlledBy>(players[0]), None);
+    }
+
+    #[test]
+    fn test_reload_replaces_the_arena() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<Input<KeyCode>>()
+            .add_state::<GameState>();
+        LevelSystems::add_to_app(&mut app);
+
+        app.world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        app.update();
+        let builtin = LevelDefinition::builtin();
+        assert_eq!(count::<Player>(&mut app.world), builtin.spawn_points.len());
+        assert_eq!(count::<Enemy>(&mut app.world), builtin.enemies.len());
+
+        let path = write_level("reload", SMALL_LEVEL);
+        app.world.send_event(LoadLevel(path.clone()));
+        app.update();
+
+        assert_eq!(count::<Player>(&mut app.world), 1);
+        assert_eq!(count::<Enemy>(&mut app.world), 1);
+        assert_eq!(count::<Obstacle>(&mut app.world), 1);
+        assert_eq!(count::<Collectible>(&mut app.world), 2);
+        assert_eq!(app.world.resource::<WorldBounds>().max, Vec2::splat(100.0));
+        assert_eq!(app.world.resource::<CurrentLevel>().path, path);
+
+        // An invalid file keeps the current arena
+        let broken = write_level("broken", "(name: \"Broken\")");
+        app.world.send_event(LoadLevel(broken.clone()));
+        app.update();
+        assert_eq!(
+            app.world.resource::<CurrentLevel>().definition.name,
+            "Small"
+        );
+        assert_eq!(count::<Enemy>(&mut app.world), 1);
+
+        std::fs::remove_file(path).ok();
+        std::fs::remove_file(broken).ok();
+    }
+
+    #[test]
+    fn test_bodies_bounce_off_obstacles() {
+        let mut world = World::new();
+        world.init_resource::<Events<WallImpact>>();
+        world.spawn((Transform::default(), Obstacle::new(Vec2::splat(100.0))));
+        let body = world
+            .spawn((
+                Transform::from_xyz(-60.0, 0.0, 0.0),
+                LinearVelocity(Vec2::new(200.0, 0.0)),
+                CollisionRadius(20.0),
+            ))
+            .id();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems(obstacle_collision);
+        schedule.run(&mut world);
+
+        let transform = world.get::<Transform>(body).unwrap();
+        assert_eq!(
+            transform.translation.x, -70.0,
+            "Pushed out to touch the face"
+        );
+        assert_eq!(world.get::<LinearVelocity>(body).unwrap().0.x, -200.0);
+
+        let impacts = world.resource::<Events<WallImpact>>();
+        let impact = impacts.get_reader().iter(impacts).next().copied().unwrap();
+        assert_eq!(impact.entity, body);
+        assert_eq!(impact.normal, Vec2::NEG_X);
+        assert_eq!(impact.speed, 200.0);
+
+        assert!(
+            obstacle_penetration(Vec2::new(-80.0, 0.0), 20.0, Vec2::ZERO, Vec2::splat(50.0))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_gameplay_entities_are_tagged() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<Input<KeyCode>>()
+            .add_state::<GameState>();
+        LevelSystems::add_to_app(&mut app);
+        app.world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        app.update();
+
+        let untagged = app
+            .world
+            .query_filtered::<Entity, (With<Sprite>, Without<GameplayEntity>)>()
+            .iter(&app.world)
+            .count();
+        assert_eq!(untagged, 0, "Cleanup must catch every level entity");
+    }
+}
@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+use bevy_wasm_game::components::*;
+use bevy_wasm_game::modifiers::*;
+use bevy_wasm_game::power_ups::*;
+
+/// Tests for stat modifiers and power-up pickups
+
+#[cfg(test)]
+mod modifiers_tests {
+    use super::*;
+
+    const TICK: f32 = 1.0 / 60.0;
+
+    fn source() -> ModifierSource {
+        ModifierSource::PowerUp(PowerUpKind::Speed)
+    }
+
+    #[test]
+    fn test_value_adds_then_multiplies_and_clamps() {
+        let mut stats = StatModifiers::new(100.0, 0.9, 1.0);
+        stats.apply(
+            StatModifier::add(Stat::Force, 50.0, source()),
+            StackingRule::Stack { max: 5 },
+        );
+        stats.apply(
+            StatModifier::mul(Stat::Force, 2.0, source()),
+            StackingRule::Stack { max: 5 },
+        );
+        assert_eq!(stats.value(Stat::Force), 300.0);
+        assert_eq!(stats.value(Stat::Mass), 1.0, "Other stats are untouched");
+
+        stats.apply(
+            StatModifier::mul(Stat::Friction, 5.0, source()),
+            StackingRule::Refresh,
+        );
+        assert!(
+            stats.value(Stat::Friction) < 1.0,
+            "Friction never stops a body dead"
+        );
+        stats.apply(
+            StatModifier::add(Stat::Mass, -10.0, source()),
+            StackingRule::Refresh,
+        );
+        assert!(stats.value(Stat::Mass) > 0.0);
+    }
+
+    #[test]
+    fn test_stacking_rules() {
+        let timed = |factor| StatModifier::mul(Stat::Force, factor, source()).lasting(2.0);
+
+        let mut stats = StatModifiers::new(100.0, 0.9, 1.0);
+        stats.apply(timed(2.0), StackingRule::Refresh);
+        stats.tick(1.0);
+        stats.apply(timed(2.0), StackingRule::Refresh);
+        assert_eq!(stats.modifiers().len(), 1);
+        assert_eq!(
+            stats.modifiers()[0].remaining,
+            Some(2.0),
+            "Refresh restarts"
+        );
+
+        let mut stats = StatModifiers::new(100.0, 0.9, 1.0);
+        stats.apply(timed(2.0), StackingRule::Extend);
+        stats.apply(timed(2.0), StackingRule::Extend);
+        assert_eq!(stats.modifiers().len(), 1);
+        assert_eq!(
+            stats.modifiers()[0].remaining,
+            Some(4.0),
+            "Extend adds time"
+        );
+
+        let mut stats = StatModifiers::new(100.0, 0.9, 1.0);
+        stats.apply(timed(2.0), StackingRule::Ignore);
+        stats.apply(timed(3.0), StackingRule::Ignore);
+        assert_eq!(stats.value(Stat::Force), 200.0, "Ignore keeps the first");
+
+        let mut stats = StatModifiers::new(100.0, 0.9, 1.0);
+        for _ in 0..3 {
+            stats.apply(timed(2.0), StackingRule::Stack { max: 2 });
+        }
+        assert_eq!(stats.value(Stat::Force), 400.0, "Stacks up to the cap");
+
+        let other = ModifierSource::PowerUp(PowerUpKind::HeavyMass);
+        stats.apply(
+            StatModifier::mul(Stat::Force, 2.0, other),
+            StackingRule::Ignore,
+        );
+        assert_eq!(
+            stats.value(Stat::Force),
+            800.0,
+            "Sources stack independently"
+        );
+        stats.remove_source(other);
+        assert_eq!(stats.value(Stat::Force), 400.0);
+    }
+
+    #[test]
+    fn test_timed_modifiers_expire() {
+        let mut stats = StatModifiers::new(100.0, 0.9, 1.0);
+        stats.apply(
+            StatModifier::mul(Stat::Force, 2.0, source()).lasting(0.5),
+            StackingRule::Refresh,
+        );
+        stats.apply(
+            StatModifier::add(Stat::Mass, 1.0, source()),
+            StackingRule::Refresh,
+        );
+
+        assert_eq!(stats.tick(0.25), 0);
+        assert_eq!(stats.value(Stat::Force), 200.0);
+        assert_eq!(stats.tick(0.25), 1);
+        assert_eq!(stats.value(Stat::Force), 100.0);
+        assert_eq!(stats.value(Stat::Mass), 2.0, "Permanent modifiers stay");
+    }
+
+    #[test]
+    fn test_update_writes_friction_and_mass() {
+        let mut world = World::new();
+        world.insert_resource(FixedTime::new_from_secs(TICK));
+        let mut stats = StatModifiers::default();
+        PowerUpKind::HeavyMass.apply_to(&mut stats);
+        PowerUpKind::LowFriction.apply_to(&mut stats);
+        let body = world
+            .spawn((stats, Friction::default(), Mass::default()))
+            .id();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems(update_stat_modifiers);
+        schedule.run(&mut world);
+
+        assert_eq!(world.get::<Mass>(body).unwrap().0, 3.0);
+        let friction = world.get::<Friction>(body).unwrap().0;
+        assert!((friction - Friction::default().0 * 0.2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_players_collect_power_ups_once() {
+        let mut world = World::new();
+        world.init_resource::<Events<PowerUpCollected>>();
+        let player = world
+            .spawn((
+                Transform::default(),
+                Player,
+                CollisionRadius::default(),
+                StatModifiers::default(),
+            ))
+            .id();
+        let other = world
+            .spawn((
+                Transform::default(),
+                Player,
+                CollisionRadius::default(),
+                StatModifiers::default(),
+            ))
+            .id();
+        world.spawn((Transform::default(), PowerUp::new(PowerUpKind::Speed)));
+        world.spawn((
+            Transform::from_xyz(500.0, 0.0, 0.0),
+            PowerUp::new(PowerUpKind::Speed),
+        ));
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems(detect_power_up_pickups);
+        schedule.run(&mut world);
+
+        let granted = |entity| {
+            world
+                .get::<StatModifiers>(entity)
+                .unwrap()
+                .modifiers()
+                .len()
+        };
+        assert_eq!(
+            granted(player) + granted(other),
+            1,
+            "Only one player gets it"
+        );
+        assert_eq!(world.query::<&PowerUp>().iter(&world).count(), 1);
+        let events = world.resource::<Events<PowerUpCollected>>();
+        let collected: Vec<_> = events.get_reader().iter(events).copied().collect();
+        assert_eq!(collected.len(), 1);
+        assert_eq!(collected[0].kind, PowerUpKind::Speed);
+    }
+}
@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+use bevy_wasm_game::mouse_control::*;
+
+/// Tests for the mouse steering behaviors
+/// These run the steering math against a simplified copy of the physics integration
+
+#[cfg(test)]
+mod mouse_control_tests {
+    use super::*;
+
+    #[test]
+    fn test_seek_points_at_target() {
+        let acceleration = seek_acceleration(Vec2::ZERO, Vec2::new(100.0, 0.0), 3000.0, 10.0);
+        assert_eq!(acceleration, Vec2::new(3000.0, 0.0));
+    }
+
+    #[test]
+    fn test_seek_dead_zone() {
+        let acceleration = seek_acceleration(Vec2::ZERO, Vec2::new(5.0, 0.0), 3000.0, 10.0);
+        assert_eq!(
+            acceleration,
+            Vec2::ZERO,
+            "Cursor inside the dead zone should not steer"
+        );
+    }
+
+    #[test]
+    fn test_arrive_settles_on_destination() {
+        let settings = MouseSteeringSettings::default();
+        let destination = Vec2::new(400.0, -250.0);
+        let friction = 0.95_f32;
+        let delta = 1.0 / 60.0;
+
+        let mut position = Vec2::ZERO;
+        let mut velocity = Vec2::ZERO;
+        let mut max_distance_past = 0.0_f32;
+
+        // Ten seconds of fixed ticks
+        for _ in 0..600 {
+            let acceleration = arrive_acceleration(position, velocity, destination, &settings);
+            velocity += acceleration * delta;
+            velocity *= (1.0 - friction).powf(delta);
+            position += velocity * delta;
+
+            let overshoot = (position - Vec2::ZERO).length() - destination.length();
+            max_distance_past = max_distance_past.max(overshoot);
+        }
+
+        assert!(
+            position.distance(destination) < settings.arrival_radius,
+            "Cube should come to rest on the destination, ended at {:?}",
+            position
+        );
+        assert!(
+            velocity.length() < settings.arrival_radius,
+            "Cube should have stopped"
+        );
+        assert!(
+            max_distance_past < 20.0,
+            "Arrival should not overshoot noticeably"
+        );
+    }
+
+    #[test]
+    fn test_control_mode_cycle() {
+        let mut mode = ControlMode::default();
+        assert_eq!(mode, ControlMode::Keyboard);
+        assert!(!mode.uses_mouse());
+
+        mode = mode.next();
+        assert_eq!(mode, ControlMode::MouseSteer);
+        mode = mode.next();
+        assert_eq!(mode, ControlMode::ClickToMove);
+        assert!(mode.uses_mouse());
+        mode = mode.next();
+        assert_eq!(mode, ControlMode::Keyboard);
+    }
+}
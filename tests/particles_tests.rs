@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+use bevy_wasm_game::components::*;
+use bevy_wasm_game::game_state::GameplayReset;
+use bevy_wasm_game::particles::*;
+use bevy_wasm_game::rng::*;
+use bevy_wasm_game::systems::*;
+
+/// Tests for the particle simulation, run headless without a renderer
+
+#[cfg(test)]
+mod particles_tests {
+    use super::*;
+
+    const TICK: f32 = 1.0 / 60.0;
+
+    fn particle_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(FixedTime::new_from_secs(TICK));
+        world.init_resource::<GameRng>();
+        world.init_resource::<ParticleSettings>();
+        world.init_resource::<Events<ParticleBurst>>();
+        world.init_resource::<Events<WallImpact>>();
+        world.init_resource::<Events<GameplayReset>>();
+        world
+    }
+
+    fn particle_count(world: &mut World) -> usize {
+        world.query::<&Particle>().iter(world).count()
+    }
+
+    #[test]
+    fn test_particle_fades_and_shrinks_over_lifetime() {
+        let effect = ParticleEffect::impact();
+        let mut particle = roll_particle(&mut GameRng::default(), &effect, Vec2::X, Vec2::ZERO);
+        assert_eq!(particle.style(), effect.start);
+
+        let start_speed = particle.velocity.length();
+        particle.advance(particle.lifetime / 2.0);
+        let halfway = particle.style();
+        assert!(halfway.scale < effect.start.scale && halfway.scale > effect.end.scale);
+        assert!(
+            particle.velocity.length() < start_speed,
+            "Drag slows particles"
+        );
+        assert!(!particle.is_expired());
+
+        particle.advance(particle.lifetime);
+        assert!(particle.is_expired());
+        let end = particle.style();
+        assert!((end.scale - effect.end.scale).abs() < 1e-4);
+        assert!(end.color.a() < 1e-4, "Particles fade out completely");
+    }
+
+    #[test]
+    fn test_emitter_rate_carries_fractions_over() {
+        let mut emitter = ParticleEmitter::new(ParticleEffect::boost_trail(), 30.0);
+        let emitted: u32 = (0..60).map(|_| emitter.due(TICK)).sum();
+        assert_eq!(emitted, 30, "30 per second over one second of ticks");
+
+        emitter.enabled = false;
+        assert_eq!(emitter.due(1.0), 0);
+    }
+
+    #[test]
+    fn test_wall_impact_bursts_along_normal_and_particles_expire() {
+        let mut world = particle_world();
+        let settings = ParticleSettings::default();
+        world.send_event(WallImpact {
+            entity: Entity::PLACEHOLDER,
+            position: Vec2::new(100.0, 0.0),
+            normal: Vec2::NEG_X,
+            speed: 1000.0,
+        });
+        world.send_event(WallImpact {
+            entity: Entity::PLACEHOLDER,
+            position: Vec2::ZERO,
+            normal: Vec2::X,
+            speed: settings.impact_min_speed - 1.0,
+        });
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems((simulate_particles, impact_bursts, emit_particles).chain());
+        schedule.run(&mut world);
+
+        assert_eq!(
+            particle_count(&mut world),
+            settings.impact_particles(1000.0) as usize,
+            "Only the fast hit makes debris"
+        );
+        for particle in world.query::<&Particle>().iter(&world) {
+            assert!(particle.velocity.x < 0.0, "Debris flies off the wall");
+        }
+
+        for _ in 0..60 {
+            schedule.run(&mut world);
+        }
+        assert_eq!(particle_count(&mut world), 0, "Expired particles despawn");
+    }
+
+    #[test]
+    fn test_budget_caps_live_particles() {
+        let mut world = particle_world();
+        world.resource_mut::<ParticleSettings>().max_particles = 20;
+        world.spawn((
+            Transform::default(),
+            LinearVelocity(Vec2::new(300.0, 0.0)),
+            ParticleEmitter::new(ParticleEffect::boost_trail(), 600.0),
+        ));
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems((simulate_particles, emit_particles).chain());
+        for _ in 0..10 {
+            world.send_event(ParticleBurst {
+                position: Vec2::ZERO,
+                direction: Vec2::ZERO,
+                count: 50,
+                effect: ParticleEffect::pickup(),
+            });
+            schedule.run(&mut world);
+            assert!(particle_count(&mut world) <= 20);
+        }
+        assert_eq!(particle_count(&mut world), 20);
+    }
+
+    #[test]
+    fn test_boost_trail_follows_boost_state() {
+        let mut world = particle_world();
+        let player = world
+            .spawn((
+                Transform::default(),
+                LinearVelocity(Vec2::new(200.0, 0.0)),
+                MovementIntent::default(),
+                BoostTrail,
+                ParticleEmitter::new(ParticleEffect::boost_trail(), 120.0),
+            ))
+            .id();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems((update_boost_trails, emit_particles).chain());
+        schedule.run(&mut world);
+        assert_eq!(particle_count(&mut world), 0, "No trail while coasting");
+
+        world.get_mut::<MovementIntent>(player).unwrap().boost = true;
+        for _ in 0..5 {
+            schedule.run(&mut world);
+        }
+        assert!(particle_count(&mut world) > 0);
+        for particle in world.query::<&Particle>().iter(&world) {
+            assert!(particle.velocity.x < 0.0, "Trail sprays behind the player");
+        }
+    }
+
+    #[test]
+    fn test_particles_leave_the_gameplay_sequence_alone() {
+        let mut world = particle_world();
+        world.insert_resource(GameRng::from_seed(3));
+        world.send_event(ParticleBurst {
+            position: Vec2::ZERO,
+            direction: Vec2::X,
+            count: 20,
+            effect: ParticleEffect::pickup(),
+        });
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems(emit_particles);
+        schedule.run(&mut world);
+        assert_eq!(particle_count(&mut world), 20);
+        assert_eq!(world.resource::<GameRng>().0, GameRng::from_seed(3).0);
+    }
+
+    #[test]
+    fn test_particles_clear_when_the_session_resets() {
+        let mut world = particle_world();
+        world.send_event(ParticleBurst {
+            position: Vec2::ZERO,
+            direction: Vec2::X,
+            count: 5,
+            effect: ParticleEffect::pickup(),
+        });
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems((emit_particles, clear_particles).chain());
+        schedule.run(&mut world);
+        assert_eq!(particle_count(&mut world), 5);
+
+        world.send_event(GameplayReset);
+        schedule.run(&mut world);
+        assert_eq!(particle_count(&mut world), 0);
+    }
+}
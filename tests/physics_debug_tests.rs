@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+use bevy_wasm_game::components::*;
+use bevy_wasm_game::physics_debug::*;
+use bevy_wasm_game::resources::*;
+use bevy_wasm_game::stamina::*;
+use bevy_wasm_game::systems::*;
+
+/// Tests for the physics frame-step debugger
+/// FixedUpdate is run by hand so every schedule run is exactly one tick
+
+#[cfg(test)]
+mod physics_debug_tests {
+    use super::*;
+
+    fn create_physics_app() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<BoostSettings>()
+            .insert_resource(WorldBounds::default_bounds())
+            .add_event::<StaminaDepleted>()
+            .add_event::<StaminaFull>();
+        PhysicsSystems::add_to_app(&mut app);
+        PhysicsDebugSystems::add_to_app(&mut app);
+
+        let player = app
+            .world
+            .spawn((
+                Transform::default(),
+                Player,
+                PhysicsBundle::new(Vec2::new(60.0, 0.0), 0.0),
+                MovementIntent::default(),
+            ))
+            .id();
+        (app, player)
+    }
+
+    fn tick(app: &mut App) {
+        app.world.run_schedule(FixedUpdate);
+    }
+
+    fn position(app: &App, entity: Entity) -> Vec2 {
+        app.world
+            .get::<Transform>(entity)
+            .unwrap()
+            .translation
+            .truncate()
+    }
+
+    #[test]
+    fn test_enabled_stepper_freezes_simulation() {
+        let (mut app, player) = create_physics_app();
+        tick(&mut app);
+        let running_position = position(&app, player);
+        assert!(
+            running_position.x > 0.0,
+            "Physics runs while the stepper is off"
+        );
+
+        app.world.resource_mut::<PhysicsStepper>().enable();
+        for _ in 0..5 {
+            tick(&mut app);
+        }
+        assert_eq!(position(&app, player), running_position);
+    }
+
+    #[test]
+    fn test_step_advances_exactly_requested_ticks() {
+        let (mut app, player) = create_physics_app();
+        app.world.resource_mut::<PhysicsStepper>().step(2);
+
+        for _ in 0..5 {
+            tick(&mut app);
+        }
+
+        let stepper = app.world.resource::<PhysicsStepper>();
+        assert_eq!(stepper.steps_taken(), 2);
+        assert_eq!(stepper.pending_steps(), 0);
+        assert!(
+            stepper.is_enabled(),
+            "Stepping leaves the simulation frozen"
+        );
+
+        let log = app.world.resource::<PhysicsStepLog>();
+        assert_eq!(log.records.len(), 2);
+
+        let last = log.last().unwrap();
+        assert_eq!(last.step, 2);
+        assert_eq!(last.before[0].entity, player);
+        assert!(last.after[0].position.x > last.before[0].position.x);
+        assert_eq!(last.after[0].position, position(&app, player));
+        assert_eq!(
+            log.records[0].after[0].position, last.before[0].position,
+            "Consecutive steps chain together"
+        );
+    }
+
+    #[test]
+    fn test_disable_resumes_and_drops_queue() {
+        let (mut app, player) = create_physics_app();
+        {
+            let mut stepper = app.world.resource_mut::<PhysicsStepper>();
+            stepper.step(10);
+            stepper.toggle();
+            assert!(!stepper.is_enabled());
+            assert_eq!(stepper.pending_steps(), 0);
+        }
+
+        tick(&mut app);
+        assert!(position(&app, player).x > 0.0);
+        assert!(app.world.resource::<PhysicsStepLog>().records.is_empty());
+    }
+}
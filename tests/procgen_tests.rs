@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use bevy_wasm_game::headless::*;
+use bevy_wasm_game::level::*;
+use bevy_wasm_game::procgen::*;
+use bevy_wasm_game::replay::*;
+
+/// Tests for seeded arena generation: determinism, spacing and reachability
+
+#[cfg(test)]
+mod procgen_tests {
+    use super::*;
+
+    fn cell_distance(grid: &ArenaGrid, a: Vec2, b: Vec2) -> u32 {
+        let (a, b) = (grid.cell_at(a).unwrap(), grid.cell_at(b).unwrap());
+        a.0.abs_diff(b.0).max(a.1.abs_diff(b.1))
+    }
+
+    #[test]
+    fn test_seeds_give_the_same_arena_everywhere() {
+        let level = ArenaSettings::with_seed(42).generate().unwrap();
+        assert_eq!(level, ArenaSettings::with_seed(42).generate().unwrap());
+        assert_ne!(level, ArenaSettings::with_seed(43).generate().unwrap());
+
+        // Pinned so a change in the generator or a platform difference shows
+        // up before shared seeds start producing other arenas
+        assert_eq!(level.name, "Arena 42");
+        assert_eq!(level.spawn_points, vec![Vec2::new(-90.0, 150.0)]);
+        assert_eq!(level.obstacles.len(), 8);
+        assert_eq!(level.obstacles[0].position, Vec2::new(-210.0, -240.0));
+        assert_eq!(level.obstacles[0].size, Vec2::new(60.0, 120.0));
+        assert_eq!(level.enemies[0].position, Vec2::new(-570.0, -210.0));
+        assert_eq!(level.power_ups[2].position, Vec2::new(390.0, 270.0));
+        assert_eq!(level.pickups[7].position, Vec2::new(-390.0, 270.0));
+    }
+
+    #[test]
+    fn test_spacing_and_reachability() {
+        let settings = ArenaSettings {
+            players: 2,
+            obstacles: 14,
+            pickups: 10,
+            ..default()
+        };
+        for seed in 0..200 {
+            let level = ArenaSettings {
+                seed,
+                ..settings.clone()
+            }
+            .generate()
+            .unwrap();
+            let grid = ArenaGrid::from_level(&level, settings.cell_size);
+            assert!(grid.is_connected(), "seed {seed}");
+            assert_eq!(level.spawn_points.len(), 2);
+            let [first, second] = level.spawn_points[..] else {
+                unreachable!()
+            };
+            assert!(cell_distance(&grid, first, second) >= settings.spawn_spacing);
+
+            let items: Vec<Vec2> = level
+                .enemies
+                .iter()
+                .map(|enemy| enemy.position)
+                .chain(level.power_ups.iter().map(|power_up| power_up.position))
+                .chain(level.pickups.iter().map(|pickup| pickup.position))
+                .collect();
+            assert!(items.len() > 10, "seed {seed} left out most items");
+            for (i, item) in items.iter().enumerate() {
+                assert!(
+                    grid.connects(first, *item),
+                    "seed {seed}: {item} unreachable"
+                );
+                for other in items[i + 1..].iter().chain(&level.spawn_points) {
+                    assert!(cell_distance(&grid, *item, *other) >= settings.item_spacing);
+                }
+            }
+            for enemy in level.enemies.iter() {
+                for spawn in level.spawn_points.iter() {
+                    assert!(
+                        cell_distance(&grid, enemy.position, *spawn) >= settings.enemy_distance
+                    );
+                }
+            }
+            for spawn in level.spawn_points.iter() {
+                let cell = grid.cell_at(*spawn).unwrap();
+                assert!(
+                    !grid.is_blocked(cell),
+                    "seed {seed}: spawn inside an obstacle"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_grids_of_hand_made_levels() {
+        let mut level = LevelDefinition::builtin();
+        // A wall across the arena cuts off the right side
+        level.obstacles.push(ObstacleDefinition {
+            position: Vec2::new(300.0, 0.0),
+            size: Vec2::new(40.0, 640.0),
+        });
+        let grid = ArenaGrid::from_level(&level, 50.0);
+        assert!(!grid.is_connected());
+        assert!(grid.connects(Vec2::ZERO, Vec2::new(-500.0, 200.0)));
+        assert!(!grid.connects(Vec2::ZERO, Vec2::new(500.0, 0.0)));
+        assert!(
+            !grid.connects(Vec2::ZERO, Vec2::new(900.0, 0.0)),
+            "Off the grid"
+        );
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        let error = ArenaSettings {
+            cell_size: 0.0,
+            ..default()
+        }
+        .generate()
+        .unwrap_err();
+        assert_eq!(error.field, "cell_size");
+        let error = ArenaSettings {
+            size: Vec2::new(30.0, 600.0),
+            ..default()
+        }
+        .generate()
+        .unwrap_err();
+        assert_eq!(error.field, "size");
+        assert!(ArenaSettings {
+            players: 0,
+            ..default()
+        }
+        .generate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_headless_runs_on_generated_arenas() {
+        let config = HeadlessConfig::from_args(&["--arena".into(), "42".into()]).unwrap();
+        assert_eq!(config.arena, Some(42));
+        let mut app = headless_app(&HeadlessConfig {
+            ticks: Some(30),
+            soak: true,
+            report: Some(std::env::temp_dir().join("procgen_tests_report.ron")),
+            ..config
+        })
+        .unwrap();
+        run_headless(&mut app).unwrap();
+        assert_eq!(
+            app.world.resource::<CurrentLevel>().definition.name,
+            "Arena 42"
+        );
+
+        // Recordings name the arena so they replay on it
+        let recorder = app.world.resource::<InputRecorder>();
+        assert_eq!(recorder.0.arena, Some(ArenaSettings::with_seed(42)));
+        let source = recorder.0.to_ron().unwrap();
+        let replay = InputReplay::from_ron(&source, "memory").unwrap();
+        assert_eq!(replay.arena, recorder.0.arena);
+    }
+}
@@ -0,0 +1,221 @@
+use bevy::prelude::*;
+use bevy_wasm_game::components::*;
+use bevy_wasm_game::entity_relations::{despawn_related, Owner};
+use bevy_wasm_game::game_state::GameplayEntity;
+use bevy_wasm_game::projectiles::*;
+use bevy_wasm_game::resources::*;
+
+/// Tests for weapons, projectile flight and hit attribution
+
+#[cfg(test)]
+mod projectiles_tests {
+    use super::*;
+
+    const TICK: f32 = 1.0 / 60.0;
+
+    fn projectile_world() -> (World, Schedule) {
+        let mut world = World::new();
+        world.insert_resource(FixedTime::new_from_secs(TICK));
+        world.insert_resource(WorldBounds::from_window_size(2000.0, 2000.0, 0.0));
+        world.init_resource::<Events<ProjectileHit>>();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems((fire_weapons, apply_deferred, update_projectiles).chain());
+        (world, schedule)
+    }
+
+    fn projectiles(world: &mut World) -> Vec<(Entity, Projectile, Entity)> {
+        world
+            .query::<(Entity, &Projectile, &Owner)>()
+            .iter(world)
+            .map(|(entity, projectile, owner)| (entity, projectile.clone(), owner.0))
+            .collect()
+    }
+
+    fn hits(world: &World) -> Vec<ProjectileHit> {
+        let events = world.resource::<Events<ProjectileHit>>();
+        events.get_reader().iter(events).copied().collect()
+    }
+
+    #[test]
+    fn test_weapon_rate_limit_and_inherited_velocity() {
+        let mut weapon = Weapon::default();
+        let shots = (0..60).filter(|_| weapon.tick(true, TICK)).count();
+        let expected = (1.0 / weapon.cooldown).ceil() as usize;
+        assert!(shots.abs_diff(expected) <= 1, "{shots} shots in one second");
+
+        assert!(!weapon.tick(false, 1.0));
+        assert!(weapon.tick(true, TICK), "Ready again after a pause");
+
+        let velocity = weapon.launch_velocity(Vec2::Y, Vec2::new(300.0, 0.0));
+        assert_eq!(velocity, Vec2::new(300.0, weapon.muzzle_speed));
+    }
+
+    #[test]
+    fn test_aim_prefers_cursor_then_heading() {
+        let facing = Vec2::NEG_Y;
+        let mut fire = FireIntent {
+            firing: true,
+            aim_at: Some(Vec2::new(0.0, 100.0)),
+        };
+        let intent = MovementIntent {
+            direction: Vec2::X,
+            ..default()
+        };
+        let velocity = Vec2::new(-50.0, 0.0);
+        assert_eq!(
+            aim_direction(Vec2::ZERO, &fire, Some(&intent), velocity, facing),
+            Vec2::Y
+        );
+
+        fire.aim_at = None;
+        assert_eq!(
+            aim_direction(Vec2::ZERO, &fire, Some(&intent), velocity, facing),
+            Vec2::X
+        );
+        assert_eq!(
+            aim_direction(Vec2::ZERO, &fire, None, velocity, facing),
+            Vec2::NEG_X
+        );
+        assert_eq!(
+            aim_direction(Vec2::ZERO, &fire, None, Vec2::ZERO, facing),
+            facing
+        );
+    }
+
+    #[test]
+    fn test_projectile_hits_are_attributed_to_the_shooter() {
+        let (mut world, mut schedule) = projectile_world();
+        let shooter = world
+            .spawn((
+                Transform::default(),
+                LinearVelocity(Vec2::new(120.0, 0.0)),
+                CollisionRadius::default(),
+                FireIntent {
+                    firing: true,
+                    aim_at: Some(Vec2::new(500.0, 0.0)),
+                },
+                Weapon::default(),
+            ))
+            .id();
+        let target = world
+            .spawn((Transform::from_xyz(300.0, 0.0, 0.0), CollisionRadius(20.0)))
+            .id();
+
+        schedule.run(&mut world);
+        let fired = projectiles(&mut world);
+        assert_eq!(fired.len(), 1);
+        let (projectile, state, owner) = fired[0].clone();
+        assert_eq!(owner, shooter);
+        assert_eq!(state.velocity, Vec2::new(900.0 + 120.0, 0.0));
+        assert!(
+            world.get::<GameplayEntity>(projectile).is_some(),
+            "Shots go with the session on reloads and restarts"
+        );
+
+        world.get_mut::<FireIntent>(shooter).unwrap().firing = false;
+        for _ in 0..30 {
+            schedule.run(&mut world);
+        }
+
+        let hits = hits(&world);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].projectile, projectile);
+        assert_eq!(hits[0].owner, Some(shooter));
+        assert_eq!(hits[0].target, Some(target));
+        assert!(world.get_entity(projectile).is_none());
+    }
+
+    #[test]
+    fn test_projectiles_stop_at_obstacles() {
+        let (mut world, mut schedule) = projectile_world();
+        world.spawn((
+            Transform::from_xyz(0.0, 200.0, 0.0),
+            Obstacle::new(Vec2::splat(50.0)),
+        ));
+        world.spawn((
+            Transform::default(),
+            FireIntent {
+                firing: true,
+                aim_at: Some(Vec2::new(0.0, 500.0)),
+            },
+            Weapon::default(),
+        ));
+
+        for _ in 0..20 {
+            schedule.run(&mut world);
+        }
+        let hits = hits(&world);
+        assert!(!hits.is_empty());
+        assert!(hits.iter().all(|hit| hit.target.is_none()));
+        assert!(hits.iter().all(|hit| hit.position.y < 200.0));
+    }
+
+    #[test]
+    fn test_projectiles_expire_after_their_lifetime() {
+        let (mut world, mut schedule) = projectile_world();
+        world.insert_resource(WorldBounds::from_window_size(100_000.0, 100_000.0, 0.0));
+        let shooter = world
+            .spawn((
+                Transform::default(),
+                FireIntent {
+                    firing: true,
+                    aim_at: None,
+                },
+                Weapon::default(),
+            ))
+            .id();
+        schedule.run(&mut world);
+        world.get_mut::<FireIntent>(shooter).unwrap().firing = false;
+        assert_eq!(projectiles(&mut world).len(), 1);
+
+        let lifetime = Weapon::default().lifetime;
+        for _ in 0..(lifetime / TICK).ceil() as usize {
+            schedule.run(&mut world);
+        }
+        assert!(projectiles(&mut world).is_empty());
+        assert!(hits(&world).is_empty());
+    }
+
+    #[test]
+    fn test_shots_keep_flying_after_their_shooter_is_gone() {
+        let (mut world, mut schedule) = projectile_world();
+        let shooter = world
+            .spawn((
+                Transform::default(),
+                FireIntent {
+                    firing: true,
+                    aim_at: Some(Vec2::new(500.0, 0.0)),
+                },
+                Weapon::default(),
+            ))
+            .id();
+        let target = world
+            .spawn((Transform::from_xyz(300.0, 0.0, 0.0), CollisionRadius(20.0)))
+            .id();
+        schedule.run(&mut world);
+        let (projectile, ..) = projectiles(&mut world)[0].clone();
+
+        despawn_related(&mut world, shooter);
+        assert!(
+            world.get::<Owner>(projectile).is_none(),
+            "Let go of the shooter"
+        );
+        for _ in 0..30 {
+            schedule.run(&mut world);
+        }
+        let hits = hits(&world);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].owner, None);
+        assert_eq!(hits[0].target, Some(target));
+    }
+
+    #[test]
+    fn test_fast_projectiles_do_not_tunnel() {
+        let start = Vec2::new(-100.0, 0.0);
+        let end = Vec2::new(100.0, 0.0);
+        assert!(swept_circle_hit(start, end, Vec2::ZERO, 10.0));
+        assert!(!swept_circle_hit(start, end, Vec2::new(0.0, 20.0), 10.0));
+        assert!(!swept_circle_hit(start, end, Vec2::new(150.0, 0.0), 10.0));
+    }
+}
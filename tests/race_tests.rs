@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+use bevy_wasm_game::level::*;
+use bevy_wasm_game::race::*;
+
+/// Tests for the checkpoint time-attack mode
+
+#[cfg(test)]
+mod race_tests {
+    use super::*;
+
+    const COURSE: &str = r#"(
+        name: "Course",
+        bounds: (min: (-500.0, -500.0), max: (500.0, 500.0), friction: 0.1),
+        spawn_points: [(0.0, 0.0)],
+        race: Some((
+            checkpoints: [(position: (200.0, 0.0)), (position: (-200.0, 0.0), radius: 30.0)],
+            laps: 2,
+        )),
+    )"#;
+
+    fn race_world() -> (World, Schedule) {
+        let mut world = World::new();
+        let definition = LevelDefinition::from_ron(COURSE, "course.ron").unwrap();
+        world.insert_resource(CurrentLevel {
+            definition,
+            path: "course.ron".into(),
+        });
+        world.init_resource::<BestTimes>();
+        world.init_resource::<Events<CheckpointReached>>();
+        world.init_resource::<Events<RaceFinished>>();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems((tick_race_timers, detect_checkpoints).chain());
+        (world, schedule)
+    }
+
+    fn spawn_course(world: &mut World) -> Entity {
+        let level = world.resource::<CurrentLevel>().definition.clone();
+        let mut queue = bevy::ecs::system::CommandQueue::default();
+        let players = {
+            let mut commands = Commands::new(&mut queue, world);
+            spawn_level(&mut commands, &level)
+        };
+        queue.apply(world);
+        players[0]
+    }
+
+    fn move_to(world: &mut World, player: Entity, x: f32) {
+        world.get_mut::<Transform>(player).unwrap().translation.x = x;
+    }
+
+    #[test]
+    fn test_progress_runs_through_laps() {
+        let mut progress = RaceProgress::new(3, 2);
+        for tick in 1..=6 {
+            progress.ticks = tick * 10;
+            assert!(!progress.finished);
+            assert_eq!(progress.pass_checkpoint(), tick * 10);
+        }
+        assert!(progress.finished);
+        assert_eq!(progress.lap, 2);
+        assert_eq!(progress.splits, vec![10, 20, 30, 40, 50, 60]);
+    }
+
+    #[test]
+    fn test_best_times_only_keep_faster_runs() {
+        let mut best = BestTimes::default();
+        assert_eq!(best.delta("Course", 0, 100), None);
+
+        assert!(best.submit("Course", &[100, 200]));
+        assert_eq!(best.delta("Course", 0, 90), Some(-10));
+        assert_eq!(best.delta("Course", 1, 230), Some(30));
+
+        assert!(!best.submit("Course", &[90, 210]), "Slower total");
+        assert!(best.submit("Course", &[120, 190]));
+        assert_eq!(best.splits("Course"), Some(&[120, 190][..]));
+        assert_eq!(best.splits("Other"), None);
+
+        assert_eq!(format_ticks(3600 + 75, 1.0 / 60.0), "1:01.250");
+    }
+
+    #[test]
+    fn test_course_levels_spawn_checkpoints_and_validate() {
+        let level = LevelDefinition::from_ron(COURSE, "course.ron").unwrap();
+        let race = level.race.as_ref().unwrap();
+        assert_eq!(race.checkpoints[0].radius, 60.0, "Radius has a default");
+        assert!(LevelDefinition::builtin().race.is_none());
+        let shipped = LevelDefinition::load("assets/levels/time_attack.ron").unwrap();
+        assert!(shipped.race.is_some());
+
+        let no_laps = COURSE.replace("laps: 2", "laps: 0");
+        match LevelDefinition::from_ron(&no_laps, "course.ron").unwrap_err() {
+            LevelError::Invalid { field, .. } => assert_eq!(field, "race.laps"),
+            other => panic!("Expected a validation error, got {other:?}"),
+        }
+        let outside = COURSE.replace("(-200.0, 0.0)", "(-900.0, 0.0)");
+        match LevelDefinition::from_ron(&outside, "course.ron").unwrap_err() {
+            LevelError::Invalid { field, .. } => {
+                assert_eq!(field, "race.checkpoints[1].position")
+            }
+            other => panic!("Expected a validation error, got {other:?}"),
+        }
+
+        let (mut world, _) = race_world();
+        let player = spawn_course(&mut world);
+        let progress = world.get::<RaceProgress>(player).unwrap();
+        assert_eq!((progress.checkpoint_count, progress.laps), (2, 2));
+        let mut indices: Vec<usize> = world
+            .query::<&Checkpoint>()
+            .iter(&world)
+            .map(|c| c.index)
+            .collect();
+        indices.sort();
+        assert_eq!(indices, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_checkpoints_count_in_order_and_finish() {
+        let (mut world, mut schedule) = race_world();
+        let player = spawn_course(&mut world);
+
+        // The second gate does not count before the first
+        move_to(&mut world, player, -200.0);
+        schedule.run(&mut world);
+        assert_eq!(world.get::<RaceProgress>(player).unwrap().next, 0);
+
+        for x in [200.0, -200.0, 200.0, 0.0, -200.0] {
+            move_to(&mut world, player, x);
+            schedule.run(&mut world);
+        }
+        let progress = world.get::<RaceProgress>(player).unwrap();
+        assert!(progress.finished);
+        assert_eq!(progress.splits, vec![2, 3, 4, 6]);
+
+        let reached = world.resource::<Events<CheckpointReached>>();
+        let reached: Vec<_> = reached.get_reader().iter(reached).copied().collect();
+        assert_eq!(reached.len(), 4);
+        assert_eq!((reached[2].index, reached[2].lap), (0, 1));
+        assert!(reached.iter().all(|event| event.delta_to_best.is_none()));
+
+        let finished = world.resource::<Events<RaceFinished>>();
+        let finished: Vec<_> = finished.get_reader().iter(finished).copied().collect();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].total, 6);
+        assert!(finished[0].new_best);
+        assert_eq!(
+            world.resource::<BestTimes>().splits("Course"),
+            Some(&[2, 3, 4, 6][..])
+        );
+
+        // The timer stops at the finish
+        schedule.run(&mut world);
+        assert_eq!(world.get::<RaceProgress>(player).unwrap().ticks, 6);
+
+        // A second run is compared against the stored best
+        world.entity_mut(player).insert(RaceProgress::new(2, 2));
+        move_to(&mut world, player, 200.0);
+        schedule.run(&mut world);
+        world.resource_mut::<Events<CheckpointReached>>().clear();
+        schedule.run(&mut world);
+        move_to(&mut world, player, -200.0);
+        schedule.run(&mut world);
+        let reached = world.resource::<Events<CheckpointReached>>();
+        let event = reached.get_reader().iter(reached).next().copied().unwrap();
+        assert_eq!(event.delta_to_best, Some(0));
+    }
+}
@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+use bevy_wasm_game::entity_relations::{
+    attach_child, ChildOf, Children, Owner, Parent, Relation, Target,
+};
+use bevy_wasm_game::reference_validation::{
+    find_reference_problems, validate_entity_references, ReferenceFault, ReferenceProblem,
+    ReferenceRepaired, ReferenceReport, ReferenceValidation, ReferenceValidationSystems,
+    RepairAction,
+};
+
+/// Tests for finding, repairing and reporting broken entity references
+
+#[cfg(test)]
+mod reference_validation_tests {
+    use super::*;
+
+    fn app(validation: ReferenceValidation) -> App {
+        let mut app = App::new();
+        ReferenceValidationSystems::add_to_app(&mut app);
+        app.insert_resource(validation);
+        app
+    }
+
+    fn repaired(app: &App) -> Vec<ReferenceRepaired> {
+        let events = app.world.resource::<Events<ReferenceRepaired>>();
+        events.get_reader().iter(events).copied().collect()
+    }
+
+    #[test]
+    fn test_intact_world_is_clean() {
+        let mut app = app(ReferenceValidation::strict());
+        let player = app.world.spawn_empty().id();
+        let shield = app.world.spawn_empty().id();
+        attach_child(&mut app.world, player, shield);
+        app.world.spawn((Owner(player), Target(player)));
+
+        app.update();
+        assert!(app.world.resource::<ReferenceReport>().is_clean());
+        assert!(repaired(&app).is_empty());
+    }
+
+    #[test]
+    fn test_default_actions_despawn_orphans_and_report_the_rest() {
+        let mut app = app(ReferenceValidation::default());
+        let player = app.world.spawn_empty().id();
+        let shield = app.world.spawn_empty().id();
+        let spark = app.world.spawn_empty().id();
+        attach_child(&mut app.world, player, shield);
+        attach_child(&mut app.world, shield, spark);
+        let projectile = app.world.spawn(Owner(player)).id();
+        let enemy = app.world.spawn(Target(player)).id();
+        // Despawned behind the hierarchy's back
+        app.world.despawn(player);
+
+        app.update();
+        assert!(app.world.get_entity(shield).is_none());
+        assert!(app.world.get_entity(spark).is_none(), "cascades");
+        assert_eq!(app.world.get::<Owner>(projectile), Some(&Owner(player)));
+        assert_eq!(app.world.get::<Target>(enemy), Some(&Target(player)));
+
+        let report = app.world.resource::<ReferenceReport>();
+        assert_eq!(report.count(Relation::ChildOf), 1);
+        assert_eq!(report.count(Relation::Owner), 1);
+        assert_eq!(report.count(Relation::Target), 1);
+        assert!(report.problems.contains(&ReferenceProblem {
+            relation: Relation::Owner,
+            fault: ReferenceFault::Dangling,
+            entity: projectile,
+            referenced: player,
+            action: RepairAction::Report,
+        }));
+        assert_eq!(
+            repaired(&app),
+            vec![ReferenceRepaired {
+                relation: Relation::ChildOf,
+                entity: shield,
+                referenced: player,
+                action: RepairAction::Despawn,
+            }]
+        );
+
+        // Reported references stay reported, nothing is repaired twice
+        app.update();
+        let report = app.world.resource::<ReferenceReport>();
+        assert_eq!(report.count(Relation::ChildOf), 0);
+        assert_eq!(report.problems.len(), 2);
+    }
+
+    #[test]
+    fn test_remove_and_default_owner_actions() {
+        let mut app = app(ReferenceValidation {
+            child_of: RepairAction::Remove,
+            target: RepairAction::Remove,
+            ..default()
+        });
+        let arena = app.world.spawn_empty().id();
+        let player = app.world.spawn_empty().id();
+        let shield = app.world.spawn_empty().id();
+        attach_child(&mut app.world, player, shield);
+        let projectile = app.world.spawn(Owner(player)).id();
+        let enemy = app.world.spawn(Target(player)).id();
+        app.world.resource_mut::<ReferenceValidation>().owner = RepairAction::DefaultOwner;
+        app.world
+            .resource_mut::<ReferenceValidation>()
+            .default_owner = Some(arena);
+        app.world.despawn(player);
+
+        app.update();
+        assert!(app.world.get_entity(shield).is_some());
+        assert_eq!(app.world.get::<ChildOf>(shield), None);
+        assert_eq!(app.world.get::<Target>(enemy), None);
+        assert_eq!(app.world.get::<Owner>(projectile), Some(&Owner(arena)));
+        assert_eq!(repaired(&app).len(), 3);
+
+        app.update();
+        assert!(app.world.resource::<ReferenceReport>().is_clean());
+    }
+
+    #[test]
+    fn test_inconsistent_hierarchy_is_made_whole() {
+        let mut world = World::new();
+        let ship = world.spawn_empty().id();
+        let other = world.spawn_empty().id();
+        // Names the ship, which does not list it
+        let turret = world.spawn(ChildOf(ship)).id();
+        // Listed by the ship, but attached elsewhere. The ship is not marked
+        // as a parent either.
+        let shield = world.spawn_empty().id();
+        attach_child(&mut world, other, shield);
+        world.entity_mut(ship).insert(Children(vec![shield]));
+        // Marked as a parent without any children
+        let gun = world.spawn(Parent).id();
+
+        let validation = ReferenceValidation::default();
+        let problems = find_reference_problems(&mut world, &validation);
+        assert_eq!(problems.len(), 4);
+        assert!(problems
+            .iter()
+            .all(|problem| problem.fault == ReferenceFault::Inconsistent));
+
+        world.insert_resource(validation);
+        validate_entity_references(&mut world);
+        assert_eq!(world.get::<Children>(ship), Some(&Children(vec![turret])));
+        assert_eq!(world.get::<Children>(other), Some(&Children(vec![shield])));
+        assert_eq!(world.get::<Parent>(ship), Some(&Parent));
+        assert_eq!(world.get::<Parent>(gun), None);
+        assert_eq!(
+            world.resource::<ReferenceReport>().count(Relation::ChildOf),
+            4
+        );
+        assert!(find_reference_problems(&mut world, &ReferenceValidation::default()).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "1 broken references")]
+    fn test_strict_mode_panics() {
+        let mut app = app(ReferenceValidation::strict());
+        let player = app.world.spawn_empty().id();
+        app.world.spawn(Target(player));
+        app.world.despawn(player);
+        app.update();
+    }
+}
@@ -0,0 +1,409 @@
+use bevy::prelude::*;
+use bevy_wasm_game::components::*;
+use bevy_wasm_game::enemy::Enemy;
+use bevy_wasm_game::input::apply_movement_intent;
+use bevy_wasm_game::particles::*;
+use bevy_wasm_game::power_ups::PowerUpKind;
+use bevy_wasm_game::replication::*;
+use bevy_wasm_game::resources::WorldBounds;
+use bevy_wasm_game::rollback::NetInput;
+use bevy_wasm_game::stamina::*;
+use bevy_wasm_game::systems::*;
+use bevy_wasm_game::transport::*;
+use bevy_wasm_game::visuals::VisualStyle;
+use std::collections::{BTreeMap, VecDeque};
+
+/// Tests for snapshot replication between an authoritative server and a client
+
+#[cfg(test)]
+mod replication_tests {
+    use super::*;
+
+    const TICK: f32 = 1.0 / 60.0;
+    const RIGHT: NetInput = NetInput {
+        x: 127,
+        y: 0,
+        buttons: 0,
+    };
+
+    fn physics_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(FixedTime::new_from_secs(TICK));
+        world.init_resource::<WorldBounds>();
+        world.init_resource::<BoostSettings>();
+        world.init_resource::<Events<WallImpact>>();
+        world.init_resource::<Events<StaminaDepleted>>();
+        world.init_resource::<Events<StaminaFull>>();
+        world
+    }
+
+    /// Hands packets over `delay` receive calls after they arrived
+    struct DelayedTransport {
+        link: ChannelTransport,
+        delay: usize,
+        arrived: VecDeque<Vec<Vec<u8>>>,
+    }
+
+    impl Transport for DelayedTransport {
+        fn send(&mut self, packet: &[u8]) -> Result<(), NetError> {
+            self.link.send(packet)
+        }
+
+        fn receive(&mut self) -> Vec<Vec<u8>> {
+            self.arrived.push_back(self.link.receive());
+            if self.arrived.len() > self.delay {
+                self.arrived.pop_front().unwrap_or_default()
+            } else {
+                Vec::new()
+            }
+        }
+    }
+
+    /// Server with one player, a drifting enemy and an obstacle, connected
+    /// to a client over an in-memory loopback
+    fn server_and_client() -> (World, World, Entity, Entity) {
+        server_and_client_over(0)
+    }
+
+    /// Like `server_and_client`, snapshots reach the client `delay` ticks late
+    fn server_and_client_over(delay: usize) -> (World, World, Entity, Entity) {
+        let (server_link, client_link) = ChannelTransport::pair();
+
+        let mut server = physics_world();
+        let mut replication = ReplicationServer::default();
+        replication.add_client(server_link);
+        server.insert_resource(replication);
+        let mut schedule = Schedule::new();
+        schedule.add_systems(
+            (
+                assign_net_ids,
+                assign_client_players,
+                apply_deferred,
+                apply_client_inputs,
+                update_stamina,
+                apply_movement_intent,
+                world_friction,
+                player_physics_integration,
+                boundary_collision,
+                obstacle_collision,
+                send_snapshots,
+            )
+                .chain(),
+        );
+        server.add_schedule(schedule, FixedUpdate);
+
+        let player = server
+            .spawn((
+                Transform::from_xyz(-200.0, 0.0, 0.0),
+                PhysicsBundle::default(),
+                MovementIntent::default(),
+                FireIntent::default(),
+                Stamina::default(),
+                CollisionRadius::default(),
+                Player,
+            ))
+            .id();
+        let enemy = server
+            .spawn((
+                Transform::from_xyz(200.0, 0.0, 0.0),
+                PhysicsBundle::new(Vec2::new(0.0, 150.0), 0.0),
+                Enemy,
+            ))
+            .id();
+        server.spawn((
+            Transform::from_xyz(0.0, -300.0, 0.0),
+            Obstacle::new(Vec2::new(100.0, 40.0)),
+        ));
+
+        let mut client = physics_world();
+        client.insert_resource(ReplicationClient::new(DelayedTransport {
+            link: client_link,
+            delay,
+            arrived: VecDeque::new(),
+        }));
+        let mut schedule = Schedule::new();
+        schedule.add_systems(
+            (
+                update_stamina,
+                apply_movement_intent,
+                world_friction,
+                player_physics_integration,
+                boundary_collision,
+                obstacle_collision,
+                simulate_particles.in_set(ParticleSet),
+            )
+                .chain(),
+        );
+        schedule.configure_set(ParticleSet.run_if(not_reconciling));
+        client.add_schedule(schedule, FixedUpdate);
+
+        (server, client, player, enemy)
+    }
+
+    /// One client tick, then one server tick
+    fn step(server: &mut World, client: &mut World, input: NetInput) {
+        advance_replication_client(client, input);
+        server.run_schedule(FixedUpdate);
+    }
+
+    fn position(world: &World, entity: Entity) -> Vec2 {
+        world
+            .get::<Transform>(entity)
+            .unwrap()
+            .translation
+            .truncate()
+    }
+
+    fn own_player(client: &World) -> Entity {
+        client
+            .resource::<ReplicationClient>()
+            .player_entity()
+            .expect("client has a player")
+    }
+
+    fn state(position: Vec2) -> EntityState {
+        EntityState {
+            kind: ReplicatedKind::Enemy,
+            position,
+            velocity: Vec2::ZERO,
+            health: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_snapshot_deltas_round_trip() {
+        let mut entities: BTreeMap<NetId, EntityState> = (0..10)
+            .map(|id| (NetId(id), state(Vec2::splat(id as f32))))
+            .collect();
+        entities.insert(
+            NetId(10),
+            EntityState {
+                kind: ReplicatedKind::PowerUp(PowerUpKind::HeavyMass),
+                position: Vec2::ZERO,
+                velocity: Vec2::ZERO,
+                health: 0.0,
+            },
+        );
+        let baseline = WorldState { tick: 4, entities };
+        let mut current = baseline.clone();
+        current.tick = 5;
+        current.entities.get_mut(&NetId(3)).unwrap().position = Vec2::new(50.0, 0.0);
+        current.entities.remove(&NetId(7));
+        current.entities.insert(NetId(11), state(Vec2::ONE));
+
+        let full = SnapshotPacket::delta(&current, None);
+        let mut delta = SnapshotPacket::delta(&current, Some(&baseline));
+        delta.player = Some(NetId(3));
+        delta.last_input = Some(12);
+        assert_eq!(delta.removed, vec![NetId(7)]);
+        assert_eq!(delta.changes.len(), 2, "Only the moved and the new entity");
+        let moved = delta.changes[0];
+        assert!(moved.position.is_some() && moved.kind.is_none() && moved.velocity.is_none());
+        assert!(delta.encode().len() * 4 < full.encode().len());
+
+        for packet in [&full, &delta] {
+            let bytes = packet.encode();
+            assert_eq!(&SnapshotPacket::decode(&bytes).unwrap(), packet);
+            assert!(SnapshotPacket::decode(&bytes[..bytes.len() - 1]).is_err());
+        }
+        assert_eq!(full.apply(None).unwrap(), current);
+        assert_eq!(delta.apply(Some(&baseline)).unwrap(), current);
+        assert!(delta.apply(None).is_err(), "A delta needs its baseline");
+        assert!(delta.apply(Some(&current)).is_err());
+
+        let inputs = ClientPacket {
+            ack: None,
+            first_input: 9,
+            inputs: vec![RIGHT, NetInput::default()],
+        };
+        assert_eq!(ClientPacket::decode(&inputs.encode()).unwrap(), inputs);
+    }
+
+    #[test]
+    fn test_interpolation_between_snapshots() {
+        let id = NetId(1);
+        let states: VecDeque<WorldState> = [(10, 0.0), (12, 20.0), (13, 40.0)]
+            .into_iter()
+            .map(|(tick, x)| WorldState {
+                tick,
+                entities: [(id, state(Vec2::new(x, 0.0)))].into(),
+            })
+            .collect();
+
+        let at = |tick: f32| interpolated_position(&states, id, tick).unwrap().x;
+        assert_eq!(at(11.0), 10.0);
+        assert_eq!(at(12.5), 30.0);
+        assert_eq!(at(5.0), 0.0, "Before the buffer the oldest state is used");
+        assert_eq!(at(20.0), 40.0, "After it the newest");
+        assert!(interpolated_position(&states, NetId(2), 11.0).is_none());
+    }
+
+    #[test]
+    fn test_client_prediction_matches_the_server() {
+        let (mut server, mut client, player, enemy) = server_and_client();
+        for tick in 0..120 {
+            let input = if tick < 60 {
+                RIGHT
+            } else {
+                NetInput::default()
+            };
+            step(&mut server, &mut client, input);
+        }
+        // The snapshot of the last tick confirms every input
+        synchronize_replication_client(&mut client);
+
+        let own = own_player(&client);
+        assert!(position(&client, own).x > -100.0, "The player moved right");
+        assert_eq!(position(&client, own), position(&server, player));
+        let replication = client.resource::<ReplicationClient>();
+        assert_eq!(replication.corrections, 0, "Predictions were right");
+        assert_eq!(replication.pending_inputs(), 0);
+        assert!(server
+            .resource::<ReplicationServer>()
+            .acked_tick(0)
+            .is_some());
+
+        // Every other entity is a replica that is not simulated locally
+        let enemy_id = *server.get::<NetId>(enemy).unwrap();
+        let replica = replication.replica(enemy_id).unwrap();
+        assert!(client.get::<LinearVelocity>(replica).is_none());
+        assert_eq!(client.query::<&Obstacle>().iter(&client).count(), 1);
+    }
+
+    #[test]
+    fn test_remote_entities_are_drawn_in_the_past() {
+        let (mut server, mut client, _, enemy) = server_and_client();
+        for _ in 0..30 {
+            step(&mut server, &mut client, NetInput::default());
+            interpolate_replicas(&mut client, TICK);
+        }
+
+        let replication = client.resource::<ReplicationClient>();
+        let latest = replication.latest_state().unwrap().tick as f32;
+        assert!(replication.render_tick() < latest);
+        assert!(replication.render_tick() >= latest - 2.0 * replication.interpolation_delay);
+
+        let enemy_id = *server.get::<NetId>(enemy).unwrap();
+        let replica = replication.replica(enemy_id).unwrap();
+        let drawn = position(&client, replica);
+        assert!(drawn.y > 0.0, "The replica follows the enemy");
+        assert!(drawn.y < position(&server, enemy).y, "But lags behind it");
+    }
+
+    #[test]
+    fn test_server_corrections_are_reconciled() {
+        let (mut server, mut client, player, enemy) = server_and_client();
+        for _ in 0..20 {
+            step(&mut server, &mut client, RIGHT);
+        }
+
+        // The server moves the player somewhere the client never predicted
+        server.get_mut::<Transform>(player).unwrap().translation.y = 150.0;
+        for _ in 0..5 {
+            step(&mut server, &mut client, RIGHT);
+        }
+        let own = own_player(&client);
+        assert!(client.resource::<ReplicationClient>().corrections >= 1);
+        assert!((position(&client, own).y - 150.0).abs() < 1.0);
+
+        // Entities the server despawns disappear on the client
+        let enemy_id = *server.get::<NetId>(enemy).unwrap();
+        server.despawn(enemy);
+        for _ in 0..2 {
+            step(&mut server, &mut client, RIGHT);
+        }
+        assert!(client
+            .resource::<ReplicationClient>()
+            .replica(enemy_id)
+            .is_none());
+        assert_eq!(client.query::<&Replica>().iter(&client).count(), 2);
+    }
+
+    #[test]
+    fn test_late_snapshots_do_not_drain_predicted_stamina_again() {
+        let (mut server, mut client, player, _) = server_and_client_over(4);
+        let boost = NetInput {
+            buttons: NetInput::BOOST,
+            ..RIGHT
+        };
+        // Until the first snapshot arrives the client has no player to predict
+        for _ in 0..10 {
+            step(&mut server, &mut client, NetInput::default());
+        }
+        for tick in 0..60 {
+            let input = if tick < 40 { boost } else { RIGHT };
+            step(&mut server, &mut client, input);
+        }
+
+        let replication = client.resource::<ReplicationClient>();
+        assert!(replication.pending_inputs() > 0, "Snapshots lag behind");
+        let own = own_player(&client);
+        let predicted = client.get::<Stamina>(own).unwrap().current;
+        let authoritative = server.get::<Stamina>(player).unwrap().current;
+        assert!(authoritative < Stamina::default().max, "The player boosted");
+        assert!((predicted - authoritative).abs() < 1e-3);
+        assert!((position(&client, own).x - position(&server, player).x).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_particles_age_once_per_predicted_tick() {
+        let (mut server, mut client, _, _) = server_and_client_over(4);
+        let style = VisualStyle::new(Color::WHITE, 4.0);
+        let particle = client
+            .spawn((
+                Particle {
+                    velocity: Vec2::ZERO,
+                    age: 0.0,
+                    lifetime: 10.0,
+                    drag: 0.0,
+                    start: style,
+                    end: style,
+                },
+                Transform::default(),
+            ))
+            .id();
+        // Until the first snapshot arrives the client has no player to predict
+        for _ in 0..10 {
+            step(&mut server, &mut client, NetInput::default());
+        }
+        let born = client.get::<Particle>(particle).unwrap().age;
+        for _ in 0..30 {
+            step(&mut server, &mut client, RIGHT);
+        }
+
+        assert!(client.resource::<ReplicationClient>().pending_inputs() > 1);
+        let age = client.get::<Particle>(particle).unwrap().age - born;
+        // Replays of the pending inputs do not count
+        assert!(
+            (age - 30.0 * TICK).abs() < 1e-4,
+            "aged {} ticks",
+            age / TICK
+        );
+    }
+
+    #[test]
+    fn test_replication_arguments() {
+        let args = |line: &str| -> Vec<String> { line.split(' ').map(String::from).collect() };
+
+        let serve = ReplicationConfig::from_args(&args(
+            "level.ron --serve 0.0.0.0:7000 10.0.0.2:7100 10.0.0.3:7100",
+        ))
+        .unwrap();
+        let Some(ReplicationConfig::Serve { local, clients }) = serve else {
+            panic!("expected a server");
+        };
+        assert_eq!(local.port(), 7000);
+        assert_eq!(clients.len(), 2);
+
+        let connect =
+            ReplicationConfig::from_args(&args("--connect 0.0.0.0:7100 10.0.0.1:7000")).unwrap();
+        assert!(matches!(connect, Some(ReplicationConfig::Connect { .. })));
+
+        assert!(ReplicationConfig::from_args(&args("level.ron"))
+            .unwrap()
+            .is_none());
+        assert!(ReplicationConfig::from_args(&args("--serve 0.0.0.0:7000")).is_err());
+        assert!(ReplicationConfig::from_args(&args("--connect 0.0.0.0:7100")).is_err());
+        assert!(ReplicationConfig::from_args(&args("--connect nowhere 10.0.0.1:7000")).is_err());
+    }
+}
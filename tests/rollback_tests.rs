@@ -0,0 +1,451 @@
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy_wasm_game::collectibles::Score;
+use bevy_wasm_game::components::*;
+use bevy_wasm_game::headless::{headless_app, HeadlessConfig};
+use bevy_wasm_game::health::Health;
+use bevy_wasm_game::input::apply_movement_intent;
+use bevy_wasm_game::level::LevelDefinition;
+use bevy_wasm_game::power_ups::PowerUp;
+use bevy_wasm_game::race::RaceProgress;
+use bevy_wasm_game::resources::WorldBounds;
+use bevy_wasm_game::rollback::*;
+use bevy_wasm_game::stamina::*;
+use bevy_wasm_game::systems::*;
+use bevy_wasm_game::transport::*;
+use std::time::Duration;
+
+/// Tests for rollback netplay and its transports
+
+#[cfg(test)]
+mod rollback_tests {
+    use super::*;
+
+    const TICK: f32 = 1.0 / 60.0;
+    const FRAMES: u32 = 120;
+
+    /// World with the movement physics in `FixedUpdate` and one body per player
+    fn peer_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(FixedTime::new_from_secs(TICK));
+        world.init_resource::<WorldBounds>();
+        world.init_resource::<BoostSettings>();
+        world.init_resource::<Events<WallImpact>>();
+        world.init_resource::<Events<StaminaDepleted>>();
+        world.init_resource::<Events<StaminaFull>>();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems(
+            (
+                update_stamina,
+                apply_movement_intent,
+                world_friction,
+                player_physics_integration,
+                boundary_collision,
+            )
+                .chain(),
+        );
+        world.add_schedule(schedule, FixedUpdate);
+
+        for (handle, x) in [(0, -200.0), (1, 200.0)] {
+            world.spawn((
+                Transform::from_xyz(x, 0.0, 0.0),
+                PhysicsBundle::default(),
+                MovementIntent::default(),
+                Stamina::default(),
+                RollbackPlayer(handle),
+            ));
+        }
+        world
+    }
+
+    /// Scripted input that changes often enough to break predictions
+    fn scripted_input(player: u8, frame: u32) -> NetInput {
+        let phase = frame / 7 + player as u32 * 3;
+        NetInput {
+            x: [127, 0, -127, 90][phase as usize % 4],
+            y: [0, 127, 90, -127][(phase / 2) as usize % 4],
+            buttons: if phase.is_multiple_of(5) {
+                NetInput::BOOST
+            } else {
+                0
+            },
+        }
+    }
+
+    fn positions(world: &mut World) -> Vec<(u8, Vec2, Vec2)> {
+        let mut bodies: Vec<_> = world
+            .query::<(&RollbackPlayer, &Transform, &LinearVelocity)>()
+            .iter(world)
+            .map(|(player, transform, velocity)| {
+                (player.0, transform.translation.truncate(), velocity.0)
+            })
+            .collect();
+        bodies.sort_by_key(|(player, ..)| *player);
+        bodies
+    }
+
+    /// The same inputs simulated directly, without any session
+    fn reference_positions(frames: u32, input_delay: u32) -> Vec<(u8, Vec2, Vec2)> {
+        let mut world = peer_world();
+        for frame in 0..frames {
+            let mut players = world.query::<(&RollbackPlayer, &mut MovementIntent)>();
+            for (player, mut intent) in players.iter_mut(&mut world) {
+                *intent = match frame.checked_sub(input_delay) {
+                    Some(read) => scripted_input(player.0, read).intent(),
+                    None => NetInput::default().intent(),
+                };
+            }
+            world.run_schedule(FixedUpdate);
+        }
+        positions(&mut world)
+    }
+
+    /// The duel level with every simulation plugin. Time stands still, only
+    /// the test advances the fixed timestep. Every player starts a race run
+    /// that never finishes, so its clock counts each tick.
+    fn duel_app(session: Option<RollbackSession>) -> App {
+        let mut app = headless_app(&HeadlessConfig {
+            level: Some("assets/levels/duel.ron".into()),
+            seed: Some(5),
+            ticks: Some(u32::MAX),
+            ..default()
+        })
+        .unwrap();
+        RollbackSystems::add_to_app(&mut app);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        if let Some(session) = session {
+            app.insert_resource(session);
+        }
+        app.update();
+        app.update();
+
+        let mut players: Vec<(Entity, f32)> = app
+            .world
+            .query_filtered::<(Entity, &Transform), With<Player>>()
+            .iter(&app.world)
+            .map(|(entity, transform)| (entity, transform.translation.x))
+            .collect();
+        players.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        assert_eq!(players.len(), ROLLBACK_PLAYERS as usize);
+        for (handle, (entity, _)) in players.into_iter().enumerate() {
+            app.world
+                .entity_mut(entity)
+                .insert((RollbackPlayer(handle as u8), RaceProgress::new(1, 1)));
+        }
+        app
+    }
+
+    /// Each player heads for a pickup of the duel level, boosting and firing
+    /// in bursts that keep breaking predictions
+    fn pickup_input(player: u8, frame: u32) -> NetInput {
+        let (x, y) = [(127, 0), (-65, -110)][player as usize];
+        let mut buttons = 0;
+        if (frame / 6 + player as u32).is_multiple_of(2) {
+            buttons |= NetInput::BOOST;
+        }
+        if frame.is_multiple_of(11) {
+            buttons |= NetInput::FIRE;
+        }
+        NetInput { x, y, buttons }
+    }
+
+    /// Player 0 keeps shooting along the middle lane, boosting on and off.
+    /// Player 1 stands in it until long after it died, then keeps changing
+    /// direction.
+    fn duel_input(player: u8, frame: u32) -> NetInput {
+        match player {
+            0 => NetInput {
+                x: 127,
+                y: 0,
+                buttons: match (frame / 4).is_multiple_of(2) {
+                    true => NetInput::FIRE | NetInput::BOOST,
+                    false => NetInput::FIRE,
+                },
+            },
+            _ if frame < 60 => NetInput::default(),
+            _ => scripted_input(player, frame),
+        }
+    }
+
+    /// Both peers of a session on the duel level
+    fn duel_peers() -> [App; 2] {
+        let (transport_a, transport_b) = ChannelTransport::pair();
+        [
+            duel_app(Some(RollbackSession::new(
+                0,
+                DEFAULT_INPUT_DELAY,
+                transport_a,
+            ))),
+            duel_app(Some(RollbackSession::new(
+                1,
+                DEFAULT_INPUT_DELAY,
+                transport_b,
+            ))),
+        ]
+    }
+
+    /// Run both peers for `ticks`, taking turns a few ticks ahead of each
+    /// other so both keep predicting and correcting, with a frame between turns
+    fn play(peers: &mut [App; 2], ticks: u32, input: fn(u8, u32) -> NetInput) {
+        let mut frames = [0u32; 2];
+        while frames.iter().any(|frame| *frame < ticks) {
+            for (player, app) in peers.iter_mut().enumerate() {
+                for _ in 0..5 + 2 * player {
+                    if frames[player] >= ticks {
+                        break;
+                    }
+                    if advance_rollback(&mut app.world, input(player as u8, frames[player])) {
+                        frames[player] += 1;
+                    }
+                }
+                app.update();
+            }
+        }
+        for app in peers.iter_mut() {
+            synchronize_rollback(&mut app.world);
+        }
+    }
+
+    fn handles(world: &mut World) -> Vec<(u8, Entity)> {
+        let mut handles: Vec<(u8, Entity)> = world
+            .query_filtered::<(Entity, &RollbackPlayer), With<Player>>()
+            .iter(world)
+            .map(|(entity, player)| (player.0, entity))
+            .collect();
+        handles.sort();
+        handles
+    }
+
+    #[test]
+    fn test_input_quantization_and_packets_round_trip() {
+        let intent = MovementIntent {
+            direction: Vec2::new(0.6, -0.8),
+            boost: true,
+            mouse_boost: false,
+        };
+        let fire = FireIntent {
+            firing: true,
+            aim_at: Some(Vec2::ONE),
+        };
+        let input = NetInput::from_intent(&intent, Some(&fire));
+        assert_eq!(
+            NetInput::from_intent(&input.intent(), Some(&input.fire())),
+            input
+        );
+        assert!((input.intent().direction - intent.direction).length() < 0.01);
+        assert!(input.intent().boost && input.fire().firing);
+        assert_eq!(input.fire().aim_at, None, "Aim points are not sent");
+
+        let packet = InputPacket {
+            start_frame: 40,
+            ack: 38,
+            inputs: vec![input, NetInput::default()],
+        };
+        let bytes = packet.encode();
+        assert_eq!(InputPacket::decode(&bytes).unwrap(), packet);
+        assert!(InputPacket::decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(InputPacket::decode(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_peers_converge_after_mispredictions() {
+        let (transport_a, transport_b) = ChannelTransport::pair();
+        let mut peers = [peer_world(), peer_world()];
+        peers[0].insert_resource(RollbackSession::new(0, DEFAULT_INPUT_DELAY, transport_a));
+        peers[1].insert_resource(RollbackSession::new(1, DEFAULT_INPUT_DELAY, transport_b));
+
+        // The peers take turns running a few ticks ahead of each other, so
+        // both keep predicting the other's input and correcting it
+        let mut frames = [0u32; 2];
+        while frames.iter().any(|frame| *frame < FRAMES) {
+            for (player, world) in peers.iter_mut().enumerate() {
+                for _ in 0..5 + 2 * player {
+                    if frames[player] >= FRAMES {
+                        break;
+                    }
+                    let input = scripted_input(player as u8, frames[player]);
+                    if advance_rollback(world, input) {
+                        frames[player] += 1;
+                    }
+                }
+            }
+        }
+        for world in peers.iter_mut() {
+            synchronize_rollback(world);
+        }
+
+        let expected = reference_positions(FRAMES, DEFAULT_INPUT_DELAY);
+        for world in peers.iter_mut() {
+            let session = world.resource::<RollbackSession>();
+            assert_eq!(session.frame(), FRAMES);
+            assert!(session.confirmed_frame() >= FRAMES);
+            assert!(session.rollbacks > 0, "Predictions were corrected");
+            assert_eq!(positions(world), expected, "Same result as a local run");
+        }
+        let [a, b] = &mut peers;
+        assert_eq!(
+            RollbackSnapshot::capture(a, FRAMES).checksum(),
+            RollbackSnapshot::capture(b, FRAMES).checksum()
+        );
+    }
+
+    #[test]
+    fn test_peers_agree_on_pickups_score_and_races_with_every_plugin() {
+        const TICKS: u32 = 240;
+        let mut peers = duel_peers();
+        play(&mut peers, TICKS, pickup_input);
+
+        // The same inputs simulated without a session
+        let mut reference = duel_app(None);
+        for frame in 0..TICKS {
+            let mut players =
+                reference
+                    .world
+                    .query::<(&RollbackPlayer, &mut MovementIntent, &mut FireIntent)>();
+            for (player, mut intent, mut fire) in players.iter_mut(&mut reference.world) {
+                let input = frame
+                    .checked_sub(DEFAULT_INPUT_DELAY)
+                    .map_or(NetInput::default(), |read| pickup_input(player.0, read));
+                *intent = input.intent();
+                *fire = input.fire();
+            }
+            reference.world.run_schedule(FixedUpdate);
+            reference.update();
+        }
+        let score = reference.world.resource::<Score>().clone();
+        assert!(score.points > 0, "A collectible was picked up");
+        let power_ups = reference
+            .world
+            .query::<&PowerUp>()
+            .iter(&reference.world)
+            .count();
+        assert!(power_ups < 2, "A power-up was picked up");
+        let expected = RollbackSnapshot::capture(&mut reference.world, TICKS).checksum();
+
+        for app in peers.iter_mut() {
+            assert!(app.world.resource::<RollbackSession>().rollbacks > 0);
+            assert_eq!(*app.world.resource::<Score>(), score);
+            let clocks: Vec<u32> = app
+                .world
+                .query::<&RaceProgress>()
+                .iter(&app.world)
+                .map(|race| race.ticks)
+                .collect();
+            assert_eq!(clocks, vec![TICKS; 2], "Resimulated ticks count once");
+            assert_eq!(
+                RollbackSnapshot::capture(&mut app.world, TICKS).checksum(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_peers_agree_after_a_death_and_respawn() {
+        let mut peers = duel_peers();
+        let mut victims = Vec::new();
+        for app in peers.iter_mut() {
+            // One shot kills player 1
+            let victim = handles(&mut app.world)[1].1;
+            app.world.get_mut::<Health>(victim).unwrap().current = 10.0;
+            victims.push(victim);
+        }
+        play(&mut peers, 300, duel_input);
+
+        for (app, victim) in peers.iter_mut().zip(victims) {
+            let handles = handles(&mut app.world);
+            assert_eq!(handles.len(), 2, "The respawned player has its handle");
+            assert_ne!(handles[1].1, victim, "Player 1 died and came back");
+        }
+        let [a, b] = &mut peers;
+        assert_eq!(
+            RollbackSnapshot::capture(&mut a.world, 300).checksum(),
+            RollbackSnapshot::capture(&mut b.world, 300).checksum()
+        );
+    }
+
+    #[test]
+    fn test_session_stalls_without_remote_inputs() {
+        let (transport, _silent_peer) = ChannelTransport::pair();
+        let mut world = peer_world();
+        world.insert_resource(RollbackSession::new(0, DEFAULT_INPUT_DELAY, transport));
+
+        let advanced = (0..50)
+            .filter(|_| advance_rollback(&mut world, NetInput::default()))
+            .count() as u32;
+        let session = world.resource::<RollbackSession>();
+        assert_eq!(advanced, session.confirmed_frame() + session.max_prediction);
+        assert!(!session.can_advance());
+    }
+
+    #[test]
+    fn test_restoring_a_snapshot_rewinds_bodies() {
+        let mut world = peer_world();
+        let snapshot = RollbackSnapshot::capture(&mut world, 0);
+        let mut players = world.query::<&mut MovementIntent>();
+        for mut intent in players.iter_mut(&mut world) {
+            intent.direction = Vec2::X;
+        }
+        for _ in 0..10 {
+            world.run_schedule(FixedUpdate);
+        }
+        assert_ne!(
+            RollbackSnapshot::capture(&mut world, 10).checksum(),
+            snapshot.checksum()
+        );
+
+        snapshot.restore(&mut world);
+        assert_eq!(
+            RollbackSnapshot::capture(&mut world, 0).checksum(),
+            snapshot.checksum()
+        );
+    }
+
+    #[test]
+    fn test_udp_transport_over_localhost() {
+        let mut a = UdpTransport::bind("127.0.0.1:0").unwrap();
+        let mut b = UdpTransport::bind("127.0.0.1:0").unwrap();
+        a.connect(b.local_addr().unwrap()).unwrap();
+        b.connect(a.local_addr().unwrap()).unwrap();
+
+        a.send(b"ping").unwrap();
+        let mut received = Vec::new();
+        for _ in 0..100 {
+            received.extend(b.receive());
+            if !received.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(received, vec![b"ping".to_vec()]);
+    }
+
+    #[test]
+    fn test_netplay_arguments() {
+        let args: Vec<String> = [
+            "level.ron",
+            "--netplay",
+            "127.0.0.1:7000",
+            "127.0.0.1:7001",
+            "1",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let config = NetplayConfig::from_args(&args).unwrap().unwrap();
+        assert_eq!(config.player, 1);
+        assert_eq!(config.peer.port(), 7001);
+
+        assert!(NetplayConfig::from_args(&args[..1]).unwrap().is_none());
+        assert!(NetplayConfig::from_args(&args[..4]).is_err());
+        let mut bad_player = args.clone();
+        bad_player[4] = "2".into();
+        assert!(NetplayConfig::from_args(&bad_player).is_err());
+    }
+
+    #[test]
+    fn test_duel_level_has_a_spawn_point_per_peer() {
+        let duel = LevelDefinition::load("assets/levels/duel.ron").unwrap();
+        assert_eq!(duel.spawn_points.len(), ROLLBACK_PLAYERS as usize);
+    }
+}
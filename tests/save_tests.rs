@@ -0,0 +1,265 @@
+use bevy::prelude::*;
+use bevy_wasm_game::collectibles::*;
+use bevy_wasm_game::components::*;
+use bevy_wasm_game::enemy::*;
+use bevy_wasm_game::entity_relations::{ChildOf, Children, GroupMember, Owner, Parent, Target};
+use bevy_wasm_game::game_state::*;
+use bevy_wasm_game::health::Health;
+use bevy_wasm_game::input::{ControlledBy, KeyboardScheme};
+use bevy_wasm_game::power_ups::{PowerUp, PowerUpKind};
+use bevy_wasm_game::race::Checkpoint;
+use bevy_wasm_game::resources::*;
+use bevy_wasm_game::save::*;
+
+/// Tests for save snapshots, entity remapping and version migration
+
+#[cfg(test)]
+mod save_tests {
+    use super::*;
+
+    struct SavedWorld {
+        world: World,
+        player: Entity,
+    }
+
+    fn create_session() -> SavedWorld {
+        let mut world = World::new();
+        world.insert_resource(WorldBounds::from_window_size(800.0, 600.0, 0.0));
+        world.insert_resource(Score {
+            points: 120,
+            combo: 2,
+            best_combo: 4,
+            combo_time_left: 0.5,
+        });
+
+        let player = world
+            .spawn((
+                Transform::from_xyz(10.0, 20.0, 0.0),
+                Player,
+                PhysicsBundle::new(Vec2::new(30.0, -40.0), 0.9),
+                GameplayEntity,
+            ))
+            .id();
+        world.spawn((
+            Transform::from_xyz(100.0, 0.0, 0.0),
+            Enemy,
+            Steering::new(SteeringBehavior::Pursue),
+            PhysicsBundle::default(),
+            Target(player),
+            GameplayEntity,
+        ));
+        let shield = world
+            .spawn((
+                Transform::from_xyz(12.0, 20.0, 0.0),
+                Owner(player),
+                ChildOf(player),
+                GroupMember {
+                    group_id: 7,
+                    role: "shield".into(),
+                },
+                GameplayEntity,
+            ))
+            .id();
+        world
+            .entity_mut(player)
+            .insert((Children(vec![shield]), Parent));
+
+        SavedWorld { world, player }
+    }
+
+    fn round_trip(save: &SaveFile) -> SaveFile {
+        let source = save.to_ron().unwrap();
+        SaveFile::from_ron(&source, "test.ron", &SaveMigrations::default()).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_into_fresh_world() {
+        let SavedWorld { mut world, player } = create_session();
+        let save = capture_snapshot(&mut world);
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.entities.len(), 3);
+
+        let loaded = round_trip(&save);
+        assert_eq!(loaded, save);
+
+        let mut fresh = World::new();
+        // Push the entity ids apart so a missing remap would be noticed
+        for _ in 0..10 {
+            fresh.spawn_empty();
+        }
+        let restored = restore_snapshot(&mut fresh, &loaded);
+        assert!(restored.dropped_references.is_empty());
+
+        let new_player = restored.entities[&player.to_bits()];
+        assert_ne!(new_player, player);
+        assert_eq!(
+            fresh.get::<LinearVelocity>(new_player).unwrap().0,
+            Vec2::new(30.0, -40.0)
+        );
+        assert_eq!(fresh.get::<Friction>(new_player).unwrap().0, 0.9);
+        assert_eq!(
+            fresh.get::<Transform>(new_player).unwrap().translation,
+            Vec3::new(10.0, 20.0, 0.0)
+        );
+        assert_eq!(fresh.resource::<Score>().points, 120);
+        assert_eq!(fresh.resource::<WorldBounds>().max, Vec2::new(400.0, 300.0));
+
+        let mut enemies = fresh.query_filtered::<(&Target, &Steering), With<Enemy>>();
+        let (target, steering) = enemies.single(&fresh);
+        assert_eq!(target.0, new_player);
+        assert_eq!(steering.behavior, SteeringBehavior::Pursue);
+
+        let mut owned = fresh.query::<(Entity, &Owner, &ChildOf, &GroupMember)>();
+        let (shield, owner, child_of, member) = owned.single(&fresh);
+        assert_eq!(owner.0, new_player);
+        assert_eq!(child_of.0, new_player);
+        assert_eq!(fresh.get::<Parent>(new_player), Some(&Parent));
+        assert_eq!(member.group_id, 7);
+        assert_eq!(fresh.get::<Children>(new_player).unwrap().0, vec![shield]);
+    }
+
+    #[test]
+    fn test_references_outside_the_save_are_dropped() {
+        let SavedWorld { mut world, .. } = create_session();
+        // Not a gameplay entity, so it is not saved
+        let outsider = world.spawn(Transform::default()).id();
+        let follower = world
+            .spawn((Transform::default(), Target(outsider), GameplayEntity))
+            .id();
+
+        let save = capture_snapshot(&mut world);
+        let mut fresh = World::new();
+        let restored = restore_snapshot(&mut fresh, &save);
+
+        assert_eq!(
+            restored.dropped_references,
+            vec![(follower.to_bits(), outsider.to_bits())]
+        );
+        let new_follower = restored.entities[&follower.to_bits()];
+        assert!(fresh.get::<Target>(new_follower).is_none());
+    }
+
+    #[test]
+    fn test_pickups_checkpoints_health_and_bindings_survive_a_load() {
+        let SavedWorld { mut world, player } = create_session();
+        world.entity_mut(player).insert((
+            Health {
+                current: 35.0,
+                ..Health::new(100.0)
+            },
+            ControlledBy::keyboard(KeyboardScheme::B),
+        ));
+        world.spawn((
+            Transform::from_xyz(-50.0, 0.0, 0.0),
+            PowerUp::new(PowerUpKind::LowFriction),
+            GameplayEntity,
+        ));
+        world.spawn((
+            Transform::from_xyz(0.0, 80.0, -1.0),
+            Checkpoint {
+                index: 2,
+                radius: 40.0,
+            },
+            GameplayEntity,
+        ));
+
+        let save = capture_snapshot(&mut world);
+        let loaded = round_trip(&save);
+        assert_eq!(loaded, save);
+        let kinds: Vec<&SavedKind> = loaded.entities.iter().map(|e| &e.kind).collect();
+        assert!(kinds.contains(&&SavedKind::PowerUp(PowerUpKind::LowFriction)));
+        assert!(kinds.contains(&&SavedKind::Checkpoint {
+            index: 2,
+            radius: 40.0
+        }));
+
+        let mut fresh = World::new();
+        let restored = restore_snapshot(&mut fresh, &loaded);
+        let new_player = restored.entities[&player.to_bits()];
+        assert_eq!(fresh.get::<Health>(new_player).unwrap().current, 35.0);
+        assert_eq!(
+            fresh.get::<ControlledBy>(new_player),
+            Some(&ControlledBy::keyboard(KeyboardScheme::B))
+        );
+
+        let power_up = fresh.query::<&PowerUp>().single(&fresh);
+        assert_eq!(power_up.kind, PowerUpKind::LowFriction);
+        let (checkpoint, transform) = fresh.query::<(&Checkpoint, &Transform)>().single(&fresh);
+        assert_eq!((checkpoint.index, checkpoint.radius), (2, 40.0));
+        assert_eq!(transform.translation, Vec3::new(0.0, 80.0, -1.0));
+    }
+
+    #[test]
+    fn test_version_1_saves_still_load() {
+        let SavedWorld { mut world, .. } = create_session();
+        let current = capture_snapshot(&mut world).to_ron().unwrap();
+        // Version 1 also stored a `Parent` reference on children, now unused
+        let version_1 = current
+            .replacen(&format!("version: {}", SAVE_VERSION), "version: 1", 1)
+            .replacen(
+                "child_of:",
+                "parent: Some(4294967296),\n            child_of:",
+                1,
+            );
+        assert!(version_1.contains("version: 1") && version_1.contains("parent:"));
+
+        let migrated =
+            SaveFile::from_ron(&version_1, "v1.ron", &SaveMigrations::default()).unwrap();
+        assert_eq!(migrated.version, SAVE_VERSION);
+        assert_eq!(migrated, capture_snapshot(&mut world));
+    }
+
+    #[test]
+    fn test_bounds_that_add_speed_are_rejected() {
+        let SavedWorld { mut world, .. } = create_session();
+        let mut save = capture_snapshot(&mut world);
+        save.bounds.bounce_factor = 2.0;
+        let source = save.to_ron().unwrap();
+
+        let error =
+            SaveFile::from_ron(&source, "bouncy.ron", &SaveMigrations::default()).unwrap_err();
+        assert!(matches!(
+            &error,
+            SaveError::Invalid { field, .. } if field == "bounds.bounce_factor"
+        ));
+        assert!(error.to_string().contains("bouncy.ron"));
+    }
+
+    fn migrate_v0(source: &str) -> Result<SaveFile, String> {
+        #[derive(serde::Deserialize)]
+        struct LegacySave {
+            points: u32,
+        }
+        let legacy: LegacySave = ron::from_str(source).map_err(|error| error.to_string())?;
+        Ok(SaveFile {
+            version: 0,
+            bounds: WorldBounds::default_bounds(),
+            score: Score {
+                points: legacy.points,
+                ..default()
+            },
+            entities: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_versions_and_migrations() {
+        let legacy = "(version: 0, points: 42)";
+        let mut migrations = SaveMigrations::default();
+
+        let error = SaveFile::from_ron(legacy, "old.ron", &migrations).unwrap_err();
+        assert!(matches!(
+            error,
+            SaveError::UnsupportedVersion { found: 0, .. }
+        ));
+
+        migrations.register(0, migrate_v0);
+        let migrated = SaveFile::from_ron(legacy, "old.ron", &migrations).unwrap();
+        assert_eq!(migrated.version, SAVE_VERSION);
+        assert_eq!(migrated.score.points, 42);
+
+        let future = format!("(version: {}, points: 1)", SAVE_VERSION + 1);
+        let error = SaveFile::from_ron(&future, "new.ron", &migrations).unwrap_err();
+        assert!(error.to_string().contains("new.ron"));
+    }
+}
@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+use bevy_wasm_game::bots::*;
+use bevy_wasm_game::headless::*;
+use bevy_wasm_game::replay::*;
+use bevy_wasm_game::resources::WorldBounds;
+use bevy_wasm_game::soak::*;
+
+/// Tests for the autoplay bots and the invariant checking soak runs
+
+#[cfg(test)]
+mod soak_tests {
+    use super::*;
+
+    fn soak(config: HeadlessConfig) -> (App, HeadlessSummary) {
+        let mut app = headless_app(&HeadlessConfig {
+            soak: true,
+            ..config
+        })
+        .unwrap();
+        let summary = run_headless(&mut app).unwrap();
+        (app, summary)
+    }
+
+    /// Soak run with tighter budgets than the defaults
+    fn soak_within(config: HeadlessConfig, budgets: SoakBudgets) -> (App, HeadlessSummary) {
+        let mut app = headless_app(&HeadlessConfig {
+            soak: true,
+            ..config
+        })
+        .unwrap();
+        app.world.resource_mut::<SoakMonitor>().budgets = budgets;
+        let summary = run_headless(&mut app).unwrap();
+        (app, summary)
+    }
+
+    #[test]
+    fn test_bot_behaviors() {
+        let bounds = WorldBounds {
+            min: Vec2::splat(-100.0),
+            max: Vec2::splat(100.0),
+            ..default()
+        };
+        let pickups = [Vec2::new(50.0, 0.0), Vec2::new(-20.0, -20.0)];
+        let senses = BotSenses {
+            position: Vec2::new(-50.0, 0.0),
+            pickups: &pickups,
+            bounds: &bounds,
+        };
+
+        let mut chaser = Bot::new(BotBehavior::ChasePickups, 1);
+        let intent = chaser.decide(&senses);
+        assert!(intent
+            .direction
+            .abs_diff_eq(Vec2::new(30.0, -20.0).normalize(), 1e-5));
+        assert!(!intent.boost, "The nearest pickup is not worth a boost");
+        let alone = BotSenses {
+            pickups: &[],
+            ..senses
+        };
+        assert_eq!(chaser.decide(&alone).direction.length(), 1.0);
+
+        let mut hugger = Bot::new(BotBehavior::WallHug, 1);
+        let inside = BotSenses {
+            position: Vec2::new(-20.0, 10.0),
+            ..senses
+        };
+        assert_eq!(hugger.decide(&inside).direction, Vec2::NEG_X);
+        let at_wall = BotSenses {
+            position: Vec2::new(-90.0, 0.0),
+            ..senses
+        };
+        let along = hugger.decide(&at_wall).direction;
+        assert!(
+            along.y < 0.0 && along.x < 0.0,
+            "Follows the wall, leaning in"
+        );
+
+        assert_eq!("wall-hug".parse(), Ok(BotBehavior::WallHug));
+        assert_eq!("chase-pickups".parse(), Ok(BotBehavior::ChasePickups));
+    }
+
+    #[test]
+    fn test_long_bot_runs_keep_the_invariants() {
+        let (mut app, summary) = soak(HeadlessConfig {
+            level: Some("assets/levels/duel.ron".into()),
+            ticks: Some(3000),
+            seed: Some(7),
+            bot: Some(BotBehavior::ChasePickups),
+            ..default()
+        });
+        let monitor = app.world.resource::<SoakMonitor>();
+        assert!(!monitor.failed(), "{:?}", monitor.violations);
+        assert_eq!(summary.ticks, 3000);
+        assert!(summary.score > 0, "The bots picked things up");
+
+        let recorder = app.world.remove_resource::<InputRecorder>().unwrap();
+        assert_eq!(recorder.0.seed, 7);
+        assert!(recorder.0.inputs.iter().any(|entry| entry.slot == 1));
+    }
+
+    #[test]
+    fn test_every_bot_keeps_the_shipped_arena_sound() {
+        for bot in [
+            BotBehavior::RandomWalk,
+            BotBehavior::ChasePickups,
+            BotBehavior::WallHug,
+        ] {
+            let (app, summary) = soak(HeadlessConfig {
+                ticks: Some(2000),
+                seed: Some(7),
+                bot: Some(bot),
+                ..default()
+            });
+            let monitor = app.world.resource::<SoakMonitor>();
+            assert!(!monitor.failed(), "{:?}: {:?}", bot, monitor.violations);
+            assert_eq!(summary.ticks, 2000);
+        }
+    }
+
+    #[test]
+    fn test_violations_produce_a_replayable_report() {
+        let path = std::env::temp_dir().join("soak_tests_report.ron");
+        // The arena alone is over a budget of one entity
+        let budgets = SoakBudgets {
+            entities: 1,
+            ..default()
+        };
+        let (app, summary) = soak_within(
+            HeadlessConfig {
+                ticks: Some(3000),
+                seed: Some(7),
+                bot: Some(BotBehavior::RandomWalk),
+                report: Some(path.clone()),
+                ..default()
+            },
+            budgets,
+        );
+        let monitor = app.world.resource::<SoakMonitor>();
+        assert!(monitor.failed() && monitor.reported);
+        assert_eq!(monitor.violations[0].invariant, Invariant::EntityBudget);
+        let first = monitor.violations[0].clone();
+        assert_eq!(
+            summary.ticks,
+            first.tick + 1,
+            "The run stops at the violation"
+        );
+
+        let report = SoakReport::read(&path).unwrap();
+        assert_eq!(report.violations[0], first);
+        assert_eq!(report.ticks, summary.ticks);
+        assert_eq!(report, SoakReport::capture(&app.world));
+        assert_eq!(read_replay(&path).unwrap(), report.replay);
+
+        // Without bots the recorded inputs take the same course
+        let replay = HeadlessConfig {
+            replay: Some(path.clone()),
+            report: Some(std::env::temp_dir().join("soak_tests_replayed.ron")),
+            ticks: Some(3000),
+            ..default()
+        };
+        let (mut replayed, replayed_summary) = soak_within(replay.clone(), budgets);
+        std::fs::remove_file(&path).ok();
+        std::fs::remove_file(replay.report.unwrap()).ok();
+        assert_eq!(replayed_summary, summary);
+        assert_eq!(
+            replayed.world.resource::<SoakMonitor>().violations[0],
+            first
+        );
+        assert!(replayed
+            .world
+            .query::<&Bot>()
+            .iter(&replayed.world)
+            .next()
+            .is_none());
+    }
+}
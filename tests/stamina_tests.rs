@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+use bevy_wasm_game::components::*;
+use bevy_wasm_game::input::*;
+use bevy_wasm_game::stamina::*;
+
+/// Tests for boost stamina and boost stacking rules
+
+#[cfg(test)]
+mod stamina_tests {
+    use super::*;
+
+    const TICK: f32 = 1.0 / 60.0;
+
+    fn boosting_intent() -> MovementIntent {
+        MovementIntent {
+            direction: Vec2::X,
+            boost: true,
+            mouse_boost: false,
+        }
+    }
+
+    #[test]
+    fn test_stacking_rules() {
+        let both = MovementIntent {
+            direction: Vec2::X,
+            boost: true,
+            mouse_boost: true,
+        };
+        // Button and mouse boost held together multiply by default, as before
+        let mut settings = BoostSettings::default();
+        assert_eq!(settings.stacking, BoostStacking::Multiply);
+
+        assert_eq!(settings.multiplier(&both), 9.0);
+        settings.stacking = BoostStacking::Add;
+        assert_eq!(settings.multiplier(&both), 5.0);
+        settings.stacking = BoostStacking::Strongest;
+        assert_eq!(settings.multiplier(&both), 3.0);
+
+        assert_eq!(settings.multiplier(&MovementIntent::default()), 1.0);
+    }
+
+    #[test]
+    fn test_drain_deplete_and_recharge() {
+        let mut stamina = Stamina::default();
+        let mut depleted_at = None;
+
+        for tick in 0..600 {
+            if let Some(StaminaChange::Depleted) = stamina.tick(1, TICK) {
+                depleted_at = Some(tick);
+                break;
+            }
+        }
+        // 100 stamina at 40 per second lasts 2.5 seconds, 150 ticks
+        let depleted_at = depleted_at.expect("Stamina should run out");
+        assert!(
+            (148..=150).contains(&depleted_at),
+            "Ran out at tick {}",
+            depleted_at
+        );
+        assert!(!stamina.can_boost(), "Empty stamina must block boost");
+
+        // Holding boost while exhausted neither boosts nor recharges the delay
+        stamina.tick(1, TICK);
+        assert!(!stamina.boosting);
+
+        // Recharge waits for the delay, then refills to full exactly once
+        let mut full_events = 0;
+        for _ in 0..(10.0 / TICK) as usize {
+            if stamina.tick(0, TICK) == Some(StaminaChange::Full) {
+                full_events += 1;
+            }
+        }
+        assert_eq!(full_events, 1);
+        assert_eq!(stamina.current, stamina.max);
+        assert!(stamina.can_boost());
+    }
+
+    #[test]
+    fn test_exhausted_until_reboost_fraction() {
+        let mut stamina = Stamina {
+            current: 0.0,
+            exhausted: true,
+            idle_time: 10.0,
+            ..default()
+        };
+
+        stamina.tick(0, 0.5);
+        assert!(stamina.fraction() < stamina.reboost_fraction);
+        assert!(
+            !stamina.can_boost(),
+            "Still locked below the reboost fraction"
+        );
+
+        stamina.tick(0, 1.0);
+        assert!(stamina.can_boost());
+    }
+
+    #[test]
+    fn test_empty_stamina_blocks_boost_force() {
+        let mut world = World::new();
+        world.insert_resource(FixedTime::new_from_secs(TICK));
+        world.init_resource::<BoostSettings>();
+        world.init_resource::<Events<StaminaDepleted>>();
+        world.init_resource::<Events<StaminaFull>>();
+
+        let fresh = world
+            .spawn((Acceleration::zero(), boosting_intent(), Stamina::default()))
+            .id();
+        let empty = world
+            .spawn((
+                Acceleration::zero(),
+                boosting_intent(),
+                Stamina {
+                    current: 0.0,
+                    exhausted: true,
+                    ..default()
+                },
+            ))
+            .id();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems((update_stamina, apply_movement_intent).chain());
+        schedule.run(&mut world);
+
+        let boosted = world.get::<Acceleration>(fresh).unwrap().0;
+        let blocked = world.get::<Acceleration>(empty).unwrap().0;
+        assert_eq!(boosted, Vec2::X * BASE_INPUT_FORCE * 3.0);
+        assert_eq!(blocked, Vec2::X * BASE_INPUT_FORCE);
+        assert!(world.get::<Stamina>(fresh).unwrap().current < 100.0);
+    }
+
+    #[test]
+    fn test_depleted_event_fires() {
+        let mut world = World::new();
+        world.insert_resource(FixedTime::new_from_secs(TICK));
+        world.init_resource::<Events<StaminaDepleted>>();
+        world.init_resource::<Events<StaminaFull>>();
+
+        let entity = world
+            .spawn((
+                boosting_intent(),
+                Stamina {
+                    current: 0.1,
+                    ..default()
+                },
+            ))
+            .id();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems(update_stamina);
+        schedule.run(&mut world);
+
+        let events = world.resource::<Events<StaminaDepleted>>();
+        let mut reader = events.get_reader();
+        let fired: Vec<_> = reader.iter(events).map(|event| event.entity).collect();
+        assert_eq!(fired, vec![entity]);
+    }
+}
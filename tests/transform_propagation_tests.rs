@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use bevy_wasm_game::bots::BotBehavior;
+use bevy_wasm_game::components::Player;
+use bevy_wasm_game::entity_relations::{attach_child, detach_child};
+use bevy_wasm_game::headless::*;
+use bevy_wasm_game::transform_propagation::*;
+use std::f32::consts::FRAC_PI_2;
+
+/// Tests for placing attached entities relative to their parents
+
+#[cfg(test)]
+mod transform_propagation_tests {
+    use super::*;
+
+    fn propagate(world: &mut World) {
+        let mut schedule = Schedule::new();
+        schedule.add_systems(propagate_local_transforms);
+        schedule.run(world);
+    }
+
+    fn position(world: &World, entity: Entity) -> Vec3 {
+        world.get::<Transform>(entity).unwrap().translation
+    }
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        a.abs_diff_eq(b, 1e-3)
+    }
+
+    #[test]
+    fn test_children_follow_position_and_rotation() {
+        let mut world = World::new();
+        let ship = world.spawn(Transform::from_xyz(100.0, 50.0, 0.0)).id();
+        let turret = world
+            .spawn((
+                Transform::default(),
+                LocalTransform(Transform::from_xyz(20.0, 0.0, 1.0)),
+            ))
+            .id();
+        let barrel = world
+            .spawn((
+                Transform::default(),
+                LocalTransform(Transform::from_xyz(10.0, 0.0, 0.0)),
+            ))
+            .id();
+        attach_child(&mut world, ship, turret);
+        attach_child(&mut world, turret, barrel);
+
+        propagate(&mut world);
+        assert_eq!(position(&world, turret), Vec3::new(120.0, 50.0, 1.0));
+        assert_eq!(position(&world, barrel), Vec3::new(130.0, 50.0, 1.0));
+
+        // The ship turns a quarter left and moves, everything swings along
+        let mut transform = world.get_mut::<Transform>(ship).unwrap();
+        transform.translation = Vec3::new(0.0, 0.0, 0.0);
+        transform.rotation = Quat::from_rotation_z(FRAC_PI_2);
+        propagate(&mut world);
+        assert!(close(position(&world, turret), Vec3::new(0.0, 20.0, 1.0)));
+        assert!(close(position(&world, barrel), Vec3::new(0.0, 30.0, 1.0)));
+        let rotation = world.get::<Transform>(barrel).unwrap().rotation;
+        assert!(rotation.abs_diff_eq(Quat::from_rotation_z(FRAC_PI_2), 1e-5));
+    }
+
+    #[test]
+    fn test_only_attached_children_with_an_offset_move() {
+        let mut world = World::new();
+        let ship = world.spawn(Transform::from_xyz(100.0, 0.0, 0.0)).id();
+        let free = world.spawn(Transform::from_xyz(-5.0, 0.0, 0.0)).id();
+        let shield = world
+            .spawn((
+                Transform::default(),
+                LocalTransform(Transform::from_xyz(0.0, 30.0, 0.0)),
+            ))
+            .id();
+        attach_child(&mut world, ship, free);
+        attach_child(&mut world, ship, shield);
+        propagate(&mut world);
+        assert_eq!(position(&world, free), Vec3::new(-5.0, 0.0, 0.0));
+        assert_eq!(position(&world, shield), Vec3::new(100.0, 30.0, 0.0));
+
+        // Detached, the shield stays where it was left
+        detach_child(&mut world, shield);
+        world.get_mut::<Transform>(ship).unwrap().translation.x = 0.0;
+        propagate(&mut world);
+        assert_eq!(position(&world, shield), Vec3::new(100.0, 30.0, 0.0));
+    }
+
+    #[test]
+    fn test_offsets_between_transforms() {
+        let parent = Transform::from_xyz(10.0, 10.0, 0.0).with_rotation(Quat::from_rotation_z(0.7));
+        let child = Transform::from_xyz(-30.0, 45.0, 2.0).with_rotation(Quat::from_rotation_z(1.2));
+        let local = LocalTransform::between(&parent, &child);
+        let world = local.world(&parent);
+        assert!(close(world.translation, child.translation));
+        assert!(world.rotation.abs_diff_eq(child.rotation, 1e-5));
+        assert!(local
+            .0
+            .rotation
+            .abs_diff_eq(Quat::from_rotation_z(0.5), 1e-5));
+    }
+
+    #[test]
+    fn test_attached_items_follow_a_simulated_player() {
+        let mut app = headless_app(&HeadlessConfig {
+            ticks: Some(120),
+            seed: Some(3),
+            bot: Some(BotBehavior::RandomWalk),
+            ..default()
+        })
+        .unwrap();
+        app.update();
+        let player = app
+            .world
+            .query_filtered::<Entity, With<Player>>()
+            .single(&app.world);
+        let shield = app
+            .world
+            .spawn((
+                Transform::default(),
+                LocalTransform(Transform::from_xyz(0.0, 40.0, 0.0)),
+            ))
+            .id();
+        attach_child(&mut app.world, player, shield);
+
+        run_headless(&mut app).unwrap();
+        let player = position(&app.world, player);
+        assert_ne!(player, Vec3::ZERO, "The bot moved the player");
+        assert_eq!(
+            position(&app.world, shield),
+            player + Vec3::new(0.0, 40.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use bevy_wasm_game::visuals::*;
+
+/// Tests for the visual state layer
+/// Priorities decide which state is shown, timed states expire, looks blend over time
+
+#[cfg(test)]
+mod visuals_tests {
+    use super::*;
+
+    const BASE: Color = Color::rgb(0.25, 0.25, 0.75);
+
+    #[test]
+    fn test_highest_priority_state_wins() {
+        let mut feedback = VisualFeedback::new(BASE);
+        assert_eq!(feedback.dominant(), VisualState::Idle);
+
+        feedback.set(VisualState::Boosting, true);
+        assert_eq!(feedback.dominant(), VisualState::Boosting);
+
+        feedback.trigger(VisualState::Hurt, 0.2);
+        assert_eq!(
+            feedback.dominant(),
+            VisualState::Hurt,
+            "Hurt outranks boosting"
+        );
+
+        feedback.set(VisualState::Boosting, false);
+        assert!(!feedback.is_active(VisualState::Boosting));
+        assert!(feedback.is_active(VisualState::Hurt));
+    }
+
+    #[test]
+    fn test_timed_state_expires() {
+        let palette = VisualPalette::default();
+        let mut feedback = VisualFeedback::new(BASE);
+        feedback.trigger(VisualState::Hurt, 0.25);
+        // A shorter retrigger must not cut the flash short
+        feedback.trigger(VisualState::Hurt, 0.1);
+
+        feedback.advance(&palette, 0.2);
+        assert!(feedback.is_active(VisualState::Hurt));
+
+        feedback.advance(&palette, 0.1);
+        assert!(!feedback.is_active(VisualState::Hurt));
+        assert_eq!(feedback.dominant(), VisualState::Idle);
+    }
+
+    #[test]
+    fn test_look_blends_toward_target() {
+        let palette = VisualPalette::default();
+        let mut feedback = VisualFeedback::new(BASE);
+        feedback.blend_time = 0.1;
+        feedback.set(VisualState::Boosting, true);
+
+        let halfway = feedback.advance(&palette, 0.05);
+        assert!((halfway.scale - (1.0 + palette.boosting.scale) / 2.0).abs() < 1e-5);
+
+        // Done once blend_time has passed, however the frames were sliced
+        for _ in 0..6 {
+            feedback.advance(&palette, 0.01);
+        }
+        assert_eq!(feedback.current(), palette.boosting);
+    }
+
+    #[test]
+    fn test_blend_does_not_depend_on_frame_rate() {
+        let palette = VisualPalette::default();
+        let mut coarse = VisualFeedback::new(BASE);
+        let mut fine = VisualFeedback::new(BASE);
+        coarse.set(VisualState::Boosting, true);
+        fine.set(VisualState::Boosting, true);
+
+        let coarse_look = coarse.advance(&palette, 0.04);
+        let mut fine_look = fine.current();
+        for _ in 0..4 {
+            fine_look = fine.advance(&palette, 0.01);
+        }
+        assert!((coarse_look.scale - fine_look.scale).abs() < 1e-5);
+        assert!(coarse_look.scale < palette.boosting.scale);
+    }
+
+    #[test]
+    fn test_system_writes_sprite_and_scale() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<VisualPalette>();
+
+        let mut feedback = VisualFeedback::new(BASE);
+        feedback.blend_time = 0.0;
+        feedback.set(VisualState::Boosting, true);
+        let entity = world
+            .spawn((feedback, Sprite::default(), Transform::default()))
+            .id();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems(apply_visual_feedback);
+        schedule.run(&mut world);
+
+        let palette = VisualPalette::default();
+        assert_eq!(
+            world.get::<Sprite>(entity).unwrap().color,
+            palette.boosting.color
+        );
+        assert_eq!(
+            world.get::<Transform>(entity).unwrap().scale,
+            Vec3::new(palette.boosting.scale, palette.boosting.scale, 1.0)
+        );
+    }
+}